@@ -0,0 +1,63 @@
+//! 单条消息的重试次数预算
+//!
+//! 处理一条消息时发送、下载等各处的重试共享同一个预算，
+//! 预算用尽后不再重试，避免上游持续出错时嵌套重试耗尽资源。
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// 处理单条消息时允许的重试总次数
+pub const DEFAULT_ATTEMPT_BUDGET: u32 = 8;
+
+/// 重试次数预算，克隆后共享同一计数
+#[derive(Debug, Clone)]
+pub struct AttemptBudget {
+    remaining: Arc<AtomicU32>,
+}
+
+impl AttemptBudget {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            remaining: Arc::new(AtomicU32::new(max_attempts)),
+        }
+    }
+
+    /// 消耗一次重试机会，预算已用尽时返回 false
+    pub fn try_acquire(&self) -> bool {
+        self.remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+    }
+
+    /// 剩余的重试次数
+    pub fn remaining(&self) -> u32 {
+        self.remaining.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for AttemptBudget {
+    fn default() -> Self {
+        Self::new(DEFAULT_ATTEMPT_BUDGET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attempt_budget() {
+        let budget = AttemptBudget::new(2);
+        let shared = budget.clone();
+        assert!(budget.try_acquire());
+        // 克隆共享同一计数
+        assert!(shared.try_acquire());
+        assert_eq!(budget.remaining(), 0);
+        assert!(!budget.try_acquire());
+        assert!(!shared.try_acquire());
+        assert_eq!(shared.remaining(), 0);
+
+        assert!(!AttemptBudget::new(0).try_acquire());
+        assert_eq!(AttemptBudget::default().remaining(), DEFAULT_ATTEMPT_BUDGET);
+    }
+}
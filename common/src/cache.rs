@@ -0,0 +1,248 @@
+//! 带有效期的通用缓存
+//!
+//! 用于缓存处理器的网络请求结果，条目超过有效期后视为不存在，
+//! 过期条目在读取时或通过 [`TtlCache::sweep_expired`] 移除。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 缓存的命中统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// 带有效期的键值缓存，可在多个任务间共享
+#[derive(Debug)]
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (V, Instant)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    /// 创建有效期为 `ttl` 的空缓存
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// 缓存条目的有效期
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// 获取未过期的缓存值，过期的条目会被移除
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_at(key, Instant::now())
+    }
+
+    fn get_at(&self, key: &K, now: Instant) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        let value = match entries.get(key) {
+            Some((value, inserted)) if now.duration_since(*inserted) < self.ttl => {
+                Some(value.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// 写入缓存，已有的条目会被覆盖并重新计算有效期
+    pub fn insert(&self, key: K, value: V) {
+        self.insert_at(key, value, Instant::now());
+    }
+
+    fn insert_at(&self, key: K, value: V, now: Instant) {
+        self.entries.lock().unwrap().insert(key, (value, now));
+    }
+
+    /// 获取缓存值，不存在或已过期时通过 `f` 生成并写入缓存
+    ///
+    /// 生成期间不持有锁，同一个键并发请求时可能会生成多次
+    pub async fn get_or_insert_with<F, Fut>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let value = f().await;
+        self.insert(key, value.clone());
+        value
+    }
+
+    /// 与 [`TtlCache::get_or_insert_with`] 相同，但 `f` 失败时不写入缓存并返回错误
+    pub async fn get_or_try_insert_with<F, Fut, E>(&self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+        let value = f().await?;
+        self.insert(key, value.clone());
+        Ok(value)
+    }
+
+    /// 移除指定的条目，返回被移除的值
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(key)
+            .map(|(value, _)| value)
+    }
+
+    /// 清除已过期的条目，返回清除的条目数量
+    pub fn sweep_expired(&self) -> usize {
+        self.sweep_expired_at(Instant::now())
+    }
+
+    fn sweep_expired_at(&self, now: Instant) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.retain(|_, (_, inserted)| now.duration_since(*inserted) < self.ttl);
+        count - entries.len()
+    }
+
+    /// 清空缓存，返回清除的条目数量，命中统计保持不变
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.clear();
+        count
+    }
+
+    /// 缓存中的条目数量，包括尚未移除的过期条目
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 读取缓存的命中和未命中次数
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_ttl_cache_expiry() {
+        let cache = TtlCache::new(TTL);
+        let start = Instant::now();
+        cache.insert_at("a", 1, start);
+
+        assert_eq!(cache.get_at(&"a", start), Some(1));
+        assert_eq!(cache.get_at(&"a", start + TTL / 2), Some(1));
+        // 到达有效期时视为过期，并在读取时移除
+        assert_eq!(cache.get_at(&"a", start + TTL), None);
+        assert!(cache.is_empty());
+
+        // 重新写入后有效期重新计算
+        cache.insert_at("a", 2, start + TTL);
+        assert_eq!(cache.get_at(&"a", start + TTL + TTL / 2), Some(2));
+        assert_eq!(cache.ttl(), TTL);
+    }
+
+    #[test]
+    fn test_ttl_cache_eviction() {
+        let cache = TtlCache::new(TTL);
+        let start = Instant::now();
+        cache.insert_at("old", 1, start);
+        cache.insert_at("new", 2, start + TTL / 2);
+        cache.insert_at("removed", 3, start);
+
+        assert_eq!(cache.remove(&"removed"), Some(3));
+        assert_eq!(cache.remove(&"removed"), None);
+
+        // 只清除过期的条目
+        assert_eq!(cache.sweep_expired_at(start + TTL), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get_at(&"new", start + TTL), Some(2));
+        assert_eq!(cache.sweep_expired_at(start + TTL), 0);
+
+        assert_eq!(cache.clear(), 1);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_ttl_cache_stats() {
+        let cache = TtlCache::new(TTL);
+        assert_eq!(cache.stats(), CacheStats::default());
+
+        assert_eq!(cache.get(&"a"), None);
+        cache.insert("a", 1);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"b"), None);
+
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 2 });
+        // 清空缓存不影响统计
+        cache.clear();
+        assert_eq!(cache.stats().hits, 2);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_cache_get_or_insert_with() {
+        let cache = TtlCache::new(TTL);
+        let calls = AtomicU64::new(0);
+        let load = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            "value".to_string()
+        };
+
+        assert_eq!(cache.get_or_insert_with("a", load).await, "value");
+        assert_eq!(cache.get_or_insert_with("a", load).await, "value");
+        // 第二次命中缓存，不再生成
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1 });
+
+        // 生成失败时不写入缓存
+        let result = cache
+            .get_or_try_insert_with("b", || async { Err::<String, _>("failed") })
+            .await;
+        assert_eq!(result, Err("failed"));
+        assert_eq!(cache.get(&"b"), None);
+
+        let result = cache
+            .get_or_try_insert_with("b", || async { Ok::<_, &str>("ok".to_string()) })
+            .await;
+        assert_eq!(result.as_deref(), Ok("ok"));
+        assert_eq!(cache.get(&"b").as_deref(), Some("ok"));
+    }
+}
@@ -0,0 +1,164 @@
+//! 配置文件支持
+//!
+//! 通过环境变量 `CONFIG_FILE` 指定 TOML 配置文件，键名与环境变量相同（不区分大小写），
+//! 表中的键会以 `表名_键名` 的形式展开，如 `[nga] uid = "1"` 对应 `NGA_UID`。
+//! 环境变量优先于配置文件中的值。
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// 全局配置，未加载时为空配置
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// 配置文件内容，保存展开后的配置项
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// 键名为大写的配置项
+    values: HashMap<String, String>,
+}
+
+impl Config {
+    /// 从 TOML 文本解析配置
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let table: toml::Table = toml::from_str(text)?;
+        let mut values = HashMap::new();
+        flatten_table(&table, "", &mut values);
+        Ok(Self { values })
+    }
+
+    /// 从 TOML 文件加载配置
+    pub fn from_file(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path))?;
+        Self::from_toml_str(&text).with_context(|| format!("Invalid config file: {}", path))
+    }
+
+    /// 获取配置项的值，键名不区分大小写
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.values.get(&name.to_uppercase()).cloned()
+    }
+}
+
+/// 将表展开为 `表名_键名` 形式的配置项，数组等无法表示为单个值的项会被忽略
+fn flatten_table(table: &toml::Table, prefix: &str, values: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let name = if prefix.is_empty() {
+            key.to_uppercase()
+        } else {
+            format!("{}_{}", prefix, key.to_uppercase())
+        };
+        let value = match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Integer(i) => i.to_string(),
+            toml::Value::Float(f) => f.to_string(),
+            toml::Value::Boolean(b) => b.to_string(),
+            toml::Value::Table(table) => {
+                flatten_table(table, &name, values);
+                continue;
+            }
+            other => {
+                log::warn!("Unsupported config value for {}: {}", name, other);
+                continue;
+            }
+        };
+        values.insert(name, value);
+    }
+}
+
+/// 设置全局配置，只能设置一次
+pub fn init_config(config: Config) {
+    if CONFIG.set(config).is_err() {
+        log::warn!("Config has already been initialized");
+    }
+}
+
+/// 从环境变量 CONFIG_FILE 指定的文件加载全局配置，未设置时不做任何操作
+pub fn load_config_from_env() -> Result<()> {
+    if let Ok(path) = std::env::var("CONFIG_FILE") {
+        let config = Config::from_file(&path)?;
+        log::info!("Loaded config file: {}", path);
+        init_config(config);
+    }
+    Ok(())
+}
+
+/// 获取全局配置
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// 按环境变量优先、配置文件其次的顺序解析配置项
+pub fn resolve_setting(env_value: Option<String>, config: &Config, name: &str) -> Option<String> {
+    env_value.or_else(|| config.get(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_CONFIG: &str = r#"
+        MAX_FILE_SIZE = "20MB"
+        telegram_proxy = "socks5://127.0.0.1:1080"
+        processor_timeout_secs = 30
+        append_source_url = true
+        admin_user_ids = [1, 2]
+
+        [nga]
+        uid = "123"
+        cid = "abc"
+    "#;
+
+    #[test]
+    fn test_config_values() {
+        let config = Config::from_toml_str(TEST_CONFIG).unwrap();
+
+        assert_eq!(config.get("MAX_FILE_SIZE").as_deref(), Some("20MB"));
+        // 键名不区分大小写
+        assert_eq!(
+            config.get("TELEGRAM_PROXY").as_deref(),
+            Some("socks5://127.0.0.1:1080")
+        );
+        assert_eq!(
+            config.get("telegram_proxy").as_deref(),
+            Some("socks5://127.0.0.1:1080")
+        );
+        // 非字符串值转换为字符串
+        assert_eq!(config.get("PROCESSOR_TIMEOUT_SECS").as_deref(), Some("30"));
+        assert_eq!(config.get("APPEND_SOURCE_URL").as_deref(), Some("true"));
+        // 表展开为 表名_键名
+        assert_eq!(config.get("NGA_UID").as_deref(), Some("123"));
+        assert_eq!(config.get("NGA_CID").as_deref(), Some("abc"));
+        // 数组被忽略
+        assert_eq!(config.get("ADMIN_USER_IDS"), None);
+        assert_eq!(config.get("MISSING"), None);
+
+        assert!(Config::from_toml_str("not = [valid").is_err());
+    }
+
+    #[test]
+    fn test_resolve_setting() {
+        let config = Config::from_toml_str(TEST_CONFIG).unwrap();
+
+        // 环境变量优先
+        assert_eq!(
+            resolve_setting(Some("50MB".to_string()), &config, "MAX_FILE_SIZE").as_deref(),
+            Some("50MB")
+        );
+        // 未设置环境变量时使用配置文件
+        assert_eq!(
+            resolve_setting(None, &config, "MAX_FILE_SIZE").as_deref(),
+            Some("20MB")
+        );
+        assert_eq!(
+            resolve_setting(None, &config, "NGA_UID").as_deref(),
+            Some("123")
+        );
+        // 都没有设置
+        assert_eq!(resolve_setting(None, &config, "PIXIV_IMAGE_PROXY"), None);
+        assert_eq!(
+            resolve_setting(None, &Config::default(), "MAX_FILE_SIZE"),
+            None
+        );
+    }
+}
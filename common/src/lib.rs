@@ -2,108 +2,125 @@
 //!
 //! 这个模块包含了整个workspace中可能用到的通用工具函数。
 use anyhow::{Result, anyhow};
-use byte_unit::Byte;
 use human_bytes::human_bytes;
-use std::cell::RefCell;
+use unicode_segmentation::UnicodeSegmentation;
 use url::Url;
 
+pub mod budget;
+pub mod cache;
+pub mod config;
 pub mod models;
+pub mod registry;
+pub mod settings;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+pub use budget::*;
+pub use cache::*;
+pub use config::*;
 pub use models::*;
+pub use registry::*;
+pub use settings::*;
 
 const DEFAULT_MAX_FILE_SIZE: usize = 10 * 1000 * 1000; // 默认最大文件大小：10MB
 pub const GENERAL_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
 pub const PIXIV_REFERER: &str = "https://www.pixiv.net/";
-/// 正常截断阈值：内容超过此长度时将使用可折叠引用
+/// 默认的正常截断阈值：内容超过此长度时将使用可折叠引用，可通过 SUMMARY_MAX_LENGTH 覆盖
 pub const SUMMARY_NORMAL_LIMIT: usize = 600;
-/// Telegram 消息绝对上限（最多 4096 字符），截断到 4000
+/// Telegram 媒体说明文字的长度上限
+pub const MEDIA_CAPTION_LIMIT: usize = 1024;
+/// Telegram 消息绝对上限（最多 4096 个 UTF-16 字符），默认截断到 4000，可通过 SUMMARY_MAX_MAX_LENGTH 调低
 pub const SUMMARY_TELEGRAM_LIMIT: usize = 4000;
 
-/// 获取最大文件大小设置，支持从环境变量 MAX_FILE_SIZE 读取
-/// 环境变量值可以是字节数（如 "10485760"）或人类可读格式（如 "10MB", "1GB"）
-/// 如果无法解析则使用默认值 10MB
+/// 获取最大文件大小设置（MAX_FILE_SIZE），默认 10MB
 ///
 /// https://core.telegram.org/bots/api#sendphoto
 /// The photo must be at most 10 MB in size.
 pub fn get_max_file_size() -> usize {
-    match get_env_var("MAX_FILE_SIZE") {
-        Some(size_str) => {
-            // 先尝试直接解析为数字（字节数）
-            if let Ok(size) = size_str.parse::<usize>() {
-                log::debug!(
-                    "Using MAX_FILE_SIZE from environment: {} bytes ({})",
-                    size,
-                    convert_bytes(size as f64)
-                );
-                return size;
-            }
+    settings().max_file_size
+}
 
-            // 如果不是纯数字，尝试解析人类可读格式
-            match Byte::parse_str(&size_str, true) {
-                Ok(byte_obj) => {
-                    let size = byte_obj.as_u64() as usize;
-                    log::debug!(
-                        "Using MAX_FILE_SIZE from environment: {} -> {} bytes ({})",
-                        size_str,
-                        size,
-                        convert_bytes(size as f64)
-                    );
-                    size
-                }
-                Err(_) => {
-                    log::warn!(
-                        "Invalid MAX_FILE_SIZE environment variable: {}, using default: {} bytes",
-                        size_str,
-                        DEFAULT_MAX_FILE_SIZE
-                    );
-                    DEFAULT_MAX_FILE_SIZE
-                }
-            }
-        }
-        None => {
-            log::debug!(
-                "MAX_FILE_SIZE not set, using default: {} bytes ({})",
-                DEFAULT_MAX_FILE_SIZE,
-                convert_bytes(DEFAULT_MAX_FILE_SIZE as f64)
-            );
-            DEFAULT_MAX_FILE_SIZE
-        }
-    }
+/// 获取正常截断阈值（SUMMARY_MAX_LENGTH），默认 [`SUMMARY_NORMAL_LIMIT`]
+pub fn get_summary_max_length() -> usize {
+    settings().summary_max_length
 }
 
-// 线程局部存储，控制是否启用文本截断
-thread_local! {
-    static TRUNCATION_ENABLED: RefCell<bool> = const { RefCell::new(true) };
+/// 获取截断上限（SUMMARY_MAX_MAX_LENGTH），默认 [`SUMMARY_TELEGRAM_LIMIT`]
+pub fn get_summary_max_max_length() -> usize {
+    settings().summary_max_max_length
 }
 
-/// 设置是否启用文本截断
-pub fn set_truncation_enabled(enabled: bool) {
-    TRUNCATION_ENABLED.with(|flag| {
-        *flag.borrow_mut() = enabled;
-    });
+/// 获取相册图片的最小文件大小（MIN_IMAGE_BYTES），未设置时返回 None，表示不过滤
+pub fn get_min_image_bytes() -> Option<usize> {
+    settings().min_image_bytes
 }
 
-/// 获取当前是否启用文本截断
-pub fn is_truncation_enabled() -> bool {
-    TRUNCATION_ENABLED.with(|flag| *flag.borrow())
+/// 根据 HEAD 请求得到的文件大小判断图片是否过小
+/// 无法获取文件大小时保留图片
+pub fn is_image_too_small(content_length: Option<u64>, min_bytes: usize) -> bool {
+    content_length.is_some_and(|size| size < min_bytes as u64)
 }
 
-/// 获取环境变量的值
+/// 发送 HEAD 请求获取文件大小，失败或没有 Content-Length 时返回 None
+pub async fn fetch_content_length(url: &str, referer: Option<&str>) -> Option<u64> {
+    send_head_request(url, referer)
+        .await?
+        .headers()
+        .get("content-length")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+}
+
+/// 发送 HEAD 请求获取内容类型，失败或没有 Content-Type 时返回 None
+pub async fn fetch_content_type(url: &str, referer: Option<&str>) -> Option<String> {
+    send_head_request(url, referer)
+        .await?
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+// 发送 HEAD 请求，失败时返回 None
+async fn send_head_request(url: &str, referer: Option<&str>) -> Option<reqwest::Response> {
+    let client = reqwest::Client::builder()
+        .user_agent(GENERAL_UA)
+        .build()
+        .ok()?;
+    let mut request = client.head(url);
+    if let Some(referer) = referer {
+        request = request.header("Referer", referer);
+    }
+
+    match request.send().await {
+        Ok(response) if response.status().is_success() => Some(response),
+        Ok(response) => {
+            log::debug!("HEAD request failed for {}: {}", url, response.status());
+            None
+        }
+        Err(e) => {
+            log::debug!("HEAD request failed for {}: {}", url, e);
+            None
+        }
+    }
+}
+
+/// 获取环境变量的值，未设置时使用配置文件中的值
 pub fn get_env_var(name: &str) -> Option<String> {
-    std::env::var(name).ok()
+    resolve_setting(std::env::var(name).ok(), config(), name)
 }
 
-/// 从环境变量 TELEGRAM_PROXY 构建 reqwest 客户端（含可选代理）
-/// 如果未设置代理环境变量，返回默认客户端
+/// 使用 TELEGRAM_PROXY 构建 reqwest 客户端（含可选代理）
+/// 如果未设置代理，返回默认客户端
 pub fn build_reqwest_client() -> reqwest::Client {
-    build_reqwest_client_with_proxy("TELEGRAM_PROXY")
+    build_reqwest_client_with_proxy(settings().telegram_proxy.as_deref())
 }
 
-/// 从指定环境变量构建 reqwest 客户端（含可选代理）
-pub fn build_reqwest_client_with_proxy(env_var: &str) -> reqwest::Client {
+/// 使用指定的代理构建 reqwest 客户端
+pub fn build_reqwest_client_with_proxy(proxy_url: Option<&str>) -> reqwest::Client {
     let mut builder = reqwest::Client::builder();
-    if let Some(proxy_url) = get_env_var(env_var) {
-        log::info!("Using proxy from '{}': {}", env_var, proxy_url);
-        match reqwest::Proxy::all(&proxy_url) {
+    if let Some(proxy_url) = proxy_url {
+        log::info!("Using proxy: {}", proxy_url);
+        match reqwest::Proxy::all(proxy_url) {
             Ok(proxy) => builder = builder.proxy(proxy),
             Err(e) => log::warn!("Failed to create proxy from '{}': {}", proxy_url, e),
         }
@@ -111,6 +128,46 @@ pub fn build_reqwest_client_with_proxy(env_var: &str) -> reqwest::Client {
     builder.build().expect("Failed to build reqwest client")
 }
 
+/// 请求 JSON 接口时使用的 Accept
+pub const ACCEPT_JSON: &str = "application/json, text/plain, */*";
+
+/// 请求网页时使用的 Accept
+pub const ACCEPT_HTML: &str = "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8";
+
+/// 处理器 HTTP 客户端的默认请求头
+pub fn http_default_headers(accept: &str) -> reqwest::header::HeaderMap {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Ok(value) = reqwest::header::HeaderValue::from_str(accept) {
+        headers.insert(reqwest::header::ACCEPT, value);
+    }
+    headers
+}
+
+/// 构建处理器使用的 HTTP 客户端，设置 User-Agent 和 Accept
+/// 启用了 reqwest 的 gzip/brotli 特性，会自动发送 Accept-Encoding 并解压响应
+pub fn build_http_client(ua: &str, accept: &str) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(ua)
+        .default_headers(http_default_headers(accept))
+        .build()
+}
+
+/// 构建下载文件使用的客户端，连接和整个请求的超时时间为 DOWNLOAD_TIMEOUT
+pub fn build_download_client(ua: &str) -> reqwest::Result<reqwest::Client> {
+    build_download_client_with_timeout(ua, settings().download_timeout)
+}
+
+fn build_download_client_with_timeout(
+    ua: &str,
+    timeout: std::time::Duration,
+) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(ua)
+        .timeout(timeout)
+        .connect_timeout(timeout)
+        .build()
+}
+
 /// 使用url库安全地拼接URL，避免斜杠重复
 pub fn join_url(base: &str, path: &str) -> Result<String> {
     let base_url = Url::parse(base)?;
@@ -118,17 +175,68 @@ pub fn join_url(base: &str, path: &str) -> Result<String> {
     Ok(joined.to_string())
 }
 
+/// 将URL改写为经过反向代理的URL，保留原始路径和查询参数
+///
+/// 如 `https://i.pximg.net/a/b.jpg` 经过 `https://proxy.example/` 改写为
+/// `https://proxy.example/a/b.jpg`
+pub fn rewrite_through_proxy(url: &str, proxy_base: &str) -> Result<String> {
+    let original_url = Url::parse(url)?;
+    let proxy_url =
+        Url::parse(proxy_base).map_err(|e| anyhow!("Invalid reverse proxy URL: {}", e))?;
+
+    let relative_path = original_url
+        .path()
+        .strip_prefix("/")
+        .unwrap_or(original_url.path());
+
+    let mut final_url = proxy_url.join(relative_path)?;
+
+    // 将原始 URL 的查询参数（?后面的部分）附加到新 URL 上
+    if let Some(query) = original_url.query() {
+        final_url.set_query(Some(query));
+    }
+
+    Ok(final_url.to_string())
+}
+
+/// 下载时需要调用方区分处理的错误，通过 `anyhow::Error::downcast_ref` 获取
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadError {
+    /// 服务器返回成功但内容为空
+    EmptyBody,
+}
+
+impl std::fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadError::EmptyBody => write!(f, "Downloaded file is empty"),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
 // 下载任意文件的通用函数
-pub async fn download_file(url: &str) -> Result<(Vec<u8>, String)> {
+// 返回文件内容、内容类型和 Content-Disposition 中的文件名
+pub async fn download_file(url: &str) -> Result<(Vec<u8>, String, Option<String>)> {
     download_file_ua(url, GENERAL_UA).await
 }
 
-pub async fn download_file_ua(url: &str, ua: &str) -> Result<(Vec<u8>, String)> {
-    download_file_internal(url, ua, None, None).await
+pub async fn download_file_ua(url: &str, ua: &str) -> Result<(Vec<u8>, String, Option<String>)> {
+    download_file_internal(url, ua, None, None, None).await
 }
 
-pub async fn download_pixiv(url: &str) -> Result<(Vec<u8>, String)> {
-    download_file_internal(url, GENERAL_UA, Some(PIXIV_REFERER), None).await
+pub async fn download_pixiv(url: &str) -> Result<(Vec<u8>, String, Option<String>)> {
+    download_file_internal(url, GENERAL_UA, Some(PIXIV_REFERER), None, None).await
+}
+
+/// 下载文件，重试时消耗 `budget`，与发送等其他重试共享次数，用尽后不再重试
+pub async fn download_file_with_budget(
+    url: &str,
+    referer: Option<&str>,
+    budget: &AttemptBudget,
+) -> Result<(Vec<u8>, String, Option<String>)> {
+    download_file_internal(url, GENERAL_UA, referer, None, Some(budget)).await
 }
 
 // 下载 GIF 文件的辅助函数
@@ -137,18 +245,81 @@ pub async fn get_gif_bytes(url: &str) -> Result<Vec<u8>> {
 }
 
 pub async fn get_gif_bytes_ua(url: &str, ua: &str) -> Result<Vec<u8>> {
-    let (bytes, _) = download_file_internal(url, ua, None, Some("gif".to_string())).await?;
+    let (bytes, _, _) =
+        download_file_internal(url, ua, None, Some("gif".to_string()), None).await?;
     Ok(bytes)
 }
 
+/// 下载请求重试前的基础等待时间，每次重试翻倍
+const DOWNLOAD_RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// 第 `attempt` 次重试前的等待时间：200ms、400ms、800ms……
+fn download_retry_delay(attempt: u32) -> std::time::Duration {
+    DOWNLOAD_RETRY_BASE_DELAY.saturating_mul(2u32.saturating_pow(attempt))
+}
+
+/// 下载请求的结果是否应该重试：连接错误、超时和 5xx 响应
+/// 4xx 等其他错误重试也不会成功
+fn is_retryable_download(result: &reqwest::Result<reqwest::Response>) -> bool {
+    match result {
+        Ok(response) => response.status().is_server_error(),
+        Err(e) => e.is_connect() || e.is_timeout(),
+    }
+}
+
+/// 发送下载的 GET 请求，遇到临时错误时按指数退避最多重试 `max_retries` 次
+/// 提供 `budget` 时每次重试还需消耗一次预算，预算用尽后不再重试
+async fn send_download_request(
+    client: &reqwest::Client,
+    url: &str,
+    referer: Option<&str>,
+    max_retries: u32,
+    budget: Option<&AttemptBudget>,
+) -> reqwest::Result<reqwest::Response> {
+    let mut attempt = 0;
+    loop {
+        let mut request = client.get(url);
+        if let Some(referer) = referer {
+            request = request.header("Referer", referer);
+        }
+
+        let result = request.send().await;
+        if attempt >= max_retries || !is_retryable_download(&result) {
+            return result;
+        }
+        if budget.is_some_and(|budget| !budget.try_acquire()) {
+            log::warn!("Retry budget exhausted, giving up on download of {}", url);
+            return result;
+        }
+
+        let reason = match &result {
+            Ok(response) => response.status().to_string(),
+            Err(e) => e.to_string(),
+        };
+        let delay = download_retry_delay(attempt);
+        attempt += 1;
+        log::debug!(
+            "Download of {} failed: {}, retrying in {:?} ({}/{})",
+            url,
+            reason,
+            delay,
+            attempt,
+            max_retries
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
 // 内部下载函数，统一处理所有下载逻辑
 async fn download_file_internal(
     url: &str,
     ua: &str,
     referer: Option<&str>,
     check_image_type: Option<String>,
-) -> Result<(Vec<u8>, String)> {
-    let client = reqwest::Client::builder().user_agent(ua).build()?;
+    budget: Option<&AttemptBudget>,
+) -> Result<(Vec<u8>, String, Option<String>)> {
+    let client = build_download_client(ua)?;
+    let max_file_size = get_max_file_size();
 
     // 先发送 HEAD 请求检查文件大小和类型
     let mut head_response = client.head(url);
@@ -159,35 +330,124 @@ async fn download_file_internal(
 
     let head_response = head_response.send().await?;
 
-    if !head_response.status().is_success() {
+    // 部分服务器不支持 HEAD，此时跳过预检查，由下载时的大小限制兜底
+    let head_response = if is_head_unsupported(head_response.status()) {
+        log::debug!(
+            "HEAD request rejected with {}, skipping size pre-check",
+            head_response.status()
+        );
+        None
+    } else if !head_response.status().is_success() {
         return Err(anyhow!(
             "HTTP HEAD request failed: {}",
             head_response.status()
         ));
-    }
+    } else {
+        Some(head_response)
+    };
 
     // 检查内容长度
-    if let Some(content_length) = head_response.headers().get("content-length") {
-        if let Ok(size_str) = content_length.to_str()
-            && let Ok(size) = size_str.parse::<usize>()
-        {
-            log::debug!("File size: {} bytes ({})", size, convert_bytes(size as f64));
-
-            let max_file_size = get_max_file_size();
-            if size > max_file_size {
-                return Err(anyhow!(
-                    "File too large: {} (max: {})",
-                    convert_bytes(size as f64),
-                    convert_bytes(max_file_size as f64)
-                ));
+    let mut declared_size = None;
+    if let Some(ref head_response) = head_response {
+        if let Some(content_length) = head_response.headers().get("content-length") {
+            if let Ok(size_str) = content_length.to_str()
+                && let Ok(size) = size_str.parse::<usize>()
+            {
+                declared_size = Some(size);
+                log::debug!("File size: {} bytes ({})", size, convert_bytes(size as f64));
+
+                if size > max_file_size {
+                    return Err(anyhow!(
+                        "File too large: {} (max: {})",
+                        convert_bytes(size as f64),
+                        convert_bytes(max_file_size as f64)
+                    ));
+                }
             }
+        } else {
+            log::debug!("Content-Length header not found, proceeding with download");
         }
-    } else {
-        log::debug!("Content-Length header not found, proceeding with download");
     }
 
-    // 获取内容类型
-    let content_type = head_response
+    // 如果检查通过，开始实际下载
+    log::debug!("Starting download from: {}", url);
+    let mut response = send_download_request(
+        &client,
+        url,
+        referer,
+        settings().download_max_retries,
+        budget,
+    )
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("HTTP GET request failed: {}", response.status()));
+    }
+
+    // 获取内容类型，HEAD 被拒绝时使用 GET 响应的，在读取内容前检查
+    let content_type = check_content_type(
+        head_response.as_ref().unwrap_or(&response),
+        check_image_type.as_deref(),
+    )?;
+
+    // 服务器通过 Content-Disposition 提供的文件名
+    let file_name = response
+        .headers()
+        .get("content-disposition")
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_content_disposition_filename);
+    if let Some(ref file_name) = file_name {
+        log::debug!("Content-Disposition filename: {}", file_name);
+    }
+
+    // 边下载边检查大小，超过限制时立即中止
+    let bytes = read_body_limited(&mut response, max_file_size).await?;
+
+    let bytes_len = bytes.len();
+    check_body_not_empty(bytes_len)?;
+
+    // HEAD 声明的大小与实际不符时记录警告，便于发现行为异常的服务器
+    if let Some(message) = check_content_length(url, declared_size, bytes_len) {
+        log::warn!("{}", message);
+    }
+
+    log::info!(
+        "Successfully downloaded {}",
+        convert_bytes(bytes_len as f64)
+    );
+    Ok((bytes, content_type, file_name))
+}
+
+/// 分块读取响应内容，累计大小超过 `max_file_size` 时立即中止，
+/// 避免服务器不提供或谎报 Content-Length 时把过大的内容全部读入内存
+async fn read_body_limited(
+    response: &mut reqwest::Response,
+    max_file_size: usize,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > max_file_size {
+            return Err(anyhow!(
+                "Downloaded file too large: exceeds {}",
+                convert_bytes(max_file_size as f64)
+            ));
+        }
+    }
+    Ok(bytes)
+}
+
+/// HEAD 请求是否因服务器不支持而被拒绝（405/501）
+fn is_head_unsupported(status: reqwest::StatusCode) -> bool {
+    matches!(
+        status,
+        reqwest::StatusCode::METHOD_NOT_ALLOWED | reqwest::StatusCode::NOT_IMPLEMENTED
+    )
+}
+
+/// 获取响应的内容类型，并在需要时检查是否与期望的类型匹配
+fn check_content_type(response: &reqwest::Response, expected: Option<&str>) -> Result<String> {
+    let content_type = response
         .headers()
         .get("content-type")
         .and_then(|ct| ct.to_str().ok())
@@ -196,8 +456,7 @@ async fn download_file_internal(
 
     log::debug!("Content-Type: {}", content_type);
 
-    // 如果需要检查类型
-    if let Some(ref check_type) = check_image_type
+    if let Some(check_type) = expected
         && !content_type.contains(check_type)
     {
         return Err(anyhow!(
@@ -206,93 +465,177 @@ async fn download_file_internal(
             check_type
         ));
     }
+    Ok(content_type)
+}
 
-    // 如果检查通过，开始实际下载
-    log::debug!("Starting download from: {}", url);
-    let mut response = client.get(url);
-
-    if let Some(referer) = referer {
-        response = response.header("Referer", referer);
+/// 下载的内容为空时返回 [`DownloadError::EmptyBody`]，避免上传空文件
+fn check_body_not_empty(len: usize) -> Result<()> {
+    if len == 0 {
+        return Err(DownloadError::EmptyBody.into());
     }
+    Ok(())
+}
 
-    let response = response.send().await?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!("HTTP GET request failed: {}", response.status()));
+/// 检查 HEAD 请求声明的大小与实际下载大小是否一致，不一致时返回描述信息
+fn check_content_length(url: &str, declared: Option<usize>, actual: usize) -> Option<String> {
+    let declared = declared?;
+    if declared == actual {
+        return None;
     }
+    Some(format!(
+        "Content-Length mismatch for {}: HEAD declared {} but downloaded {}",
+        url,
+        convert_bytes(declared as f64),
+        convert_bytes(actual as f64)
+    ))
+}
 
-    let bytes = response.bytes().await?;
-
-    let bytes_len = bytes.len();
-
-    // 再次检查实际下载的文件大小
-    let max_file_size = get_max_file_size();
-    if bytes_len > max_file_size {
-        return Err(anyhow!(
-            "Downloaded file too large: {} (max: {})",
-            convert_bytes(bytes_len as f64),
-            convert_bytes(max_file_size as f64)
-        ));
+/// 为缺少协议的链接补充 `https://`，如 `b23.tv/abc` → `https://b23.tv/abc`
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let has_scheme = url.split_once("://").is_some_and(|(scheme, _)| {
+        !scheme.is_empty()
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    if has_scheme {
+        url.to_string()
+    } else {
+        format!("https://{}", url.trim_start_matches('/'))
     }
+}
 
-    log::info!(
-        "Successfully downloaded {}",
-        convert_bytes(bytes_len as f64)
-    );
-    Ok((bytes.to_vec(), content_type))
+/// 判断链接的主机名是否为指定域名或其子域名，`domain` 也可以是完整链接
+/// 按解析后的主机名比较，域名出现在路径中时不会误判，国际化域名统一按 punycode 比较
+pub fn host_matches(url: &str, domain: &str) -> bool {
+    let host_of = |value: &str| {
+        Url::parse(&normalize_url(value)).ok().and_then(|url| {
+            url.host_str()
+                .map(|host| host.trim_end_matches('.').to_string())
+        })
+    };
+    let (Some(host), Some(domain)) = (host_of(url), host_of(domain)) else {
+        return false;
+    };
+    host == domain
+        || host
+            .strip_suffix(&domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// 转义HTML特殊字符，防止Telegram将文本内容识别为HTML标签
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
 
 /// 截断描述文本到指定长度
 pub fn substring_desc(desc: &str) -> String {
-    // 检查是否启用截断
-    if !is_truncation_enabled() {
-        return desc.trim().to_string();
-    }
-
     substring_desc_with_truncation(desc, true)
 }
 
 /// 控制是否截断描述文本
+/// 长度按字素簇计算，截断时不会拆开组合的 emoji（如国旗、肤色修饰）；
+/// 同时保证保留的内容不超过 Telegram 按 UTF-16 计算的 [`SUMMARY_TELEGRAM_LIMIT`]
 pub fn substring_desc_with_truncation(desc: &str, should_truncate: bool) -> String {
     if !should_truncate {
         return desc.trim().to_string();
     }
 
-    let chars: Vec<char> = desc.chars().collect();
-    let total_len = chars.len();
+    let normal_limit = get_summary_max_length();
+    let max_limit = get_summary_max_max_length();
+    let graphemes: Vec<&str> = desc.graphemes(true).collect();
+    let total_len = graphemes.len();
+    let keep = truncation_point(&graphemes, max_limit);
+    let exceeds_limit = keep < total_len;
 
     // 如果字符数没有超过正常限制，直接返回
-    if total_len <= SUMMARY_NORMAL_LIMIT {
+    if total_len <= normal_limit && !exceeds_limit {
         return desc.trim().to_string();
     }
 
     // 如果内容已包含 blockquote 标签，不再包裹新的 blockquote
     if desc.contains("<blockquote>") {
-        if total_len <= SUMMARY_TELEGRAM_LIMIT {
+        if !exceeds_limit {
             return desc.trim().to_string();
         } else {
             // 超过 Telegram 限制，直接截断
-            let truncated: String = chars[..SUMMARY_TELEGRAM_LIMIT].iter().collect();
+            let truncated = graphemes[..keep].concat();
             return format!("{}……", truncated.trim());
         }
     }
 
-    if total_len <= SUMMARY_TELEGRAM_LIMIT {
+    if !exceeds_limit {
         // 超过正常限制但未达 Telegram 上限，整个内容放入可折叠引用
         format!("<blockquote expandable>{}</blockquote>", desc.trim())
     } else {
         // 超过 Telegram 限制，截断后放入可折叠引用
-        let truncated: String = chars[..SUMMARY_TELEGRAM_LIMIT].iter().collect();
+        let truncated = graphemes[..keep].concat();
         format!("<blockquote expandable>{}……</blockquote>", truncated.trim())
     }
 }
 
+/// 截断时保留的字素簇数量：最多 `max_graphemes` 个，且 UTF-16 长度不超过 [`SUMMARY_TELEGRAM_LIMIT`]
+fn truncation_point(graphemes: &[&str], max_graphemes: usize) -> usize {
+    let mut utf16_len = 0;
+    for (index, grapheme) in graphemes.iter().take(max_graphemes).enumerate() {
+        utf16_len += grapheme.encode_utf16().count();
+        if utf16_len > SUMMARY_TELEGRAM_LIMIT {
+            return index;
+        }
+    }
+    graphemes.len().min(max_graphemes)
+}
+
 /// 将字节数转换为人类可读的格式
 pub fn convert_bytes<T: Into<f64>>(bytes: T) -> String {
     human_bytes(bytes.into())
 }
 
+/// 将时长转换为人类可读的格式，只保留最大的两个单位，如 `2h 13m`、`3d 4h`
+/// 不足一秒时以毫秒表示
+pub fn human_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    if secs == 0 {
+        return format!("{}ms", duration.as_millis());
+    }
+
+    let units = [
+        (secs / 86400, "d"),
+        (secs % 86400 / 3600, "h"),
+        (secs % 3600 / 60, "m"),
+        (secs % 60, "s"),
+    ];
+    // 从第一个非零单位开始取两个单位，第二个为零时省略
+    let first = units.iter().position(|(value, _)| *value > 0).unwrap_or(3);
+    units[first..]
+        .iter()
+        .take(2)
+        .enumerate()
+        .filter(|(index, (value, _))| *index == 0 || *value > 0)
+        .map(|(_, (value, unit))| format!("{}{}", value, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 去掉 Pixiv 缩略图文件名中的尺寸后缀，如 `123_p0_master1200` -> `123_p0`
+/// 代理可能返回 webp，尺寸后缀对文件名没有意义
+fn strip_pixiv_size_suffix(stem: &str) -> &str {
+    for marker in ["_master", "_square", "_custom"] {
+        if let Some(pos) = stem.rfind(marker) {
+            let size = &stem[pos + marker.len()..];
+            if pos > 0 && !size.is_empty() && size.bytes().all(|b| b.is_ascii_digit()) {
+                return &stem[..pos];
+            }
+        }
+    }
+    stem
+}
+
 /// 从URL中提取文件名，优先使用content-type推断的文件扩展名，无法确定时使用URL的扩展名
+/// Pixiv 缩略图的尺寸后缀会被去掉
 pub fn extract_filename_from_url(url: &str, content_type: &str) -> String {
     use std::path::Path;
 
@@ -313,6 +656,7 @@ pub fn extract_filename_from_url(url: &str, content_type: &str) -> String {
             let stem = path_obj
                 .file_stem()
                 .and_then(|s| s.to_str())
+                .map(strip_pixiv_size_suffix)
                 .unwrap_or("file");
 
             // 优先使用content-type的扩展名，如果没有则使用URL的扩展名
@@ -343,6 +687,99 @@ pub fn extract_filename_from_url(url: &str, content_type: &str) -> String {
     }
 }
 
+/// 从 Content-Disposition 头中解析文件名
+/// 优先使用 `filename*=`（RFC 5987 编码），其次为 `filename=`，去除路径部分，为空时返回 None
+pub fn parse_content_disposition_filename(value: &str) -> Option<String> {
+    let mut filename = None;
+    let mut encoded_filename = None;
+
+    for param in split_header_params(value) {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename*" => encoded_filename = decode_ext_value(value.trim()),
+            "filename" => filename = Some(unquote(value.trim())),
+            _ => {}
+        }
+    }
+
+    let filename = encoded_filename.or(filename)?;
+    // 只保留最后一段，防止文件名中带有路径
+    let filename = filename.rsplit(['/', '\\']).next()?.trim();
+    (!filename.is_empty()).then(|| filename.to_string())
+}
+
+/// 按分号拆分头部参数，引号内的分号不拆分
+fn split_header_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (index, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                params.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+/// 去除参数值两端的引号并处理转义
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(inner) => {
+            let mut result = String::with_capacity(inner.len());
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => result.extend(chars.next()),
+                    c => result.push(c),
+                }
+            }
+            result
+        }
+        None => value.to_string(),
+    }
+}
+
+/// 解码 RFC 5987 格式的参数值，如 `UTF-8''%E6%96%87.jpg`
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _language = parts.next()?;
+    let encoded = parts.next()?;
+
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut iter = encoded.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else {
+        // ISO-8859-1 的每个字节对应同值的 Unicode 字符
+        Some(bytes.into_iter().map(char::from).collect())
+    }
+}
+
 /// 根据URL的文件扩展名推断Content-Type
 pub fn guess_content_type_from_url(url: &str) -> Option<String> {
     use std::path::Path;
@@ -361,6 +798,9 @@ pub fn guess_content_type_from_url(url: &str) -> Option<String> {
                 "webp" => "image/webp".to_string(),
                 "bmp" => "image/bmp".to_string(),
                 "svg" => "image/svg+xml".to_string(),
+                "avif" => "image/avif".to_string(),
+                "heic" => "image/heic".to_string(),
+                "heif" => "image/heif".to_string(),
 
                 // 视频格式
                 "mp4" => "video/mp4".to_string(),
@@ -399,6 +839,10 @@ pub fn get_file_extension_from_content_type(content_type: &str) -> Option<String
             "image/png" => Some("png"),
             "image/gif" => Some("gif"),
             "image/webp" => Some("webp"),
+            "image/avif" => Some("avif"),
+            "image/heic" => Some("heic"),
+            "image/heif" => Some("heif"),
+            "image/svg+xml" => Some("svg"),
             _ => None, // 未知图片格式返回None
         }
     } else if content_type.starts_with("video/") {
@@ -427,6 +871,95 @@ pub fn get_file_extension_from_content_type(content_type: &str) -> Option<String
     extension.map(|ext| ext.to_string())
 }
 
+/// Telegram 无法作为图片显示的图片格式，只能作为文件发送
+const UNSUPPORTED_IMAGE_TYPES: &[&str] = &[
+    "image/svg+xml",
+    "image/avif",
+    "image/heic",
+    "image/heif",
+    "image/tiff",
+    "image/x-icon",
+    "image/vnd.microsoft.icon",
+];
+
+/// 获取 content-type 的类型部分（忽略 `; charset=...` 等参数），转换为小写
+pub fn content_type_essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// 媒体类型，按 content-type 的主类型划分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Audio,
+    Other,
+}
+
+impl MediaKind {
+    /// 根据content-type判断媒体类型
+    pub fn from_content_type(content_type: &str) -> Self {
+        if content_type.starts_with("image/") {
+            Self::Image
+        } else if content_type.starts_with("video/") {
+            Self::Video
+        } else if content_type.starts_with("audio/") {
+            Self::Audio
+        } else {
+            Self::Other
+        }
+    }
+
+    /// 是否为 Telegram 无法作为图片显示的图片格式（SVG、AVIF、HEIC 等）
+    pub fn is_unsupported_image(content_type: &str) -> bool {
+        UNSUPPORTED_IMAGE_TYPES.contains(&content_type_essence(content_type).as_str())
+    }
+
+    /// 根据名称解析媒体类型（image/video/audio），用于命令参数
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "image" => Some(Self::Image),
+            "video" => Some(Self::Video),
+            "audio" => Some(Self::Audio),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Video => "video",
+            Self::Audio => "audio",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Telegram 图片宽度和高度总和的上限
+const PHOTO_MAX_DIMENSION_SUM: usize = 10000;
+/// Telegram 图片宽高比的上限
+const PHOTO_MAX_ASPECT_RATIO: f64 = 20.0;
+
+/// 宽高是否超出 Telegram 图片的尺寸限制（总和或宽高比过大）
+/// 宽度或高度为 0 的图片无效，不视为超出限制
+pub fn exceeds_photo_limits(width: usize, height: usize) -> bool {
+    if width == 0 || height == 0 {
+        return false;
+    }
+    let ratio = width.max(height) as f64 / width.min(height) as f64;
+    width + height > PHOTO_MAX_DIMENSION_SUM || ratio > PHOTO_MAX_ASPECT_RATIO
+}
+
+/// 是否为能读取尺寸且尺寸超出 Telegram 限制的图片，无法解析的数据返回 false
+pub fn is_oversize_image(image_data: &[u8]) -> bool {
+    imagesize::blob_size(image_data).is_ok_and(|size| exceeds_photo_limits(size.width, size.height))
+}
+
 /// 验证图片尺寸是否符合Telegram的要求
 ///
 /// Telegram对图片的要求：
@@ -449,12 +982,13 @@ pub fn validate_image_dimensions(image_data: &[u8]) -> Result<()> {
             }
 
             // 检查宽度+高度的总和
-            if width + height > 10000 {
+            if width + height > PHOTO_MAX_DIMENSION_SUM {
                 return Err(anyhow!(
-                    "Image dimensions too large: {}x{} (sum {} exceeds 10000)",
+                    "Image dimensions too large: {}x{} (sum {} exceeds {})",
                     width,
                     height,
-                    width + height
+                    width + height,
+                    PHOTO_MAX_DIMENSION_SUM
                 ));
             }
 
@@ -465,12 +999,13 @@ pub fn validate_image_dimensions(image_data: &[u8]) -> Result<()> {
                 height as f64 / width as f64
             };
 
-            if ratio > 20.0 {
+            if ratio > PHOTO_MAX_ASPECT_RATIO {
                 return Err(anyhow!(
-                    "Image aspect ratio too extreme: {}x{} (ratio {:.2} exceeds 20)",
+                    "Image aspect ratio too extreme: {}x{} (ratio {:.2} exceeds {})",
                     width,
                     height,
-                    ratio
+                    ratio,
+                    PHOTO_MAX_ASPECT_RATIO
                 ));
             }
 
@@ -480,6 +1015,28 @@ pub fn validate_image_dimensions(image_data: &[u8]) -> Result<()> {
     }
 }
 
+/// 判断是否为动画 WEBP：RIFF 容器中含有 ANIM 块
+/// 不是 WEBP 或数据不完整时返回 false
+pub fn is_animated_webp(data: &[u8]) -> bool {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return false;
+    }
+
+    let mut offset = 12;
+    while let Some(header) = data.get(offset..offset + 8) {
+        if &header[0..4] == b"ANIM" {
+            return true;
+        }
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        // 块内容按偶数字节对齐
+        offset = match offset.checked_add(8 + size + (size & 1)) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -498,6 +1055,39 @@ mod tests {
         assert_eq!(missing_value, None);
     }
 
+    #[test]
+    fn test_escape_html() {
+        let test_cases = vec![
+            ("<text>", "&lt;text&gt;"),
+            ("a & b", "a &amp; b"),
+            // 已转义的实体会被再次转义
+            ("&lt;", "&amp;lt;"),
+            // 引号不转义
+            ("\"quoted\" 'single'", "\"quoted\" 'single'"),
+            ("普通文本", "普通文本"),
+            ("", ""),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(escape_html(input), expected, "Failed for input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_is_image_too_small() {
+        let min_bytes = 10 * 1024;
+
+        assert!(is_image_too_small(Some(1024), min_bytes));
+        assert!(is_image_too_small(Some(10 * 1024 - 1), min_bytes));
+        // 等于阈值时保留
+        assert!(!is_image_too_small(Some(10 * 1024), min_bytes));
+        assert!(!is_image_too_small(Some(2 * 1024 * 1024), min_bytes));
+        // 未知大小时保留
+        assert!(!is_image_too_small(None, min_bytes));
+        // 阈值为 0 时全部保留
+        assert!(!is_image_too_small(Some(0), 0));
+    }
+
     #[test]
     fn test_url_joining() {
         let test_cases = vec![
@@ -530,6 +1120,443 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_rewrite_through_proxy() {
+        assert_eq!(
+            rewrite_through_proxy(
+                "https://i.pximg.net/img-original/img/2023/12/25/114514_p0.jpg",
+                "https://i.pixiv.cat/"
+            )
+            .unwrap(),
+            "https://i.pixiv.cat/img-original/img/2023/12/25/114514_p0.jpg"
+        );
+
+        // 保留查询参数，代理地址可以带路径
+        assert_eq!(
+            rewrite_through_proxy(
+                "https://img.nga.178.com/attachments/mon_202301/01/abc.jpg?token=t",
+                "https://proxy.example/nga/"
+            )
+            .unwrap(),
+            "https://proxy.example/nga/attachments/mon_202301/01/abc.jpg?token=t"
+        );
+
+        assert!(rewrite_through_proxy("not a url", "https://proxy.example/").is_err());
+        assert!(rewrite_through_proxy("https://a.com/1.jpg", "proxy.example").is_err());
+    }
+
+    #[test]
+    fn test_is_head_unsupported() {
+        use reqwest::StatusCode;
+
+        // 不支持 HEAD 时跳过预检查
+        assert!(is_head_unsupported(StatusCode::METHOD_NOT_ALLOWED));
+        assert!(is_head_unsupported(StatusCode::NOT_IMPLEMENTED));
+        // 其他错误仍然视为失败
+        assert!(!is_head_unsupported(StatusCode::OK));
+        assert!(!is_head_unsupported(StatusCode::FORBIDDEN));
+        assert!(!is_head_unsupported(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        // 缺少协议时补充 https
+        assert_eq!(normalize_url("b23.tv/abc123"), "https://b23.tv/abc123");
+        assert_eq!(
+            normalize_url("//i.pximg.net/img.jpg"),
+            "https://i.pximg.net/img.jpg"
+        );
+        assert_eq!(normalize_url(" b23.tv/abc123 "), "https://b23.tv/abc123");
+        // 已有协议时保持不变
+        assert_eq!(
+            normalize_url("http://b23.tv/abc123"),
+            "http://b23.tv/abc123"
+        );
+        assert_eq!(
+            normalize_url("https://x.com/user/status/1"),
+            "https://x.com/user/status/1"
+        );
+        // 查询参数中的 :// 不视为协议
+        assert_eq!(
+            normalize_url("example.com/?next=https://a.com"),
+            "https://example.com/?next=https://a.com"
+        );
+
+        assert!(Url::parse(&normalize_url("b23.tv/abc123")).is_ok());
+    }
+
+    #[test]
+    fn test_host_matches() {
+        assert!(host_matches(
+            "https://www.pixiv.net/artworks/1",
+            "pixiv.net"
+        ));
+        assert!(host_matches("https://i.pximg.net/img.jpg", "pximg.net"));
+        assert!(host_matches("i.pximg.net/img.jpg", "pximg.net"));
+        assert!(host_matches(
+            "https://www.phixiv.net/i/img.jpg",
+            "https://www.phixiv.net/i/"
+        ));
+
+        // 域名出现在路径或查询参数中不匹配
+        assert!(!host_matches(
+            "https://example.com/pixiv.net/1.jpg",
+            "pixiv.net"
+        ));
+        assert!(!host_matches(
+            "https://example.com/?u=i.pximg.net",
+            "pximg.net"
+        ));
+        // 只是后缀相同的其他域名不匹配
+        assert!(!host_matches("https://notpixiv.net/1.jpg", "pixiv.net"));
+
+        // 国际化域名与 punycode 形式等价
+        assert!(host_matches("https://图片.例子.中国/a.jpg", "例子.中国"));
+        assert!(host_matches(
+            "https://xn--fsqu00a.xn--fiqs8s/a.jpg",
+            "https://例子.中国/"
+        ));
+        assert!(!host_matches("https://例子.中国/a.jpg", "example.com"));
+
+        assert!(!host_matches("not a url", "pixiv.net"));
+    }
+
+    #[test]
+    fn test_http_default_headers() {
+        let headers = http_default_headers(ACCEPT_JSON);
+        assert_eq!(headers.get(reqwest::header::ACCEPT).unwrap(), ACCEPT_JSON);
+        // 解压由 reqwest 处理，不手动设置 Accept-Encoding
+        assert!(headers.get(reqwest::header::ACCEPT_ENCODING).is_none());
+
+        let headers = http_default_headers(ACCEPT_HTML);
+        assert_eq!(headers.get(reqwest::header::ACCEPT).unwrap(), ACCEPT_HTML);
+
+        // 无效的值被忽略
+        assert!(http_default_headers("bad\nvalue").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_build_http_client_request_headers() {
+        let (addr, request) = capture_request();
+        let client = build_http_client(GENERAL_UA, ACCEPT_HTML).unwrap();
+        let response = client
+            .get(format!("http://{}/", addr))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // 请求头名称不区分大小写，统一转为小写后查找
+        let request = request.recv().unwrap().to_ascii_lowercase();
+        let header = |name: &str| {
+            request
+                .lines()
+                .find_map(|line| line.strip_prefix(&format!("{}: ", name)))
+                .map(str::to_string)
+                .unwrap_or_default()
+        };
+        let accept_encoding = header("accept-encoding");
+        assert!(accept_encoding.contains("gzip"));
+        assert!(accept_encoding.contains("br"));
+        assert_eq!(header("accept"), ACCEPT_HTML.to_ascii_lowercase());
+        assert_eq!(header("user-agent"), GENERAL_UA.to_ascii_lowercase());
+    }
+
+    #[test]
+    fn test_download_retry_delay() {
+        assert_eq!(
+            download_retry_delay(0),
+            std::time::Duration::from_millis(200)
+        );
+        assert_eq!(
+            download_retry_delay(1),
+            std::time::Duration::from_millis(400)
+        );
+        assert_eq!(
+            download_retry_delay(2),
+            std::time::Duration::from_millis(800)
+        );
+        // 不会溢出
+        assert!(download_retry_delay(64) > std::time::Duration::from_secs(3600));
+    }
+
+    /// 依次以给定的状态码响应的 HTTP 服务器，返回地址和已处理的请求数
+    fn serve_statuses(
+        statuses: &[u16],
+    ) -> (
+        std::net::SocketAddr,
+        std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    ) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = served.clone();
+        let statuses = statuses.to_vec();
+        std::thread::spawn(move || {
+            for status in statuses {
+                let Ok((mut stream, _)) = listener.accept() else {
+                    return;
+                };
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf);
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    status
+                );
+            }
+        });
+        (addr, served)
+    }
+
+    /// 启动只处理一次请求的本地 HTTP 服务器，返回收到的原始请求
+    fn capture_request() -> (std::net::SocketAddr, std::sync::mpsc::Receiver<String>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let Ok((mut stream, _)) = listener.accept() else {
+                return;
+            };
+            let mut buf = [0; 4096];
+            let n = stream.read(&mut buf).unwrap_or(0);
+            let _ = tx.send(String::from_utf8_lossy(&buf[..n]).into_owned());
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+            );
+        });
+        (addr, rx)
+    }
+
+    #[tokio::test]
+    async fn test_send_download_request_retries() {
+        let client = build_download_client(GENERAL_UA).unwrap();
+
+        // 5xx 重试后成功
+        let (addr, served) = serve_statuses(&[503, 502, 200]);
+        let url = format!("http://{}/a.png", addr);
+        let response = send_download_request(&client, &url, None, 3, None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // 重试次数用尽时返回最后的响应
+        let (addr, served) = serve_statuses(&[500, 500]);
+        let url = format!("http://{}/a.png", addr);
+        let response = send_download_request(&client, &url, None, 1, None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 500);
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // 4xx 不重试
+        let (addr, served) = serve_statuses(&[404, 200]);
+        let url = format!("http://{}/a.png", addr);
+        let response = send_download_request(&client, &url, None, 3, None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // 重试预算用尽时不再重试
+        let budget = AttemptBudget::new(1);
+        let (addr, served) = serve_statuses(&[503, 503, 200]);
+        let url = format!("http://{}/a.png", addr);
+        let response = send_download_request(&client, &url, None, 3, Some(&budget))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 503);
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(budget.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_download_client_timeout() {
+        assert!(build_download_client(GENERAL_UA).is_ok());
+
+        // 接受连接但不响应的服务器
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let _stream = listener.accept();
+            std::thread::sleep(std::time::Duration::from_secs(2));
+        });
+
+        let client =
+            build_download_client_with_timeout(GENERAL_UA, std::time::Duration::from_millis(200))
+                .unwrap();
+        let err = client
+            .get(format!("http://{}/file.png", addr))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_timeout());
+    }
+
+    #[tokio::test]
+    async fn test_read_body_limited() {
+        use std::io::{Read, Write};
+
+        let client = build_download_client(GENERAL_UA).unwrap();
+
+        // 未超过限制时读取完整内容
+        let (addr, _) = serve_statuses(&[200]);
+        let mut response = client
+            .get(format!("http://{}/a.png", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(read_body_limited(&mut response, 2).await.unwrap(), b"ok");
+
+        // 不提供 Content-Length，持续发送数据直到连接断开的服务器
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let Ok((mut stream, _)) = listener.accept() else {
+                return;
+            };
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n"
+            );
+            let chunk = [b'x'; 512];
+            while write!(stream, "{:x}\r\n", chunk.len()).is_ok()
+                && stream.write_all(&chunk).is_ok()
+                && stream.write_all(b"\r\n").is_ok()
+            {}
+        });
+
+        let mut response = client
+            .get(format!("http://{}/a.png", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.content_length(), None);
+
+        // 超过限制时立即返回错误，不会等待读完（永远不会结束的）响应
+        let err = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            read_body_limited(&mut response, 4096),
+        )
+        .await
+        .expect("超过限制时应该立即中止")
+        .unwrap_err();
+        assert!(err.to_string().contains("too large"));
+    }
+
+    #[test]
+    fn test_human_duration() {
+        use std::time::Duration;
+
+        assert_eq!(human_duration(Duration::ZERO), "0ms");
+        assert_eq!(human_duration(Duration::from_millis(350)), "350ms");
+        assert_eq!(human_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(human_duration(Duration::from_secs(125)), "2m 5s");
+        assert_eq!(human_duration(Duration::from_secs(600)), "10m");
+        assert_eq!(
+            human_duration(Duration::from_secs(2 * 3600 + 13 * 60 + 59)),
+            "2h 13m"
+        );
+        assert_eq!(human_duration(Duration::from_secs(3600 + 30)), "1h");
+        assert_eq!(human_duration(Duration::from_secs(86400)), "1d");
+        assert_eq!(
+            human_duration(Duration::from_secs(3 * 86400 + 4 * 3600 + 5)),
+            "3d 4h"
+        );
+        assert_eq!(human_duration(Duration::from_secs(400 * 86400)), "400d");
+    }
+
+    #[test]
+    fn test_check_body_not_empty() {
+        let err = check_body_not_empty(0).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<DownloadError>(),
+            Some(&DownloadError::EmptyBody)
+        );
+        assert_eq!(err.to_string(), "Downloaded file is empty");
+        assert!(check_body_not_empty(1).is_ok());
+    }
+
+    #[test]
+    fn test_check_content_length() {
+        let url = "https://example.com/a.jpg";
+
+        // 大小一致或未声明时不报告
+        assert_eq!(check_content_length(url, Some(1024), 1024), None);
+        assert_eq!(check_content_length(url, None, 1024), None);
+
+        // 实际内容比声明的大
+        let message = check_content_length(url, Some(1000), 5000).unwrap();
+        assert!(message.contains(url));
+        assert!(message.contains(&convert_bytes(1000.0)));
+        assert!(message.contains(&convert_bytes(5000.0)));
+
+        // 实际内容比声明的小
+        assert!(check_content_length(url, Some(5000), 1000).is_some());
+    }
+
+    #[test]
+    fn test_substring_desc_graphemes() {
+        // 国旗、肤色修饰和 ZWJ 组合的 emoji 各算一个字素簇
+        let emoji = ["🇯🇵", "👍🏽", "👨‍👩‍👧", "é"];
+        for unit in emoji {
+            assert_eq!(unit.graphemes(true).count(), 1);
+            let text = unit.repeat(SUMMARY_TELEGRAM_LIMIT + 10);
+            let result = substring_desc_with_truncation(&text, true);
+            let inner = result
+                .strip_prefix("<blockquote expandable>")
+                .and_then(|s| s.strip_suffix("……</blockquote>"))
+                .unwrap();
+            // 截断在字素簇边界上，且 UTF-16 长度不超过 Telegram 上限
+            let keep = SUMMARY_TELEGRAM_LIMIT / unit.encode_utf16().count();
+            assert_eq!(inner, unit.repeat(keep));
+            assert!(inner.encode_utf16().count() <= SUMMARY_TELEGRAM_LIMIT);
+        }
+
+        // 字素簇数量未超过正常限制，但 UTF-16 长度超过 Telegram 上限时仍然截断
+        let family = "👨‍👩‍👧".repeat(SUMMARY_NORMAL_LIMIT);
+        assert!(family.encode_utf16().count() > SUMMARY_TELEGRAM_LIMIT);
+        let result = substring_desc_with_truncation(&family, true);
+        let inner = result
+            .strip_prefix("<blockquote expandable>")
+            .and_then(|s| s.strip_suffix("……</blockquote>"))
+            .unwrap();
+        let keep = SUMMARY_TELEGRAM_LIMIT / "👨‍👩‍👧".encode_utf16().count();
+        assert_eq!(inner, "👨‍👩‍👧".repeat(keep));
+
+        // 字素簇数量未超过正常限制时不折叠，即使 char 数量超过
+        let flags = "🇨🇳".repeat(SUMMARY_NORMAL_LIMIT);
+        assert!(flags.chars().count() > SUMMARY_NORMAL_LIMIT);
+        assert_eq!(substring_desc_with_truncation(&flags, true), flags);
+
+        // CJK 与 emoji 混合
+        let mixed = "中文👍🏽テキスト🇯🇵".repeat(SUMMARY_TELEGRAM_LIMIT / 5);
+        let result = substring_desc_with_truncation(&mixed, true);
+        let inner = result
+            .strip_prefix("<blockquote expandable>")
+            .and_then(|s| s.strip_suffix("……</blockquote>"))
+            .unwrap();
+        let mut utf16_len = 0;
+        let expected: String = mixed
+            .graphemes(true)
+            .take_while(|g| {
+                utf16_len += g.encode_utf16().count();
+                utf16_len <= SUMMARY_TELEGRAM_LIMIT
+            })
+            .collect();
+        assert_eq!(inner, expected);
+        assert!(inner.graphemes(true).count() < SUMMARY_TELEGRAM_LIMIT);
+    }
+
     #[test]
     fn test_extract_filename_from_url() {
         let test_cases = vec![
@@ -558,6 +1585,38 @@ mod tests {
                 "image.png",
             ),
             ("https://example.com/", "application/unknown", "file"),
+            // Pixiv 缩略图去掉尺寸后缀，代理返回 webp 时使用 webp 扩展名
+            (
+                "https://i.pximg.net/img-master/img/2024/01/01/00/00/00/123_p0_master1200.jpg",
+                "image/jpeg",
+                "123_p0.jpg",
+            ),
+            (
+                "https://i.pixiv.re/img-master/img/2024/01/01/00/00/00/123_p1_master1200.jpg",
+                "image/webp",
+                "123_p1.webp",
+            ),
+            (
+                "https://i.pximg.net/c/250x250_80_a2/img-master/img/2024/01/01/00/00/00/123_p0_square1200.jpg",
+                "application/unknown",
+                "123_p0.jpg",
+            ),
+            // 原图和不符合格式的文件名不变
+            (
+                "https://i.pximg.net/img-original/img/2024/01/01/00/00/00/123_p0.png",
+                "image/png",
+                "123_p0.png",
+            ),
+            (
+                "https://example.com/_master1200.jpg",
+                "image/jpeg",
+                "_master1200.jpg",
+            ),
+            (
+                "https://example.com/a_masterpiece.jpg",
+                "image/jpeg",
+                "a_masterpiece.jpg",
+            ),
         ];
 
         for (url, content_type, expected) in test_cases {
@@ -570,12 +1629,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_content_disposition_filename() {
+        let cases = [
+            // 带引号
+            (r#"attachment; filename="image.png""#, Some("image.png")),
+            (
+                r#"attachment; filename="a; b \"c\".png""#,
+                Some(r#"a; b "c".png"#),
+            ),
+            // 不带引号
+            ("attachment; filename=image.png", Some("image.png")),
+            ("inline;filename=video.mp4 ", Some("video.mp4")),
+            // RFC 5987 编码，优先于 filename
+            (
+                r#"attachment; filename="fallback.jpg"; filename*=UTF-8''%E5%9B%BE%E7%89%87.jpg"#,
+                Some("图片.jpg"),
+            ),
+            ("attachment; FILENAME*=utf-8'en'a%20b.gif", Some("a b.gif")),
+            (
+                "attachment; filename*=ISO-8859-1''caf%E9.txt",
+                Some("café.txt"),
+            ),
+            // 去除路径
+            (r#"attachment; filename="../../etc/passwd""#, Some("passwd")),
+            // 无效或缺失
+            ("attachment", None),
+            (r#"attachment; filename="""#, None),
+            ("attachment; filename*=UTF-8''%FF", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(
+                parse_content_disposition_filename(value).as_deref(),
+                expected,
+                "value: {}",
+                value
+            );
+        }
+    }
+
     #[test]
     fn test_get_file_extension_from_content_type() {
         let test_cases = vec![
             // 已知格式
             ("image/jpeg", Some("jpg")),
             ("image/png", Some("png")),
+            ("image/avif", Some("avif")),
+            ("image/heic", Some("heic")),
+            ("image/svg+xml", Some("svg")),
             ("video/mp4", Some("mp4")),
             ("audio/mpeg", Some("mp3")),
             ("application/pdf", Some("pdf")),
@@ -598,6 +1699,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_media_kind() {
+        let test_cases = vec![
+            ("image/jpeg", MediaKind::Image),
+            ("image/gif", MediaKind::Image),
+            ("video/mp4", MediaKind::Video),
+            ("audio/mpeg", MediaKind::Audio),
+            ("application/pdf", MediaKind::Other),
+            ("application/octet-stream", MediaKind::Other),
+        ];
+
+        for (content_type, expected) in test_cases {
+            assert_eq!(
+                MediaKind::from_content_type(content_type),
+                expected,
+                "Failed for content-type: {}",
+                content_type
+            );
+        }
+
+        // AVIF、HEIC 等仍属于图片，但无法作为图片显示
+        assert_eq!(MediaKind::from_content_type("image/avif"), MediaKind::Image);
+        assert!(MediaKind::is_unsupported_image("image/avif"));
+        assert!(MediaKind::is_unsupported_image("image/HEIC"));
+        assert!(MediaKind::is_unsupported_image(
+            "image/svg+xml; charset=utf-8"
+        ));
+        assert!(!MediaKind::is_unsupported_image("image/jpeg"));
+        assert!(!MediaKind::is_unsupported_image("image/webp"));
+        assert_eq!(
+            guess_content_type_from_url("https://example.com/a.avif").as_deref(),
+            Some("image/avif")
+        );
+
+        assert_eq!(MediaKind::from_name("image"), Some(MediaKind::Image));
+        assert_eq!(MediaKind::from_name("Video"), Some(MediaKind::Video));
+        assert_eq!(MediaKind::from_name("audio"), Some(MediaKind::Audio));
+        assert_eq!(MediaKind::from_name("other"), None);
+        assert_eq!(MediaKind::from_name(""), None);
+    }
+
+    #[test]
+    fn test_is_animated_webp() {
+        // 静态 WEBP：只有一个 VP8L 块
+        let static_webp: &[u8] =
+            b"RIFF\x12\x00\x00\x00WEBPVP8L\x05\x00\x00\x00\x2f\x00\x00\x00\x00\x00";
+        assert!(!is_animated_webp(static_webp));
+
+        // 动画 WEBP：VP8X 块之后是 ANIM 块
+        let animated_webp: &[u8] = b"RIFF\x24\x00\x00\x00WEBPVP8X\x0a\x00\x00\x00\x02\x00\x00\x00\x00\x00\x00\x00\x00\x00ANIM\x06\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+        assert!(is_animated_webp(animated_webp));
+
+        // 奇数长度的块需要跳过填充字节
+        let padded: &[u8] =
+            b"RIFF\x1c\x00\x00\x00WEBPEXIF\x01\x00\x00\x00\x00\x00ANIM\x06\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+        assert!(is_animated_webp(padded));
+
+        // 不完整或不是 WEBP
+        assert!(!is_animated_webp(b"RIFF\x00\x00\x00\x00WEBP"));
+        assert!(!is_animated_webp(b"RIFF\x00\x00\x00\x00WAVEANIM"));
+        assert!(!is_animated_webp(b"GIF89a"));
+        assert!(!is_animated_webp(b""));
+        // 块长度溢出
+        assert!(!is_animated_webp(
+            b"RIFF\x00\x00\x00\x00WEBPVP8 \xff\xff\xff\xff"
+        ));
+    }
+
     #[test]
     fn test_validate_image_dimensions() {
         // 创建一个简单的1x1 PNG图片数据 (最小的有效PNG)
@@ -614,5 +1783,18 @@ mod tests {
         // 测试无效数据
         let invalid_data = vec![0x00, 0x01, 0x02];
         assert!(validate_image_dimensions(&invalid_data).is_err());
+
+        // 只有能读取尺寸且超出限制的图片才算超出尺寸
+        assert!(!is_oversize_image(&valid_png));
+        assert!(!is_oversize_image(&invalid_data));
+        let mut long_png = valid_png.clone();
+        long_png[16..24].copy_from_slice(&[0, 0, 0, 100, 0, 0, 0x2E, 0xE0]);
+        assert!(validate_image_dimensions(&long_png).is_err());
+        assert!(is_oversize_image(&long_png));
+
+        assert!(!exceeds_photo_limits(1280, 720));
+        assert!(exceeds_photo_limits(6000, 5000));
+        assert!(exceeds_photo_limits(100, 2500));
+        assert!(!exceeds_photo_limits(0, 100));
     }
 }
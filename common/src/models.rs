@@ -1,11 +1,64 @@
 /// 处理器解析结果
-#[derive(Debug, Clone)]
+/// 其他 crate 需通过 [`ProcessorResultMedia::new`] 和 `with_*` 方法构建，新增字段时无需修改调用处
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct ProcessorResultMedia {
     pub caption: String,
     pub urls: Vec<String>,
     pub spoiler: bool,
     /// 原始URL列表，用于下载时使用（如果为空则使用urls）
     pub original_urls: Option<Vec<String>>,
+    /// 媒体的 content-type 提示（如 `image/gif`），URL没有扩展名时用于选择发送方式
+    pub content_type: Option<String>,
+    /// 放不进媒体说明文字的后续内容，在媒体之后作为文本发送
+    pub overflow_text: Option<String>,
+    /// 媒体来源的页面链接，处理器没有设置时为匹配到的链接
+    pub source_url: Option<String>,
+}
+
+impl ProcessorResultMedia {
+    /// 只有说明文字、没有媒体的结果
+    pub fn new(caption: impl Into<String>) -> Self {
+        Self {
+            caption: caption.into(),
+            urls: Vec::new(),
+            spoiler: false,
+            original_urls: None,
+            content_type: None,
+            overflow_text: None,
+            source_url: None,
+        }
+    }
+
+    pub fn with_urls(mut self, urls: Vec<String>) -> Self {
+        self.urls = urls;
+        self
+    }
+
+    pub fn with_spoiler(mut self, spoiler: bool) -> Self {
+        self.spoiler = spoiler;
+        self
+    }
+
+    pub fn with_original_urls(mut self, original_urls: Vec<String>) -> Self {
+        self.original_urls = Some(original_urls);
+        self
+    }
+
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    pub fn with_overflow_text(mut self, overflow_text: impl Into<String>) -> Self {
+        self.overflow_text = Some(overflow_text.into());
+        self
+    }
+
+    pub fn with_source_url(mut self, source_url: impl Into<String>) -> Self {
+        self.source_url = Some(source_url.into());
+        self
+    }
 }
 
 /// Rich Message 处理结果
@@ -26,11 +79,78 @@ pub enum ProcessorResult {
     Rich(ProcessorResultRich),
 }
 
+/// 处理器错误类型，便于调用方区分处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessorErrorKind {
+    /// 网络请求失败或超时
+    Network,
+    /// 响应内容解析失败
+    Parse,
+    /// 内容不存在或已删除
+    NotFound,
+    /// 无权访问（私密或需要登录）
+    Forbidden,
+    /// 请求过于频繁，需要稍后再试
+    RateLimited,
+    /// 其他错误
+    #[default]
+    Unknown,
+}
+
+impl ProcessorErrorKind {
+    /// 根据 HTTP 状态码推断错误类型
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            404 | 410 => Self::NotFound,
+            401 | 403 => Self::Forbidden,
+            429 => Self::RateLimited,
+            400..=599 => Self::Network,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// 从 anyhow 错误中推断错误类型
+    ///
+    /// 优先使用内部的 [`ProcessorError`]，其次根据 reqwest 错误判断
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        if let Some(e) = error.downcast_ref::<ProcessorError>() {
+            e.kind
+        } else if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+            Self::from_reqwest(e)
+        } else {
+            Self::Unknown
+        }
+    }
+
+    fn from_reqwest(error: &reqwest::Error) -> Self {
+        match error.status() {
+            Some(status) => Self::from_status(status.as_u16()),
+            None if error.is_decode() => Self::Parse,
+            None => Self::Network,
+        }
+    }
+}
+
+impl std::fmt::Display for ProcessorErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Network => "网络错误",
+            Self::Parse => "解析错误",
+            Self::NotFound => "内容不存在",
+            Self::Forbidden => "无权访问",
+            Self::RateLimited => "请求过于频繁",
+            Self::Unknown => "未知错误",
+        };
+        write!(f, "{}", text)
+    }
+}
+
 /// 统一的处理器错误类型
 #[derive(Debug, Clone)]
 pub struct ProcessorError {
     pub message: String,
     pub source: Option<String>,
+    pub kind: ProcessorErrorKind,
 }
 
 impl std::fmt::Display for ProcessorError {
@@ -49,6 +169,7 @@ impl ProcessorError {
         Self {
             message: message.into(),
             source: None,
+            kind: ProcessorErrorKind::Unknown,
         }
     }
 
@@ -56,19 +177,27 @@ impl ProcessorError {
         Self {
             message: message.into(),
             source: Some(source.into()),
+            kind: ProcessorErrorKind::Unknown,
         }
     }
+
+    /// 设置错误类型
+    pub fn with_kind(mut self, kind: ProcessorErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
 }
 
 impl From<anyhow::Error> for ProcessorError {
     fn from(error: anyhow::Error) -> Self {
-        ProcessorError::new(error.to_string())
+        ProcessorError::new(error.to_string()).with_kind(ProcessorErrorKind::from_error(&error))
     }
 }
 
 impl From<reqwest::Error> for ProcessorError {
     fn from(error: reqwest::Error) -> Self {
-        ProcessorError::with_source("网络请求失败", error.to_string())
+        let kind = ProcessorErrorKind::from_reqwest(&error);
+        ProcessorError::with_source("网络请求失败", error.to_string()).with_kind(kind)
     }
 }
 
@@ -86,8 +215,164 @@ pub trait LinkProcessor: Send + Sync {
 
     /// 处理匹配的链接并返回结果
     /// captures: 正则表达式的捕获组
-    async fn process_captures(&self, captures: &regex::Captures<'_>) -> ProcessorResultType;
+    /// truncation: 是否截断输出的长文本
+    async fn process_captures(
+        &self,
+        captures: &regex::Captures<'_>,
+        truncation: bool,
+    ) -> ProcessorResultType;
 
     /// 获取处理器名称
     fn name(&self) -> &'static str;
+
+    /// 附加在说明文字末尾的来源标识（SHOW_SOURCE_FOOTER），默认使用处理器名称
+    fn footer(&self) -> String {
+        format!("🔗 {}", self.name())
+    }
+
+    /// 处理器支持的链接示例，用于帮助信息和测试
+    fn example_urls(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// 匹配的链接中必然包含其一的小写字符串，用于快速跳过不含链接的消息
+    /// 为空时不跳过任何消息
+    fn link_hints(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// 清除处理器的缓存，返回清除的条目数量
+    /// 没有缓存的处理器返回 None
+    fn clear_cache(&self) -> Option<usize> {
+        None
+    }
+
+    /// 清除处理器缓存中已过期的条目，返回清除的条目数量
+    /// 没有缓存的处理器返回 None
+    fn sweep_expired(&self) -> Option<usize> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_processor_result_media_builder() {
+        assert_eq!(
+            ProcessorResultMedia::new("caption"),
+            ProcessorResultMedia {
+                caption: "caption".to_string(),
+                urls: Vec::new(),
+                spoiler: false,
+                original_urls: None,
+                content_type: None,
+                overflow_text: None,
+                source_url: None,
+            }
+        );
+
+        let urls = vec!["https://example.com/1.jpg".to_string()];
+        assert_eq!(
+            ProcessorResultMedia::new("caption")
+                .with_urls(urls.clone())
+                .with_spoiler(true)
+                .with_original_urls(urls.clone())
+                .with_content_type("image/gif")
+                .with_overflow_text("overflow")
+                .with_source_url("https://example.com/page"),
+            ProcessorResultMedia {
+                caption: "caption".to_string(),
+                urls: urls.clone(),
+                spoiler: true,
+                original_urls: Some(urls),
+                content_type: Some("image/gif".to_string()),
+                overflow_text: Some("overflow".to_string()),
+                source_url: Some("https://example.com/page".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_processor_error_kind() {
+        let cases = [
+            (ProcessorErrorKind::Network, "网络错误"),
+            (ProcessorErrorKind::Parse, "解析错误"),
+            (ProcessorErrorKind::NotFound, "内容不存在"),
+            (ProcessorErrorKind::Forbidden, "无权访问"),
+            (ProcessorErrorKind::RateLimited, "请求过于频繁"),
+            (ProcessorErrorKind::Unknown, "未知错误"),
+        ];
+
+        for (kind, expected) in cases {
+            assert_eq!(kind.to_string(), expected);
+
+            // 设置类型不影响错误信息的显示
+            let error = ProcessorError::with_source("处理失败", "detail").with_kind(kind);
+            assert_eq!(error.kind, kind);
+            assert_eq!(error.to_string(), "处理失败: detail");
+        }
+
+        // 默认为 Unknown
+        assert_eq!(ProcessorError::new("x").kind, ProcessorErrorKind::Unknown);
+        assert_eq!(
+            ProcessorError::with_source("x", "y").kind,
+            ProcessorErrorKind::Unknown
+        );
+    }
+
+    #[test]
+    fn test_processor_error_kind_from_status() {
+        assert_eq!(
+            ProcessorErrorKind::from_status(404),
+            ProcessorErrorKind::NotFound
+        );
+        assert_eq!(
+            ProcessorErrorKind::from_status(410),
+            ProcessorErrorKind::NotFound
+        );
+        assert_eq!(
+            ProcessorErrorKind::from_status(401),
+            ProcessorErrorKind::Forbidden
+        );
+        assert_eq!(
+            ProcessorErrorKind::from_status(403),
+            ProcessorErrorKind::Forbidden
+        );
+        assert_eq!(
+            ProcessorErrorKind::from_status(429),
+            ProcessorErrorKind::RateLimited
+        );
+        assert_eq!(
+            ProcessorErrorKind::from_status(500),
+            ProcessorErrorKind::Network
+        );
+        assert_eq!(
+            ProcessorErrorKind::from_status(200),
+            ProcessorErrorKind::Unknown
+        );
+    }
+
+    #[test]
+    fn test_processor_error_kind_from_error() {
+        // 包装在 anyhow 中的 ProcessorError 保留原类型
+        let error = anyhow::Error::from(
+            ProcessorError::new("not found").with_kind(ProcessorErrorKind::NotFound),
+        );
+        assert_eq!(
+            ProcessorErrorKind::from_error(&error),
+            ProcessorErrorKind::NotFound
+        );
+        let converted = ProcessorError::from(error);
+        assert_eq!(converted.kind, ProcessorErrorKind::NotFound);
+        assert_eq!(converted.message, "not found");
+
+        // 普通错误为 Unknown
+        let error = anyhow::anyhow!("something wrong");
+        assert_eq!(
+            ProcessorErrorKind::from_error(&error),
+            ProcessorErrorKind::Unknown
+        );
+    }
 }
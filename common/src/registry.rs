@@ -0,0 +1,622 @@
+//! 处理器注册表
+//!
+//! 持有一组处理器并运行它们，返回 [`ProcessorResult`](crate::models::ProcessorResult)，
+//! 不依赖 Telegram，可在其他项目中嵌入使用。
+
+use regex::RegexSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::models::{
+    LinkProcessor, ProcessorError, ProcessorErrorKind, ProcessorResult, ProcessorResultMedia,
+    ProcessorResultType,
+};
+use crate::settings::{DEFAULT_MAX_SCAN_LENGTH, Settings};
+
+/// 默认的单个处理器超时时间
+pub const DEFAULT_PROCESSOR_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// 单个链接的解析结果
+#[derive(Debug)]
+pub struct LinkResolution {
+    /// 处理该链接的处理器名称
+    pub processor: &'static str,
+    /// 匹配到的原始链接文本
+    pub matched: String,
+    /// 处理结果
+    pub result: ProcessorResultType,
+}
+
+/// [`ProcessorRegistry::process`] 的选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessOptions {
+    /// 只处理文本的前多少个字符
+    pub max_scan_length: usize,
+    /// 是否截断处理器输出的长文本
+    pub truncation: bool,
+    /// 是否将同一处理器连续的媒体结果合并为一个相册
+    pub combine_same_source: bool,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        Self {
+            max_scan_length: DEFAULT_MAX_SCAN_LENGTH,
+            truncation: true,
+            combine_same_source: false,
+        }
+    }
+}
+
+impl ProcessOptions {
+    /// 使用配置中的 MAX_SCAN_LENGTH 和 COMBINE_SAME_SOURCE
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            max_scan_length: settings.max_scan_length,
+            combine_same_source: settings.combine_same_source,
+            ..Self::default()
+        }
+    }
+
+    /// 设置是否截断处理器输出的长文本
+    pub fn truncation(mut self, truncation: bool) -> Self {
+        self.truncation = truncation;
+        self
+    }
+}
+
+/// 处理器列表和用于快速匹配的 RegexSet，替换处理器时整体替换
+struct ProcessorSet {
+    processors: Vec<Arc<dyn LinkProcessor>>,
+    regex_set: RegexSet,
+}
+
+impl ProcessorSet {
+    fn new(processors: Vec<Box<dyn LinkProcessor>>) -> Self {
+        let processors: Vec<Arc<dyn LinkProcessor>> =
+            processors.into_iter().map(Arc::from).collect();
+        Self {
+            regex_set: build_regex_set(&processors),
+            processors,
+        }
+    }
+}
+
+/// 处理器注册表，持有处理器列表和用于快速匹配的 RegexSet
+pub struct ProcessorRegistry {
+    current: RwLock<Arc<ProcessorSet>>,
+    timeout: Duration,
+}
+
+impl ProcessorRegistry {
+    /// 使用给定的处理器列表创建注册表
+    pub fn new(processors: Vec<Box<dyn LinkProcessor>>) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(ProcessorSet::new(processors))),
+            timeout: DEFAULT_PROCESSOR_TIMEOUT,
+        }
+    }
+
+    /// 替换处理器列表并重新构建 RegexSet，超时设置保持不变
+    /// 注册表共享时也可以在运行时替换，正在进行的处理继续使用替换前的处理器
+    pub fn set_processors(&self, processors: Vec<Box<dyn LinkProcessor>>) {
+        let set = Arc::new(ProcessorSet::new(processors));
+        *self.current.write().unwrap() = set;
+    }
+
+    /// 当前生效的处理器列表，不持有锁
+    fn current(&self) -> Arc<ProcessorSet> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// 设置单个处理器的超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 获取已注册的处理器列表
+    pub fn processors(&self) -> Vec<Arc<dyn LinkProcessor>> {
+        self.current().processors.clone()
+    }
+
+    /// 快速检查文本是否可能包含任一处理器的链接，根据各处理器的 [`LinkProcessor::link_hints`] 判断
+    /// 有处理器没有提供提示时总是返回 true
+    pub fn may_contain_links(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.current().processors.iter().any(|p| {
+            let hints = p.link_hints();
+            hints.is_empty() || hints.iter().any(|hint| text.contains(hint))
+        })
+    }
+
+    /// 按名称查找处理器，忽略大小写
+    pub fn find_processor(&self, name: &str) -> Option<Arc<dyn LinkProcessor>> {
+        let name = name.trim();
+        self.current()
+            .processors
+            .iter()
+            .find(|p| p.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// 按选项处理文本中的链接：过滤不含链接特征的文本，只扫描文本前部，
+    /// 解析后按需合并同一处理器的媒体结果
+    pub async fn process(&self, text: &str, options: &ProcessOptions) -> Vec<LinkResolution> {
+        // 快速检查是否包含任何可能的链接特征
+        if !self.may_contain_links(text) {
+            return Vec::new();
+        }
+        // 如果文本过长，只处理前面部分（MAX_SCAN_LENGTH）
+        let text = scan_prefix(text, options.max_scan_length);
+
+        let resolutions = self.resolve_links(text, options.truncation).await;
+        if options.combine_same_source {
+            combine_same_source(resolutions)
+        } else {
+            resolutions
+        }
+    }
+
+    /// 解析文本中的所有链接
+    ///
+    /// 每次处理都有超时限制，超时的链接返回错误结果，不影响其他链接
+    /// `truncation` 控制处理器是否截断输出的长文本
+    pub async fn resolve_links(&self, text: &str, truncation: bool) -> Vec<LinkResolution> {
+        let mut results = Vec::new();
+        let current = self.current();
+
+        // 使用 RegexSet 快速检查是否有任何匹配
+        if !current.regex_set.is_match(text) {
+            return results;
+        }
+
+        // 只对匹配的处理器进行详细匹配
+        for match_index in current.regex_set.matches(text).into_iter() {
+            let processor = current.processors[match_index].as_ref();
+            results.extend(self.resolve_with(processor, text, truncation).await);
+        }
+
+        results
+    }
+
+    /// 只使用指定的处理器解析文本中的链接
+    pub async fn resolve_with(
+        &self,
+        processor: &dyn LinkProcessor,
+        text: &str,
+        truncation: bool,
+    ) -> Vec<LinkResolution> {
+        let mut results = Vec::new();
+
+        // 使用对应的正则表达式进行详细匹配
+        for captures in processor.regex().captures_iter(text) {
+            let matched = captures.get(0).unwrap().as_str().to_string();
+            log::info!("Processing link with {}: {}", processor.name(), matched);
+
+            // 超时视为处理失败
+            let mut result = tokio::time::timeout(
+                self.timeout,
+                processor.process_captures(&captures, truncation),
+            )
+            .await
+            .unwrap_or_else(|_| {
+                Err(
+                    ProcessorError::new(format!("处理超时（{} 秒）", self.timeout.as_secs()))
+                        .with_kind(ProcessorErrorKind::Network),
+                )
+            });
+
+            // 处理器没有提供来源链接时使用匹配到的链接
+            if let Ok(ProcessorResult::Media(media)) = &mut result
+                && media.source_url.is_none()
+            {
+                media.source_url = Some(matched.clone());
+            }
+
+            results.push(LinkResolution {
+                processor: processor.name(),
+                matched,
+                result,
+            });
+        }
+
+        results
+    }
+}
+
+/// 根据处理器的正则表达式构建 RegexSet，索引与处理器顺序一致
+fn build_regex_set(processors: &[Arc<dyn LinkProcessor>]) -> RegexSet {
+    let patterns: Vec<&str> = processors.iter().map(|p| p.pattern()).collect();
+    RegexSet::new(&patterns).expect("Failed to create RegexSet")
+}
+
+/// 截取文本的前 `max_chars` 个字符，按字符边界截断
+pub fn scan_prefix(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// 相册最多包含的媒体数量
+const MAX_ALBUM_ITEMS: usize = 10;
+
+/// 将同一处理器连续的媒体结果合并为一个相册，合并后超过相册数量上限时不合并
+pub fn combine_same_source(resolutions: Vec<LinkResolution>) -> Vec<LinkResolution> {
+    let mut combined: Vec<LinkResolution> = Vec::new();
+    for resolution in resolutions {
+        if let Some(last) = combined.last_mut()
+            && last.processor == resolution.processor
+            && let (Ok(ProcessorResult::Media(merged)), Ok(ProcessorResult::Media(media))) =
+                (&mut last.result, &resolution.result)
+            && !merged.urls.is_empty()
+            && !media.urls.is_empty()
+            && merged.urls.len() + media.urls.len() <= MAX_ALBUM_ITEMS
+        {
+            merge_media(merged, media);
+            last.matched.push('\n');
+            last.matched.push_str(&resolution.matched);
+            continue;
+        }
+        combined.push(resolution);
+    }
+    combined
+}
+
+/// 将媒体结果合并到 `target`，说明文字依次拼接
+fn merge_media(target: &mut ProcessorResultMedia, media: &ProcessorResultMedia) {
+    if !media.caption.is_empty() {
+        if !target.caption.is_empty() {
+            target.caption.push_str("\n\n");
+        }
+        target.caption.push_str(&media.caption);
+    }
+
+    // 原始URL与媒体URL一一对应，只有一方提供时用另一方的媒体URL补齐
+    if target.original_urls.is_some() || media.original_urls.is_some() {
+        let mut original_urls = target
+            .original_urls
+            .take()
+            .unwrap_or_else(|| target.urls.clone());
+        original_urls.extend(
+            media
+                .original_urls
+                .clone()
+                .unwrap_or_else(|| media.urls.clone()),
+        );
+        target.original_urls = Some(original_urls);
+    }
+    target.urls.extend(media.urls.iter().cloned());
+    target.spoiler |= media.spoiler;
+    if target.content_type != media.content_type {
+        target.content_type = None;
+    }
+    if let Some(overflow_text) = &media.overflow_text {
+        target.overflow_text = Some(match target.overflow_text.take() {
+            Some(text) => format!("{}\n\n{}", text, overflow_text),
+            None => overflow_text.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProcessor;
+
+    fn text_of(resolution: &LinkResolution) -> &str {
+        match &resolution.result {
+            Ok(ProcessorResult::Text(text)) => text,
+            other => panic!("应该返回文本结果，实际: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_links() {
+        let registry = ProcessorRegistry::new(vec![
+            Box::new(MockProcessor::new("A", r"https://a\.example/\d+")),
+            Box::new(MockProcessor::new("B", r"https://b\.example/\d+")),
+        ]);
+
+        let text = "https://b.example/1 https://a.example/2 https://a.example/3";
+        let results = registry.resolve_links(text, true).await;
+
+        // 按处理器注册顺序返回，同一处理器内按出现顺序
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].processor, "A");
+        assert_eq!(results[0].matched, "https://a.example/2");
+        assert_eq!(text_of(&results[0]), "A:https://a.example/2");
+        assert_eq!(text_of(&results[1]), "A:https://a.example/3");
+        assert_eq!(results[2].processor, "B");
+        assert_eq!(text_of(&results[2]), "B:https://b.example/1");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_links_source_url() {
+        let registry = ProcessorRegistry::new(vec![Box::new(
+            MockProcessor::new("A", r"https://a\.example/\d+").media(),
+        )]);
+
+        // 媒体结果的来源为匹配到的页面链接，而不是媒体链接
+        let results = registry
+            .resolve_links("看看 https://a.example/1", true)
+            .await;
+        match &results[0].result {
+            Ok(ProcessorResult::Media(media)) => {
+                assert_eq!(media.urls, vec!["https://a.example/1.jpg"]);
+                assert_eq!(media.source_url.as_deref(), Some("https://a.example/1"));
+            }
+            other => panic!("应该是媒体结果，实际: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_processors() {
+        let registry = Arc::new(
+            ProcessorRegistry::new(vec![Box::new(MockProcessor::new(
+                "A",
+                r"https://a\.example/\d+",
+            ))])
+            .with_timeout(Duration::from_secs(5)),
+        );
+        let text = "https://a.example/1 https://b.example/2";
+
+        let results = registry.resolve_links(text, true).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].processor, "A");
+
+        // 在其他任务中通过共享的注册表替换，替换后只匹配新的处理器
+        let shared = Arc::clone(&registry);
+        tokio::spawn(async move {
+            shared.set_processors(vec![
+                Box::new(MockProcessor::new("B", r"https://b\.example/\d+")),
+                Box::new(MockProcessor::new("C", r"https://c\.example/\d+")),
+            ]);
+        })
+        .await
+        .unwrap();
+        let results = registry.resolve_links(text, true).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(text_of(&results[0]), "B:https://b.example/2");
+        assert!(registry.find_processor("A").is_none());
+        assert_eq!(registry.processors().len(), 2);
+        assert_eq!(registry.timeout, Duration::from_secs(5));
+
+        // RegexSet 索引与新的处理器顺序一致
+        let results = registry.resolve_links("https://c.example/3", true).await;
+        assert_eq!(results[0].processor, "C");
+
+        registry.set_processors(Vec::new());
+        assert!(registry.resolve_links(text, true).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_links_no_match() {
+        let registry = ProcessorRegistry::new(vec![Box::new(MockProcessor::new(
+            "A",
+            r"https://a\.example/\d+",
+        ))]);
+
+        assert!(
+            registry
+                .resolve_links("nothing here", true)
+                .await
+                .is_empty()
+        );
+        assert!(registry.resolve_links("", true).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_links_error_and_timeout() {
+        let registry = ProcessorRegistry::new(vec![
+            Box::new(
+                MockProcessor::new("Slow", r"https://slow\.example/\d+")
+                    .delay(Duration::from_secs(10)),
+            ),
+            Box::new(MockProcessor::new("Fail", r"https://fail\.example/\d+").failing()),
+            Box::new(MockProcessor::new("Fast", r"https://fast\.example/\d+")),
+        ])
+        .with_timeout(Duration::from_millis(100));
+
+        let text = "https://slow.example/1 https://fail.example/2 https://fast.example/3";
+        let started = std::time::Instant::now();
+        let results = registry.resolve_links(text, true).await;
+
+        // 超时应该在慢处理器完成之前触发
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(results.len(), 3);
+
+        let err = results[0].result.as_ref().unwrap_err();
+        assert!(err.message.contains("超时"));
+        assert_eq!(err.kind, ProcessorErrorKind::Network);
+        let err = results[1].result.as_ref().unwrap_err();
+        assert_eq!(err.message, "mock failure");
+        assert_eq!(text_of(&results[2]), "Fast:https://fast.example/3");
+    }
+
+    #[tokio::test]
+    async fn test_find_processor_and_resolve_with() {
+        let registry = ProcessorRegistry::new(vec![
+            Box::new(MockProcessor::new("Alpha", r"https://a\.example/\d+")),
+            Box::new(MockProcessor::new("Beta Two", r"https://b\.example/\d+")),
+        ]);
+
+        assert_eq!(registry.find_processor("alpha").unwrap().name(), "Alpha");
+        assert_eq!(
+            registry.find_processor(" beta two ").unwrap().name(),
+            "Beta Two"
+        );
+        assert!(registry.find_processor("Gamma").is_none());
+
+        // 只运行指定的处理器
+        let processor = registry.find_processor("Beta Two").unwrap();
+        let text = "https://a.example/1 https://b.example/2";
+        let results = registry.resolve_with(processor.as_ref(), text, true).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(text_of(&results[0]), "Beta Two:https://b.example/2");
+
+        assert!(
+            registry
+                .resolve_with(processor.as_ref(), "https://a.example/1", true)
+                .await
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process() {
+        let registry = ProcessorRegistry::new(vec![
+            Box::new(MockProcessor::new("A", r"https://a\.example/\d+")),
+            Box::new(MockProcessor::new("B", r"https://b\.example/\d+")),
+        ]);
+        let text = "https://a.example/1 https://b.example/2";
+
+        let results = registry.process(text, &ProcessOptions::default()).await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(text_of(&results[0]), "A:https://a.example/1");
+        assert_eq!(text_of(&results[1]), "B:https://b.example/2");
+
+        // 只处理扫描长度内的链接
+        let options = ProcessOptions {
+            max_scan_length: 20,
+            ..ProcessOptions::default()
+        };
+        let results = registry.process(text, &options).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].processor, "A");
+
+        // 文本结果不会被合并
+        let options = ProcessOptions {
+            combine_same_source: true,
+            ..ProcessOptions::default()
+        };
+        let text = "https://a.example/1 https://a.example/2";
+        assert_eq!(registry.process(text, &options).await.len(), 2);
+
+        assert!(
+            registry
+                .process("", &ProcessOptions::default())
+                .await
+                .is_empty()
+        );
+
+        let options = ProcessOptions::from_settings(&Settings {
+            max_scan_length: 100,
+            combine_same_source: true,
+            ..Settings::default()
+        })
+        .truncation(false);
+        assert_eq!(options.max_scan_length, 100);
+        assert!(options.combine_same_source);
+        assert!(!options.truncation);
+    }
+
+    #[tokio::test]
+    async fn test_process_truncation_per_call() {
+        let registry = ProcessorRegistry::new(vec![Box::new(
+            MockProcessor::new("A", r"https://a\.example/\d+")
+                .media()
+                .delay(Duration::from_millis(50)),
+        )]);
+        let caption_of = |resolutions: Vec<LinkResolution>| match &resolutions[0].result {
+            Ok(ProcessorResult::Media(media)) => media.caption.clone(),
+            other => panic!("应该是媒体结果，实际: {:?}", other),
+        };
+
+        // 同时处理的消息各自使用自己的截断选项
+        let truncated = ProcessOptions::default();
+        let full = ProcessOptions::default().truncation(false);
+        let (first, second) = tokio::join!(
+            registry.process("https://a.example/1", &truncated),
+            registry.process("https://a.example/2", &full),
+        );
+        assert_eq!(caption_of(first), "A:truncation=true");
+        assert_eq!(caption_of(second), "A:truncation=false");
+    }
+
+    #[test]
+    fn test_combine_same_source() {
+        let photo = |processor: &'static str, caption: &str, urls: &[&str]| LinkResolution {
+            processor,
+            matched: format!("https://example.com/{}", caption),
+            result: Ok(ProcessorResult::Media(
+                ProcessorResultMedia::new(caption)
+                    .with_urls(urls.iter().map(|s| s.to_string()).collect())
+                    .with_source_url(format!("https://example.com/{}", caption)),
+            )),
+        };
+        let media_of = |resolution: &LinkResolution| match &resolution.result {
+            Ok(ProcessorResult::Media(media)) => media.clone(),
+            other => panic!("应该是媒体结果，实际: {:?}", other),
+        };
+
+        let mut restricted = photo("Pixiv", "b", &["https://i.pixiv.re/b.jpg"]);
+        if let Ok(ProcessorResult::Media(media)) = &mut restricted.result {
+            media.spoiler = true;
+            media.original_urls = Some(vec!["https://i.pximg.net/b.jpg".to_string()]);
+        }
+        let combined = combine_same_source(vec![
+            photo("Pixiv", "a", &["https://i.pixiv.re/a.jpg"]),
+            restricted,
+            photo("X/Twitter", "c", &["https://pbs.twimg.com/c.jpg"]),
+            photo("Pixiv", "d", &["https://i.pixiv.re/d.jpg"]),
+        ]);
+
+        // 只合并连续的同一处理器结果
+        assert_eq!(combined.len(), 3);
+        let merged = media_of(&combined[0]);
+        assert_eq!(merged.caption, "a\n\nb");
+        assert_eq!(
+            merged.urls,
+            vec!["https://i.pixiv.re/a.jpg", "https://i.pixiv.re/b.jpg"]
+        );
+        assert_eq!(
+            merged.original_urls,
+            Some(vec![
+                "https://i.pixiv.re/a.jpg".to_string(),
+                "https://i.pximg.net/b.jpg".to_string()
+            ])
+        );
+        assert!(merged.spoiler);
+        // 合并后的来源为第一个链接
+        assert_eq!(merged.source_url.as_deref(), Some("https://example.com/a"));
+        assert_eq!(
+            combined[0].matched,
+            "https://example.com/a\nhttps://example.com/b"
+        );
+        assert_eq!(combined[1].processor, "X/Twitter");
+        assert_eq!(media_of(&combined[2]).caption, "d");
+
+        // 超过相册数量上限时不合并
+        let many = ["https://i.pixiv.re/1.jpg"; 6];
+        let combined =
+            combine_same_source(vec![photo("Pixiv", "a", &many), photo("Pixiv", "b", &many)]);
+        assert_eq!(combined.len(), 2);
+
+        // 没有媒体的结果不合并
+        let combined = combine_same_source(vec![
+            photo("Pixiv", "a", &["https://i.pixiv.re/a.jpg"]),
+            photo("Pixiv", "b", &[]),
+        ]);
+        assert_eq!(combined.len(), 2);
+    }
+
+    #[test]
+    fn test_scan_prefix() {
+        assert_eq!(scan_prefix("hello", 10), "hello");
+        assert_eq!(scan_prefix("hello", 5), "hello");
+        assert_eq!(scan_prefix("hello", 3), "hel");
+        assert_eq!(scan_prefix("hello", 0), "");
+
+        // 按字符计数，不会截断在多字节字符中间
+        assert_eq!(scan_prefix("链接：https://b23.tv/abc", 3), "链接：");
+        assert_eq!(scan_prefix("🎨🎨🎨", 2), "🎨🎨");
+
+        // 超出扫描长度的链接不处理
+        let text = format!("{}https://b23.tv/abc", "字".repeat(4000));
+        let prefix = scan_prefix(&text, DEFAULT_MAX_SCAN_LENGTH);
+        assert_eq!(prefix.chars().count(), DEFAULT_MAX_SCAN_LENGTH);
+        assert!(!prefix.contains("b23.tv"));
+    }
+}
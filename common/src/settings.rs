@@ -0,0 +1,837 @@
+//! 集中管理的配置项
+//!
+//! 所有配置在启动时从环境变量（及配置文件）读取一次并校验，
+//! 其他模块通过 [`settings()`] 获取，不再直接读取环境变量。
+
+use anyhow::{Result, anyhow};
+use byte_unit::Byte;
+use std::sync::OnceLock;
+use std::time::Duration;
+use url::Url;
+
+use crate::{
+    DEFAULT_MAX_FILE_SIZE, DEFAULT_PROCESSOR_TIMEOUT, SUMMARY_NORMAL_LIMIT, SUMMARY_TELEGRAM_LIMIT,
+    get_env_var,
+};
+
+/// 默认的过期缓存清理间隔
+pub const DEFAULT_CACHE_SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// 默认的下载超时时间
+pub const DEFAULT_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 默认的下载重试次数
+pub const DEFAULT_DOWNLOAD_MAX_RETRIES: u32 = 3;
+
+/// 默认的消息扫描长度（字符数）
+pub const DEFAULT_MAX_SCAN_LENGTH: usize = 4000;
+
+/// 默认的调度器工作队列长度，与 teloxide 的默认值相同
+pub const DEFAULT_DISPATCH_QUEUE_SIZE: usize = 64;
+
+/// 调度器工作队列长度的上限
+pub const MAX_DISPATCH_QUEUE_SIZE: usize = 4096;
+
+/// 调度器对更新的分组方式，同一组的更新依次处理，不同组之间并发处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DispatchDistribution {
+    /// 同一聊天的更新依次处理
+    #[default]
+    Chat,
+    /// 同一用户的更新依次处理
+    User,
+    /// 所有更新都并发处理
+    None,
+}
+
+/// 全局配置，未初始化时在首次访问时从环境变量加载
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// 类型化的配置项
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub telegram_token: Option<String>,
+    /// Telegram 代理（TELEGRAM_PROXY）
+    pub telegram_proxy: Option<String>,
+    /// 代理连接失败时改为直连（PROXY_FALLBACK_DIRECT）
+    pub proxy_fallback_direct: bool,
+    /// 最大文件大小（MAX_FILE_SIZE），默认 10MB
+    pub max_file_size: usize,
+    /// 相册图片的最小文件大小（MIN_IMAGE_BYTES），未设置时不过滤
+    pub min_image_bytes: Option<usize>,
+    /// 下载上传的单个相册的最大总大小（MAX_ALBUM_BYTES），超过时拆分发送，未设置时不拆分
+    pub max_album_bytes: Option<usize>,
+    /// 单个处理器的超时时间（PROCESSOR_TIMEOUT_SECS）
+    pub processor_timeout: Duration,
+    /// 摘要超过此长度时放入可折叠引用（SUMMARY_MAX_LENGTH）
+    pub summary_max_length: usize,
+    /// 摘要超过此长度时截断（SUMMARY_MAX_MAX_LENGTH），不超过 [`SUMMARY_TELEGRAM_LIMIT`]
+    pub summary_max_max_length: usize,
+    /// 下载文件的超时时间（DOWNLOAD_TIMEOUT），单位为秒
+    pub download_timeout: Duration,
+    /// 下载遇到连接错误、超时或 5xx 响应时的最大重试次数（DOWNLOAD_MAX_RETRIES）
+    pub download_max_retries: u32,
+    /// 过期缓存的清理间隔（CACHE_SWEEP_SECS），为 0 时不清理
+    pub cache_sweep_interval: Option<Duration>,
+    /// 只在消息的前 N 个字符中查找链接（MAX_SCAN_LENGTH）
+    pub max_scan_length: usize,
+    /// 调度器每个工作者的更新队列长度（DISPATCH_QUEUE_SIZE）
+    pub dispatch_queue_size: usize,
+    /// 调度器对更新的分组方式（DISPATCH_DISTRIBUTION）
+    pub dispatch_distribution: DispatchDistribution,
+    /// 管理员用户 ID（ADMIN_USER_IDS，逗号分隔）
+    pub admin_user_ids: Vec<u64>,
+    /// 在媒体说明末尾附加来源链接（APPEND_SOURCE_URL）
+    pub append_source_url: bool,
+    pub group_require_mention: bool,
+    /// 不处理转发的消息（SKIP_FORWARDS）
+    pub skip_forwards: bool,
+    pub show_skip_errors: bool,
+    /// 在媒体说明文字末尾附加来源标识（SHOW_SOURCE_FOOTER）
+    pub show_source_footer: bool,
+    /// 在相册说明文字末尾附加媒体数量，下载上传时附加总大小（SHOW_MEDIA_STATS）
+    pub show_media_stats: bool,
+    /// 合并同一处理器连续的媒体结果为一个相册（COMBINE_SAME_SOURCE）
+    pub combine_same_source: bool,
+    pub suppress_caption_preview: bool,
+    /// 相册的说明文字放在最后一个媒体上（CAPTION_ON_LAST），默认放在第一个
+    pub caption_on_last: bool,
+    /// 类型提示为 GIF 但下载得到 `video/mp4` 时作为动画发送（GIF_MP4_AS_ANIMATION）
+    pub gif_mp4_as_animation: bool,
+    /// 媒体组因图片尺寸被拒绝时，将超出尺寸的图片作为文件发送（OVERSIZE_PHOTO_AS_DOCUMENT）
+    pub oversize_photo_as_document: bool,
+    /// 只发送文本，媒体结果转换为说明文字和链接（TEXT_ONLY_MODE）
+    pub text_only_mode: bool,
+    /// Pixiv PHPSESSID（PIXIV_COOKIE）
+    pub pixiv_cookie: Option<String>,
+    /// Pixiv 图片代理（PIXIV_IMAGE_PROXY），未设置时使用处理器的默认代理
+    pub pixiv_image_proxy: Option<String>,
+    /// 启动时检查 PIXIV_COOKIE 是否有效（PIXIV_VALIDATE_ON_START）
+    pub pixiv_validate_on_start: bool,
+    pub nga_uid: Option<String>,
+    pub nga_cid: Option<String>,
+    /// 完整的 NGA Cookie（NGA_COOKIE），优先于 UID/CID
+    pub nga_cookie: Option<String>,
+    pub nga_attach_token: Option<String>,
+    /// 将 NGA 的 webp 附件替换为 jpg（NGA_PREFER_JPG）
+    pub nga_prefer_jpg: bool,
+    /// 移除 NGA 附件链接中的画质和尺寸参数（NGA_FORCE_FULLRES）
+    pub nga_force_fullres: bool,
+    /// 将 NGA 贴纸替换为 emoji（NGA_STICKER_EMOJI），否则移除
+    pub nga_sticker_emoji: bool,
+    /// 自定义的贴纸 emoji 映射（NGA_STICKER_EMOJI_MAP），覆盖内置映射
+    pub nga_sticker_emoji_map: Vec<(String, String)>,
+    pub nga_image_proxy: Option<String>,
+    pub nga_title_selector: Option<String>,
+    pub nga_content_selector: Option<String>,
+    pub bili_send_cover: bool,
+    /// X 链接前附加用户名，格式为 `@用户名: 链接`（X_SHOW_USERNAME）
+    pub x_show_username: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            telegram_token: None,
+            telegram_proxy: None,
+            proxy_fallback_direct: false,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            min_image_bytes: None,
+            max_album_bytes: None,
+            processor_timeout: DEFAULT_PROCESSOR_TIMEOUT,
+            summary_max_length: SUMMARY_NORMAL_LIMIT,
+            summary_max_max_length: SUMMARY_TELEGRAM_LIMIT,
+            download_timeout: DEFAULT_DOWNLOAD_TIMEOUT,
+            download_max_retries: DEFAULT_DOWNLOAD_MAX_RETRIES,
+            cache_sweep_interval: Some(DEFAULT_CACHE_SWEEP_INTERVAL),
+            max_scan_length: DEFAULT_MAX_SCAN_LENGTH,
+            dispatch_queue_size: DEFAULT_DISPATCH_QUEUE_SIZE,
+            dispatch_distribution: DispatchDistribution::Chat,
+            admin_user_ids: Vec::new(),
+            append_source_url: false,
+            group_require_mention: false,
+            skip_forwards: false,
+            show_skip_errors: false,
+            show_source_footer: false,
+            show_media_stats: false,
+            combine_same_source: false,
+            suppress_caption_preview: false,
+            caption_on_last: false,
+            gif_mp4_as_animation: false,
+            oversize_photo_as_document: false,
+            text_only_mode: false,
+            pixiv_cookie: None,
+            pixiv_image_proxy: None,
+            pixiv_validate_on_start: false,
+            nga_uid: None,
+            nga_cid: None,
+            nga_cookie: None,
+            nga_attach_token: None,
+            nga_prefer_jpg: false,
+            nga_force_fullres: false,
+            nga_sticker_emoji: false,
+            nga_sticker_emoji_map: Vec::new(),
+            nga_image_proxy: None,
+            nga_title_selector: None,
+            nga_content_selector: None,
+            bili_send_cover: false,
+            x_show_username: false,
+        }
+    }
+}
+
+impl Settings {
+    /// 从环境变量（及配置文件）加载配置
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(get_env_var)
+    }
+
+    /// 通过给定的查找函数加载配置，所有无效的配置项会一起报告
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        // 空值视为未设置
+        let get = |name: &str| {
+            lookup(name)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let flag =
+            |name: &str| get(name).is_some_and(|value| matches!(value.as_str(), "1" | "true"));
+
+        let mut errors = Vec::new();
+        let mut check = |name: &str, result: Result<()>| {
+            if let Err(e) = result {
+                errors.push(format!("{}: {}", name, e));
+            }
+        };
+
+        // 设置 PROXY_OPTIONAL 时，无效的代理只记录警告并按未设置处理
+        let proxy_optional = flag("PROXY_OPTIONAL");
+        let mut proxy = |name: &str, validate: fn(&str) -> Result<()>| {
+            let value = get(name)?;
+            match validate(&value) {
+                Ok(()) => Some(value),
+                Err(e) if proxy_optional => {
+                    log::warn!("{}: {:#}, continuing without it", name, e);
+                    None
+                }
+                Err(e) => {
+                    check(name, Err(e));
+                    None
+                }
+            }
+        };
+
+        let telegram_proxy = proxy("TELEGRAM_PROXY", validate_proxy_url);
+        let pixiv_image_proxy = proxy("PIXIV_IMAGE_PROXY", validate_proxy_base_url);
+        let nga_image_proxy = proxy("NGA_IMAGE_PROXY", validate_proxy_base_url);
+
+        let mut max_file_size = DEFAULT_MAX_FILE_SIZE;
+        if let Some(value) = get("MAX_FILE_SIZE") {
+            match parse_size(&value) {
+                Some(size) => max_file_size = size,
+                None => check("MAX_FILE_SIZE", Err(anyhow!("invalid size {:?}", value))),
+            }
+        }
+
+        let mut min_image_bytes = None;
+        if let Some(value) = get("MIN_IMAGE_BYTES") {
+            match parse_size(&value) {
+                Some(size) => min_image_bytes = Some(size),
+                None => check("MIN_IMAGE_BYTES", Err(anyhow!("invalid size {:?}", value))),
+            }
+        }
+
+        let mut max_album_bytes = None;
+        if let Some(value) = get("MAX_ALBUM_BYTES") {
+            match parse_size(&value) {
+                Some(size) => max_album_bytes = Some(size),
+                None => check("MAX_ALBUM_BYTES", Err(anyhow!("invalid size {:?}", value))),
+            }
+        }
+
+        let mut processor_timeout = DEFAULT_PROCESSOR_TIMEOUT;
+        if let Some(value) = get("PROCESSOR_TIMEOUT_SECS") {
+            match value.parse::<u64>() {
+                Ok(secs) => processor_timeout = Duration::from_secs(secs),
+                Err(e) => check(
+                    "PROCESSOR_TIMEOUT_SECS",
+                    Err(anyhow!("invalid seconds {:?}: {}", value, e)),
+                ),
+            }
+        }
+
+        // 无效的摘要长度只记录警告并使用默认值
+        let summary_limit = |name: &str| {
+            let value = get(name)?;
+            match value.parse::<usize>() {
+                Ok(limit) if limit > 0 => Some(limit),
+                _ => {
+                    log::warn!("{}: invalid length {:?}, using default", name, value);
+                    None
+                }
+            }
+        };
+        let (summary_max_length, summary_max_max_length) = resolve_summary_limits(
+            summary_limit("SUMMARY_MAX_LENGTH"),
+            summary_limit("SUMMARY_MAX_MAX_LENGTH"),
+        );
+
+        // 无效的下载超时只记录警告并使用默认值
+        let mut download_timeout = DEFAULT_DOWNLOAD_TIMEOUT;
+        if let Some(value) = get("DOWNLOAD_TIMEOUT") {
+            match value.parse::<u64>() {
+                Ok(secs) if secs > 0 => download_timeout = Duration::from_secs(secs),
+                _ => log::warn!(
+                    "DOWNLOAD_TIMEOUT: invalid seconds {:?}, using default {:?}",
+                    value,
+                    DEFAULT_DOWNLOAD_TIMEOUT
+                ),
+            }
+        }
+
+        let mut download_max_retries = DEFAULT_DOWNLOAD_MAX_RETRIES;
+        if let Some(value) = get("DOWNLOAD_MAX_RETRIES") {
+            match value.parse::<u32>() {
+                Ok(retries) => download_max_retries = retries,
+                Err(e) => check(
+                    "DOWNLOAD_MAX_RETRIES",
+                    Err(anyhow!("invalid count {:?}: {}", value, e)),
+                ),
+            }
+        }
+
+        let mut cache_sweep_interval = Some(DEFAULT_CACHE_SWEEP_INTERVAL);
+        if let Some(value) = get("CACHE_SWEEP_SECS") {
+            match value.parse::<u64>() {
+                Ok(0) => cache_sweep_interval = None,
+                Ok(secs) => cache_sweep_interval = Some(Duration::from_secs(secs)),
+                Err(e) => check(
+                    "CACHE_SWEEP_SECS",
+                    Err(anyhow!("invalid seconds {:?}: {}", value, e)),
+                ),
+            }
+        }
+
+        let mut max_scan_length = DEFAULT_MAX_SCAN_LENGTH;
+        if let Some(value) = get("MAX_SCAN_LENGTH") {
+            match value.parse::<usize>() {
+                Ok(length) => max_scan_length = length,
+                Err(e) => check(
+                    "MAX_SCAN_LENGTH",
+                    Err(anyhow!("invalid length {:?}: {}", value, e)),
+                ),
+            }
+        }
+
+        let mut dispatch_queue_size = DEFAULT_DISPATCH_QUEUE_SIZE;
+        if let Some(value) = get("DISPATCH_QUEUE_SIZE") {
+            match parse_dispatch_queue_size(&value) {
+                Ok(size) => dispatch_queue_size = size,
+                Err(e) => check("DISPATCH_QUEUE_SIZE", Err(e)),
+            }
+        }
+
+        let mut dispatch_distribution = DispatchDistribution::Chat;
+        if let Some(value) = get("DISPATCH_DISTRIBUTION") {
+            match parse_dispatch_distribution(&value) {
+                Ok(distribution) => dispatch_distribution = distribution,
+                Err(e) => check("DISPATCH_DISTRIBUTION", Err(e)),
+            }
+        }
+
+        let mut nga_sticker_emoji_map = Vec::new();
+        if let Some(value) = get("NGA_STICKER_EMOJI_MAP") {
+            match parse_sticker_emoji_map(&value) {
+                Ok(map) => nga_sticker_emoji_map = map,
+                Err(e) => check("NGA_STICKER_EMOJI_MAP", Err(e)),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow!("Invalid settings:\n{}", errors.join("\n")));
+        }
+
+        Ok(Self {
+            telegram_token: get("TELEGRAM_TOKEN"),
+            telegram_proxy,
+            proxy_fallback_direct: flag("PROXY_FALLBACK_DIRECT"),
+            max_file_size,
+            min_image_bytes,
+            max_album_bytes,
+            processor_timeout,
+            summary_max_length,
+            summary_max_max_length,
+            download_timeout,
+            download_max_retries,
+            cache_sweep_interval,
+            max_scan_length,
+            dispatch_queue_size,
+            dispatch_distribution,
+            admin_user_ids: get("ADMIN_USER_IDS")
+                .map(|ids| parse_admin_user_ids(&ids))
+                .unwrap_or_default(),
+            append_source_url: flag("APPEND_SOURCE_URL"),
+            group_require_mention: flag("GROUP_REQUIRE_MENTION"),
+            skip_forwards: flag("SKIP_FORWARDS"),
+            show_skip_errors: flag("SHOW_SKIP_ERRORS"),
+            show_source_footer: flag("SHOW_SOURCE_FOOTER"),
+            show_media_stats: flag("SHOW_MEDIA_STATS"),
+            combine_same_source: flag("COMBINE_SAME_SOURCE"),
+            suppress_caption_preview: flag("SUPPRESS_CAPTION_PREVIEW"),
+            caption_on_last: flag("CAPTION_ON_LAST"),
+            gif_mp4_as_animation: flag("GIF_MP4_AS_ANIMATION"),
+            oversize_photo_as_document: flag("OVERSIZE_PHOTO_AS_DOCUMENT"),
+            text_only_mode: flag("TEXT_ONLY_MODE"),
+            pixiv_cookie: get("PIXIV_COOKIE"),
+            pixiv_image_proxy,
+            pixiv_validate_on_start: flag("PIXIV_VALIDATE_ON_START"),
+            nga_uid: get("NGA_UID"),
+            nga_cid: get("NGA_CID"),
+            nga_cookie: get("NGA_COOKIE"),
+            nga_attach_token: get("NGA_ATTACH_TOKEN"),
+            nga_prefer_jpg: flag("NGA_PREFER_JPG"),
+            nga_force_fullres: flag("NGA_FORCE_FULLRES"),
+            nga_sticker_emoji: flag("NGA_STICKER_EMOJI"),
+            nga_sticker_emoji_map,
+            nga_image_proxy,
+            nga_title_selector: get("NGA_TITLE_SELECTOR"),
+            nga_content_selector: get("NGA_CONTENT_SELECTOR"),
+            bili_send_cover: flag("BILI_SEND_COVER"),
+            x_show_username: flag("X_SHOW_USERNAME"),
+        })
+    }
+
+    /// 判断用户是否为管理员
+    pub fn is_admin(&self, user_id: u64) -> bool {
+        self.admin_user_ids.contains(&user_id)
+    }
+}
+
+/// 校验网络代理地址，错误信息包含无效的值和期望的格式
+pub fn validate_proxy_url(value: &str) -> Result<()> {
+    reqwest::Proxy::all(value).map(|_| ()).map_err(|e| {
+        anyhow!(
+            "invalid proxy URL {:?} ({}), expected scheme://[user:password@]host:port, \
+             e.g. http://127.0.0.1:7890 or socks5://127.0.0.1:1080",
+            value,
+            e
+        )
+    })
+}
+
+/// 校验图片反向代理地址，错误信息包含无效的值和期望的格式
+pub fn validate_proxy_base_url(value: &str) -> Result<()> {
+    match Url::parse(value) {
+        Ok(url) if url.has_host() => Ok(()),
+        Ok(_) => Err(anyhow!(
+            "invalid proxy base URL {:?} (missing host), expected e.g. https://proxy.example/",
+            value
+        )),
+        Err(e) => Err(anyhow!(
+            "invalid proxy base URL {:?} ({}), expected e.g. https://proxy.example/",
+            value,
+            e
+        )),
+    }
+}
+
+/// 解析文件大小，可以是字节数（如 "10485760"）或人类可读格式（如 "10MB", "1GB"）
+pub fn parse_size(value: &str) -> Option<usize> {
+    if let Ok(size) = value.parse::<usize>() {
+        return Some(size);
+    }
+    Byte::parse_str(value, true)
+        .ok()
+        .map(|byte_obj| byte_obj.as_u64() as usize)
+}
+
+/// 解析调度器工作队列长度，必须在 1 到 [`MAX_DISPATCH_QUEUE_SIZE`] 之间
+pub fn parse_dispatch_queue_size(value: &str) -> Result<usize> {
+    let size = value
+        .parse::<usize>()
+        .map_err(|e| anyhow!("invalid number {:?}: {}", value, e))?;
+    if !(1..=MAX_DISPATCH_QUEUE_SIZE).contains(&size) {
+        return Err(anyhow!(
+            "{} is out of range, expected 1 to {}",
+            size,
+            MAX_DISPATCH_QUEUE_SIZE
+        ));
+    }
+    Ok(size)
+}
+
+/// 解析调度器的分组方式（chat/user/none），不区分大小写
+pub fn parse_dispatch_distribution(value: &str) -> Result<DispatchDistribution> {
+    match value.to_lowercase().as_str() {
+        "chat" => Ok(DispatchDistribution::Chat),
+        "user" => Ok(DispatchDistribution::User),
+        "none" => Ok(DispatchDistribution::None),
+        _ => Err(anyhow!(
+            "invalid distribution {:?}, expected chat, user or none",
+            value
+        )),
+    }
+}
+
+/// 确定摘要的折叠阈值和截断上限，未设置的一项使用默认值
+/// 截断上限超过 Telegram 限制时使用 [`SUMMARY_TELEGRAM_LIMIT`]，不大于折叠阈值时两项都使用默认值
+pub fn resolve_summary_limits(length: Option<usize>, max_length: Option<usize>) -> (usize, usize) {
+    let mut limits = (
+        length.unwrap_or(SUMMARY_NORMAL_LIMIT),
+        max_length.unwrap_or(SUMMARY_TELEGRAM_LIMIT),
+    );
+    if limits.1 > SUMMARY_TELEGRAM_LIMIT {
+        log::warn!(
+            "SUMMARY_MAX_MAX_LENGTH ({}) exceeds the Telegram limit, using {}",
+            limits.1,
+            SUMMARY_TELEGRAM_LIMIT
+        );
+        limits.1 = SUMMARY_TELEGRAM_LIMIT;
+    }
+    if limits.1 <= limits.0 {
+        log::warn!(
+            "SUMMARY_MAX_MAX_LENGTH ({}) must be greater than SUMMARY_MAX_LENGTH ({}), using defaults",
+            limits.1,
+            limits.0
+        );
+        return (SUMMARY_NORMAL_LIMIT, SUMMARY_TELEGRAM_LIMIT);
+    }
+    limits
+}
+
+/// 解析逗号分隔的贴纸 emoji 映射，如 `ac:cry=😭,ac:怒=😡`
+/// 贴纸名可以带 `s:` 前缀，emoji 为空时表示移除该贴纸
+pub fn parse_sticker_emoji_map(value: &str) -> Result<Vec<(String, String)>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, emoji) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("invalid entry {:?}, expected name=emoji", entry))?;
+            let name = name.trim();
+            let name = name.strip_prefix("s:").unwrap_or(name);
+            if name.is_empty() {
+                return Err(anyhow!("empty sticker name in {:?}", entry));
+            }
+            Ok((name.to_string(), emoji.trim().to_string()))
+        })
+        .collect()
+}
+
+/// 解析逗号分隔的管理员用户 ID 列表，忽略空项和无效项
+pub fn parse_admin_user_ids(value: &str) -> Vec<u64> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .filter_map(|id| match id.parse::<u64>() {
+            Ok(id) => Some(id),
+            Err(_) => {
+                log::warn!("Invalid user id in ADMIN_USER_IDS: {}", id);
+                None
+            }
+        })
+        .collect()
+}
+
+/// 设置全局配置，只能设置一次
+pub fn init_settings(settings: Settings) {
+    if SETTINGS.set(settings).is_err() {
+        log::warn!("Settings have already been initialized");
+    }
+}
+
+/// 获取全局配置
+///
+/// 未初始化时从环境变量加载，配置无效时记录错误并使用默认值
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| {
+        Settings::from_env().unwrap_or_else(|e| {
+            log::error!("{:#}, using default settings", e);
+            Settings::default()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn settings_from(pairs: &[(&str, &str)]) -> Result<Settings> {
+        let env: HashMap<&str, &str> = pairs.iter().copied().collect();
+        Settings::from_lookup(|name| env.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn test_settings_defaults() {
+        assert_eq!(settings_from(&[]).unwrap(), Settings::default());
+
+        // 空值视为未设置
+        let settings = settings_from(&[("PIXIV_IMAGE_PROXY", " "), ("MAX_FILE_SIZE", "")]).unwrap();
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn test_settings_typed_values() {
+        let settings = settings_from(&[
+            ("TELEGRAM_PROXY", "socks5://127.0.0.1:1080"),
+            ("MAX_FILE_SIZE", "20MB"),
+            ("MIN_IMAGE_BYTES", "10240"),
+            ("MAX_ALBUM_BYTES", "40MB"),
+            ("PROCESSOR_TIMEOUT_SECS", "30"),
+            ("DOWNLOAD_TIMEOUT", "45"),
+            ("DOWNLOAD_MAX_RETRIES", "5"),
+            ("CACHE_SWEEP_SECS", "60"),
+            ("MAX_SCAN_LENGTH", "8000"),
+            ("ADMIN_USER_IDS", "123, abc,456"),
+            ("APPEND_SOURCE_URL", "true"),
+            ("GROUP_REQUIRE_MENTION", "true"),
+            ("SHOW_SKIP_ERRORS", "0"),
+            ("PIXIV_IMAGE_PROXY", "https://i.pixiv.re/"),
+            ("NGA_COOKIE", "ngaPassportUid=1"),
+            ("NGA_PREFER_JPG", "1"),
+            ("NGA_FORCE_FULLRES", "true"),
+            ("BILI_SEND_COVER", "1"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            settings.telegram_proxy.as_deref(),
+            Some("socks5://127.0.0.1:1080")
+        );
+        assert_eq!(settings.max_file_size, 20 * 1000 * 1000);
+        assert_eq!(settings.min_image_bytes, Some(10240));
+        assert_eq!(settings.max_album_bytes, Some(40 * 1000 * 1000));
+        assert_eq!(settings.processor_timeout, Duration::from_secs(30));
+        assert_eq!(settings.download_timeout, Duration::from_secs(45));
+        assert_eq!(settings.download_max_retries, 5);
+        assert_eq!(settings.cache_sweep_interval, Some(Duration::from_secs(60)));
+        assert_eq!(settings.max_scan_length, 8000);
+        assert_eq!(settings.admin_user_ids, vec![123, 456]);
+        assert!(settings.is_admin(456));
+        assert!(!settings.is_admin(789));
+        assert!(settings.append_source_url);
+        assert!(settings.group_require_mention);
+        assert!(!settings.show_skip_errors);
+        assert_eq!(
+            settings.pixiv_image_proxy.as_deref(),
+            Some("https://i.pixiv.re/")
+        );
+        assert_eq!(settings.nga_cookie.as_deref(), Some("ngaPassportUid=1"));
+        assert!(settings.nga_prefer_jpg);
+        assert!(settings.nga_force_fullres);
+        assert!(settings.bili_send_cover);
+
+        // 开关只接受 1 和 true
+        let settings =
+            settings_from(&[("APPEND_SOURCE_URL", "yes"), ("NGA_PREFER_JPG", "false")]).unwrap();
+        assert!(!settings.append_source_url);
+        assert!(!settings.nga_prefer_jpg);
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        let err = settings_from(&[
+            ("PIXIV_IMAGE_PROXY", "not a url"),
+            ("MAX_FILE_SIZE", "huge"),
+            ("PROCESSOR_TIMEOUT_SECS", "-1"),
+            ("DOWNLOAD_MAX_RETRIES", "many"),
+        ])
+        .unwrap_err()
+        .to_string();
+
+        // 所有无效的配置项一起报告
+        assert!(err.contains("PIXIV_IMAGE_PROXY"));
+        assert!(err.contains("MAX_FILE_SIZE"));
+        assert!(err.contains("PROCESSOR_TIMEOUT_SECS"));
+        assert!(err.contains("DOWNLOAD_MAX_RETRIES"));
+        assert!(!err.contains("NGA_IMAGE_PROXY"));
+
+        assert!(settings_from(&[("NGA_IMAGE_PROXY", "proxy.example")]).is_err());
+
+        // 无效的下载超时使用默认值而不是报错
+        for value in ["abc", "0", "-5"] {
+            let settings = settings_from(&[("DOWNLOAD_TIMEOUT", value)]).unwrap();
+            assert_eq!(settings.download_timeout, DEFAULT_DOWNLOAD_TIMEOUT);
+        }
+
+        // 为 0 时不清理缓存
+        let settings = settings_from(&[("CACHE_SWEEP_SECS", "0")]).unwrap();
+        assert_eq!(settings.cache_sweep_interval, None);
+        assert!(settings_from(&[("CACHE_SWEEP_SECS", "soon")]).is_err());
+        assert!(settings_from(&[("MAX_SCAN_LENGTH", "-1")]).is_err());
+    }
+
+    #[test]
+    fn test_summary_limits() {
+        let defaults = (SUMMARY_NORMAL_LIMIT, SUMMARY_TELEGRAM_LIMIT);
+
+        // 未设置时使用默认值
+        assert_eq!(resolve_summary_limits(None, None), defaults);
+        assert_eq!(
+            settings_from(&[]).unwrap().summary_max_length,
+            SUMMARY_NORMAL_LIMIT
+        );
+
+        // 有效的设置
+        assert_eq!(resolve_summary_limits(Some(300), Some(2000)), (300, 2000));
+        assert_eq!(
+            resolve_summary_limits(Some(1000), None),
+            (1000, SUMMARY_TELEGRAM_LIMIT)
+        );
+        let settings = settings_from(&[
+            ("SUMMARY_MAX_LENGTH", "200"),
+            ("SUMMARY_MAX_MAX_LENGTH", "1000"),
+        ])
+        .unwrap();
+        assert_eq!(settings.summary_max_length, 200);
+        assert_eq!(settings.summary_max_max_length, 1000);
+
+        // 顺序无效时两项都使用默认值
+        assert_eq!(resolve_summary_limits(Some(2000), Some(1000)), defaults);
+        assert_eq!(resolve_summary_limits(Some(500), Some(500)), defaults);
+        assert_eq!(resolve_summary_limits(None, Some(100)), defaults);
+
+        // 截断上限不超过 Telegram 限制
+        assert_eq!(
+            resolve_summary_limits(Some(300), Some(10000)),
+            (300, SUMMARY_TELEGRAM_LIMIT)
+        );
+        assert_eq!(resolve_summary_limits(Some(5000), Some(8000)), defaults);
+        let settings = settings_from(&[("SUMMARY_MAX_MAX_LENGTH", "4096")]).unwrap();
+        assert_eq!(settings.summary_max_max_length, SUMMARY_TELEGRAM_LIMIT);
+
+        // 无效的值视为未设置
+        let settings = settings_from(&[
+            ("SUMMARY_MAX_LENGTH", "abc"),
+            ("SUMMARY_MAX_MAX_LENGTH", "2000"),
+        ])
+        .unwrap();
+        assert_eq!(settings.summary_max_length, SUMMARY_NORMAL_LIMIT);
+        assert_eq!(settings.summary_max_max_length, 2000);
+    }
+
+    #[test]
+    fn test_sticker_emoji_settings() {
+        assert_eq!(
+            parse_sticker_emoji_map("ac:cry=😭, s:ac:怒 = 😡,,ac:smile=").unwrap(),
+            vec![
+                ("ac:cry".to_string(), "😭".to_string()),
+                ("ac:怒".to_string(), "😡".to_string()),
+                ("ac:smile".to_string(), String::new()),
+            ]
+        );
+        assert!(parse_sticker_emoji_map("ac:cry").is_err());
+        assert!(parse_sticker_emoji_map("=😭").is_err());
+
+        let settings = settings_from(&[
+            ("NGA_STICKER_EMOJI", "1"),
+            ("NGA_STICKER_EMOJI_MAP", "ac:cry=😢"),
+        ])
+        .unwrap();
+        assert!(settings.nga_sticker_emoji);
+        assert_eq!(
+            settings.nga_sticker_emoji_map,
+            vec![("ac:cry".to_string(), "😢".to_string())]
+        );
+        let err = settings_from(&[("NGA_STICKER_EMOJI_MAP", "bad")]).unwrap_err();
+        assert!(err.to_string().contains("NGA_STICKER_EMOJI_MAP"));
+    }
+
+    #[test]
+    fn test_dispatch_settings() {
+        assert_eq!(parse_dispatch_queue_size("1").unwrap(), 1);
+        assert_eq!(parse_dispatch_queue_size("256").unwrap(), 256);
+        assert_eq!(
+            parse_dispatch_queue_size(&MAX_DISPATCH_QUEUE_SIZE.to_string()).unwrap(),
+            MAX_DISPATCH_QUEUE_SIZE
+        );
+        // 超出范围或无效
+        assert!(parse_dispatch_queue_size("0").is_err());
+        let err = parse_dispatch_queue_size("100000").unwrap_err().to_string();
+        assert!(err.contains("out of range"));
+        assert!(parse_dispatch_queue_size("-1").is_err());
+        assert!(parse_dispatch_queue_size("many").is_err());
+
+        assert_eq!(
+            parse_dispatch_distribution("User").unwrap(),
+            DispatchDistribution::User
+        );
+        assert_eq!(
+            parse_dispatch_distribution("none").unwrap(),
+            DispatchDistribution::None
+        );
+        assert!(parse_dispatch_distribution("message").is_err());
+
+        let settings = settings_from(&[
+            ("DISPATCH_QUEUE_SIZE", "128"),
+            ("DISPATCH_DISTRIBUTION", "user"),
+        ])
+        .unwrap();
+        assert_eq!(settings.dispatch_queue_size, 128);
+        assert_eq!(settings.dispatch_distribution, DispatchDistribution::User);
+
+        let err = settings_from(&[("DISPATCH_QUEUE_SIZE", "0"), ("DISPATCH_DISTRIBUTION", "x")])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("DISPATCH_QUEUE_SIZE"));
+        assert!(err.contains("DISPATCH_DISTRIBUTION"));
+    }
+
+    #[test]
+    fn test_proxy_validation_messages() {
+        assert!(validate_proxy_url("http://127.0.0.1:7890").is_ok());
+        assert!(validate_proxy_base_url("https://i.pixiv.re/").is_ok());
+
+        // 错误信息包含无效的值和期望的格式
+        let err = validate_proxy_url("http://[::1").unwrap_err().to_string();
+        assert!(err.contains("\"http://[::1\""));
+        assert!(err.contains("scheme://[user:password@]host:port"));
+
+        let err = validate_proxy_base_url("proxy.example")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("\"proxy.example\""));
+        assert!(err.contains("https://proxy.example/"));
+        assert!(validate_proxy_base_url("mailto:a@b.c").is_err());
+
+        // 启动时报告配置项名称
+        let err = settings_from(&[("TELEGRAM_PROXY", "http://[::1")])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("TELEGRAM_PROXY: invalid proxy URL"));
+    }
+
+    #[test]
+    fn test_proxy_optional() {
+        let settings = settings_from(&[
+            ("PROXY_OPTIONAL", "1"),
+            ("TELEGRAM_PROXY", "http://[::1"),
+            ("NGA_IMAGE_PROXY", "proxy.example"),
+            ("PIXIV_IMAGE_PROXY", "https://i.pixiv.re/"),
+        ])
+        .unwrap();
+
+        // 无效的代理按未设置处理，有效的代理保留
+        assert_eq!(settings.telegram_proxy, None);
+        assert_eq!(settings.nga_image_proxy, None);
+        assert_eq!(
+            settings.pixiv_image_proxy.as_deref(),
+            Some("https://i.pixiv.re/")
+        );
+
+        // 其他无效配置仍然报错
+        assert!(settings_from(&[("PROXY_OPTIONAL", "1"), ("MAX_FILE_SIZE", "huge")]).is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("10485760"), Some(10485760));
+        assert_eq!(parse_size("10KB"), Some(10000));
+        assert_eq!(parse_size("1MiB"), Some(1024 * 1024));
+        assert_eq!(parse_size("abc"), None);
+    }
+}
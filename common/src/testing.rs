@@ -0,0 +1,101 @@
+//! 测试辅助工具
+//!
+//! 只在本 crate 的测试中或开启 `test-util` feature 时编译，供其他 crate 的测试共用。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::models::{
+    LinkProcessor, ProcessorError, ProcessorResult, ProcessorResultMedia, ProcessorResultType,
+};
+
+/// 用于测试的模拟处理器
+///
+/// 默认返回 `名称:匹配文本` 的文本结果，可以设置延迟、失败、媒体结果和缓存
+pub struct MockProcessor {
+    name: &'static str,
+    pattern: &'static str,
+    delay: Duration,
+    fail: bool,
+    media: bool,
+    regex: regex::Regex,
+    cache: Option<AtomicUsize>,
+}
+
+impl MockProcessor {
+    pub fn new(name: &'static str, pattern: &'static str) -> Self {
+        Self {
+            name,
+            pattern,
+            delay: Duration::ZERO,
+            fail: false,
+            media: false,
+            regex: regex::Regex::new(pattern).unwrap(),
+            cache: None,
+        }
+    }
+
+    /// 处理前等待指定时间，用于测试超时
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// 总是返回 `mock failure` 错误
+    pub fn failing(mut self) -> Self {
+        self.fail = true;
+        self
+    }
+
+    /// 返回媒体结果，说明文字包含截断选项，媒体链接为匹配文本加上 `.jpg`
+    pub fn media(mut self) -> Self {
+        self.media = true;
+        self
+    }
+
+    /// 模拟带有指定条目数量缓存的处理器
+    pub fn with_cache(mut self, entries: usize) -> Self {
+        self.cache = Some(AtomicUsize::new(entries));
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl LinkProcessor for MockProcessor {
+    fn pattern(&self) -> &'static str {
+        self.pattern
+    }
+
+    fn regex(&self) -> &regex::Regex {
+        &self.regex
+    }
+
+    async fn process_captures(
+        &self,
+        captures: &regex::Captures<'_>,
+        truncation: bool,
+    ) -> ProcessorResultType {
+        tokio::time::sleep(self.delay).await;
+        if self.fail {
+            return Err(ProcessorError::new("mock failure"));
+        }
+        if self.media {
+            return Ok(ProcessorResult::Media(
+                ProcessorResultMedia::new(format!("{}:truncation={}", self.name, truncation))
+                    .with_urls(vec![format!("{}.jpg", &captures[0])]),
+            ));
+        }
+        Ok(ProcessorResult::Text(format!(
+            "{}:{}",
+            self.name, &captures[0]
+        )))
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn clear_cache(&self) -> Option<usize> {
+        self.cache.as_ref().map(|c| c.swap(0, Ordering::SeqCst))
+    }
+}
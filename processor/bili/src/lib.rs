@@ -1,19 +1,31 @@
 //! BiliBili短链接处理模块
 //!
-//! 这个模块提供了处理BiliBili (b23.tv) 短链接重定向的功能。
+//! 这个模块提供了处理BiliBili (b23.tv) 短链接重定向的功能，
+//! 以及获取视频信息的功能（[`get_bili_video`]）。
 
 use anyhow::{Result, anyhow};
-use common::{LinkProcessor, ProcessorError, ProcessorResult, ProcessorResultType};
+use common::{
+    LinkProcessor, ProcessorError, ProcessorErrorKind, ProcessorResult, ProcessorResultMedia,
+    ProcessorResultType, host_matches, normalize_url, settings,
+};
 use regex::Regex;
 use reqwest::Client;
 use std::collections::HashMap;
 use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use url::Url;
 
-// 全局缓存，存储 b23 短链接到重定向目标的映射
-static B23_CACHE: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+mod video;
+
+pub use video::{BiliVideo, build_video_caption, get_bili_video, get_video_info};
+
+// 全局缓存，存储 b23 短链接到重定向目标的映射及写入时间
+static B23_CACHE: OnceLock<Mutex<HashMap<String, (String, Instant)>>> = OnceLock::new();
 static BILI_REGEX: OnceLock<Regex> = OnceLock::new();
 
+/// b23 缓存条目的有效期
+const B23_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
 /// BiliBili链接处理器
 pub struct BiliBiliProcessor;
 
@@ -32,23 +44,122 @@ impl LinkProcessor for BiliBiliProcessor {
             .get_or_init(|| Regex::new(Self::PATTERN).expect("Invalid BiliBili regex pattern"))
     }
 
-    async fn process_captures(&self, captures: &regex::Captures<'_>) -> ProcessorResultType {
-        let full_match = captures.get(0).unwrap().as_str();
-        match get_b23_redirect(full_match).await {
-            Ok(redirect_url) => Ok(ProcessorResult::Text(redirect_url)),
-            Err(e) => Err(ProcessorError::with_source(
-                "处理BiliBili链接失败",
-                e.to_string(),
-            )),
+    async fn process_captures(
+        &self,
+        captures: &regex::Captures<'_>,
+        _truncation: bool,
+    ) -> ProcessorResultType {
+        // 正则允许省略协议，请求前补充
+        let short_url = normalize_url(captures.get(0).unwrap().as_str());
+
+        let redirect_url = match get_b23_redirect(&short_url).await {
+            Ok(redirect_url) => redirect_url,
+            Err(e) => {
+                return Err(
+                    ProcessorError::with_source("处理BiliBili链接失败", e.to_string())
+                        .with_kind(ProcessorErrorKind::from_error(&e)),
+                );
+            }
+        };
+
+        let link_type = BiliLinkType::from_url(&redirect_url);
+        log::debug!("BiliBili link type: {:?} ({})", link_type, redirect_url);
+
+        // 开启 BILI_SEND_COVER 时视频链接发送封面，获取失败则回退到纯文本链接
+        if link_type == BiliLinkType::Video && is_send_cover_enabled() {
+            match get_video_info(&redirect_url).await {
+                Ok(video) => return Ok(video_to_result(video)),
+                Err(e) => log::warn!("Failed to get BiliBili video info, fallback to link: {}", e),
+            }
         }
+
+        Ok(ProcessorResult::Text(redirect_url))
     }
 
     fn name(&self) -> &'static str {
         "BiliBili"
     }
+
+    fn footer(&self) -> String {
+        "📺 BiliBili".to_string()
+    }
+
+    fn link_hints(&self) -> &'static [&'static str] {
+        &["b23.tv", "bili2233.cn"]
+    }
+
+    fn example_urls(&self) -> &'static [&'static str] {
+        &["https://b23.tv/abc123", "https://bili2233.cn/abc123"]
+    }
+
+    fn clear_cache(&self) -> Option<usize> {
+        Some(clear_b23_cache())
+    }
+
+    fn sweep_expired(&self) -> Option<usize> {
+        Some(sweep_expired())
+    }
+}
+
+/// b23.tv 短链接展开后的链接类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiliLinkType {
+    /// 视频 `bilibili.com/video/BV...`
+    Video,
+    /// 专栏文章 `bilibili.com/read/cv...`
+    Article,
+    /// 图文动态 `bilibili.com/opus/...`、`t.bilibili.com/...`
+    Opus,
+    /// 直播间 `live.bilibili.com/...`
+    Live,
+    /// 其他链接
+    Other,
 }
 
-fn get_b23_cache() -> &'static Mutex<HashMap<String, String>> {
+impl BiliLinkType {
+    /// 根据展开后的链接判断类型
+    pub fn from_url(url: &str) -> Self {
+        let Ok(url) = Url::parse(url) else {
+            return Self::Other;
+        };
+        let host = url.host_str().unwrap_or_default();
+        if !(host == "bilibili.com" || host.ends_with(".bilibili.com")) {
+            return Self::Other;
+        }
+
+        let path = url.path();
+        match host {
+            "live.bilibili.com" => Self::Live,
+            "t.bilibili.com" => Self::Opus,
+            _ if path.starts_with("/video/") => Self::Video,
+            _ if path.starts_with("/read/") => Self::Article,
+            _ if path.starts_with("/opus/") => Self::Opus,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// 是否发送视频封面（BILI_SEND_COVER）
+fn is_send_cover_enabled() -> bool {
+    settings().bili_send_cover
+}
+
+/// 根据视频信息选择返回封面图片或纯文本链接，没有封面时返回链接
+fn video_to_result(video: BiliVideo) -> ProcessorResult {
+    if video.cover.is_empty() {
+        return ProcessorResult::Text(video.url);
+    }
+    // 封面是静态图片，链接没有扩展名时按 JPEG 处理，发送前不需要 HEAD 请求获取类型
+    let content_type =
+        common::guess_content_type_from_url(&video.cover).unwrap_or_else(|| "image/jpeg".into());
+    ProcessorResult::Media(
+        ProcessorResultMedia::new(build_video_caption(&video))
+            .with_urls(vec![video.cover])
+            .with_content_type(content_type),
+    )
+}
+
+fn get_b23_cache() -> &'static Mutex<HashMap<String, (String, Instant)>> {
     B23_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
@@ -57,8 +168,16 @@ async fn get_b23_redirect(short_url: &str) -> Result<String> {
     // 首先检查缓存
     {
         let cache = get_b23_cache().lock().unwrap();
-        if let Some(cached_url) = cache.get(short_url) {
-            log::debug!("Cache hit for {} -> {}", short_url, cached_url);
+        if let Some((cached_url, inserted)) = cache.get(short_url)
+            && inserted.elapsed() < B23_CACHE_TTL
+        {
+            log::debug!(
+                "Cache hit for {} -> {} (age {}, ttl {})",
+                short_url,
+                cached_url,
+                common::human_duration(inserted.elapsed()),
+                common::human_duration(B23_CACHE_TTL)
+            );
             return Ok(cached_url.clone());
         }
     }
@@ -78,7 +197,7 @@ async fn get_b23_redirect(short_url: &str) -> Result<String> {
                 .map_err(|e| anyhow!("无法解析 Location 头: {}", e))?;
 
             // 如果是 B 站链接，清理追踪参数
-            let clean_url = if location_str.contains("bilibili.com") {
+            let clean_url = if host_matches(location_str, "bilibili.com") {
                 clean_bilibili_url(location_str)?
             } else {
                 location_str.to_string()
@@ -87,7 +206,7 @@ async fn get_b23_redirect(short_url: &str) -> Result<String> {
             // 将结果存入缓存
             {
                 let mut cache = get_b23_cache().lock().unwrap();
-                cache.insert(short_url.to_string(), clean_url.clone());
+                cache.insert(short_url.to_string(), (clean_url.clone(), Instant::now()));
             }
 
             Ok(clean_url)
@@ -95,10 +214,12 @@ async fn get_b23_redirect(short_url: &str) -> Result<String> {
             Err(anyhow!("响应中没有找到 Location 头"))
         }
     } else {
-        Err(anyhow!(
-            "期望重定向响应，但收到状态码: {}",
-            response.status()
-        ))
+        let status = response.status();
+        Err(
+            ProcessorError::new(format!("期望重定向响应，但收到状态码: {}", status))
+                .with_kind(ProcessorErrorKind::from_status(status.as_u16()))
+                .into(),
+        )
     }
 }
 
@@ -112,11 +233,25 @@ fn clean_bilibili_url(url_str: &str) -> Result<String> {
     Ok(url.to_string())
 }
 
-/// 清空 b23 缓存
-#[allow(dead_code)]
-fn clear_b23_cache() {
+/// 清空 b23 缓存，返回清除的条目数量
+pub fn clear_b23_cache() -> usize {
     let mut cache = get_b23_cache().lock().unwrap();
+    let count = cache.len();
     cache.clear();
+    count
+}
+
+/// 清除 b23 缓存中已过期的条目，返回清除的条目数量
+pub fn sweep_expired() -> usize {
+    let mut cache = get_b23_cache().lock().unwrap();
+    remove_expired(&mut cache, Instant::now())
+}
+
+/// 移除在 `now` 时已超过有效期的条目
+fn remove_expired(cache: &mut HashMap<String, (String, Instant)>, now: Instant) -> usize {
+    let count = cache.len();
+    cache.retain(|_, (_, inserted)| now.duration_since(*inserted) < B23_CACHE_TTL);
+    count - cache.len()
 }
 
 /// 获取缓存中的条目数量
@@ -149,6 +284,116 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bili_link_type() {
+        let cases = [
+            (
+                "https://www.bilibili.com/video/BV1xx411c7mD/",
+                BiliLinkType::Video,
+            ),
+            ("https://m.bilibili.com/video/av170001", BiliLinkType::Video),
+            (
+                "https://www.bilibili.com/read/cv123456",
+                BiliLinkType::Article,
+            ),
+            (
+                "https://www.bilibili.com/read/mobile/123456",
+                BiliLinkType::Article,
+            ),
+            (
+                "https://www.bilibili.com/opus/987654321",
+                BiliLinkType::Opus,
+            ),
+            ("https://m.bilibili.com/opus/987654321", BiliLinkType::Opus),
+            ("https://t.bilibili.com/987654321", BiliLinkType::Opus),
+            ("https://live.bilibili.com/21452505", BiliLinkType::Live),
+            ("https://space.bilibili.com/2", BiliLinkType::Other),
+            (
+                "https://example.com/video/BV1xx411c7mD",
+                BiliLinkType::Other,
+            ),
+            ("not a url", BiliLinkType::Other),
+        ];
+
+        for (url, expected) in cases {
+            assert_eq!(BiliLinkType::from_url(url), expected, "Failed for: {}", url);
+        }
+    }
+
+    #[test]
+    fn test_video_to_result() {
+        let video = BiliVideo {
+            url: "https://www.bilibili.com/video/BV1xx411c7mD/".to_string(),
+            title: "测试视频".to_string(),
+            up_name: "UP主".to_string(),
+            duration: 245,
+            cover: "https://i0.hdslb.com/bfs/archive/cover.jpg".to_string(),
+        };
+
+        // 有封面时返回图片
+        match video_to_result(video.clone()) {
+            ProcessorResult::Media(media) => {
+                assert_eq!(media.urls, vec![video.cover.clone()]);
+                assert!(media.caption.contains("测试视频"));
+                assert!(media.caption.contains(&video.url));
+                assert_eq!(media.content_type.as_deref(), Some("image/jpeg"));
+            }
+            other => panic!("应该返回图片结果，实际: {:?}", other),
+        }
+
+        // 封面链接没有扩展名时也提供类型提示
+        let png_cover = BiliVideo {
+            cover: "https://i0.hdslb.com/bfs/archive/cover.png".to_string(),
+            ..video.clone()
+        };
+        let no_extension = BiliVideo {
+            cover: "https://i0.hdslb.com/bfs/archive/cover".to_string(),
+            ..video.clone()
+        };
+        for (video, expected) in [(png_cover, "image/png"), (no_extension, "image/jpeg")] {
+            match video_to_result(video) {
+                ProcessorResult::Media(media) => {
+                    assert_eq!(media.content_type.as_deref(), Some(expected))
+                }
+                other => panic!("应该返回图片结果，实际: {:?}", other),
+            }
+        }
+
+        // 没有封面时返回链接
+        let no_cover = BiliVideo {
+            cover: String::new(),
+            ..video.clone()
+        };
+        match video_to_result(no_cover) {
+            ProcessorResult::Text(text) => assert_eq!(text, video.url),
+            other => panic!("应该返回文本结果，实际: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_remove_expired() {
+        let start = Instant::now();
+        let mut cache = HashMap::from([
+            ("old".to_string(), ("https://old".to_string(), start)),
+            (
+                "new".to_string(),
+                ("https://new".to_string(), start + B23_CACHE_TTL),
+            ),
+        ]);
+
+        // 都未过期
+        assert_eq!(
+            remove_expired(&mut cache, start + Duration::from_secs(1)),
+            0
+        );
+        assert_eq!(cache.len(), 2);
+
+        // 只移除过期的条目
+        assert_eq!(remove_expired(&mut cache, start + B23_CACHE_TTL), 1);
+        assert!(cache.contains_key("new"));
+        assert!(!cache.contains_key("old"));
+    }
+
     #[tokio::test]
     async fn test_b23_cache() {
         // 清空缓存
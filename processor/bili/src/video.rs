@@ -0,0 +1,236 @@
+//! BiliBili 视频信息
+//!
+//! 通过 BiliBili Web API 获取视频标题、UP主和时长等信息。
+
+use anyhow::{Result, anyhow};
+use common::{GENERAL_UA, ProcessorError, ProcessorErrorKind, escape_html};
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+use crate::get_b23_redirect;
+
+const VIEW_API_URL: &str = "https://api.bilibili.com/x/web-interface/view";
+
+static BVID_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)/video/(BV[0-9A-Za-z]{10})").unwrap());
+static AID_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)/video/av(\d+)").unwrap());
+
+/// BiliBili 视频信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct BiliVideo {
+    /// 清理追踪参数后的视频链接
+    pub url: String,
+    pub title: String,
+    pub up_name: String,
+    /// 视频时长（秒）
+    pub duration: u64,
+    /// 封面图片链接
+    pub cover: String,
+}
+
+/// Web API 响应
+#[derive(Debug, Deserialize)]
+struct BiliApiResponse {
+    code: i64,
+    message: String,
+    data: Option<BiliViewData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BiliViewData {
+    title: String,
+    duration: u64,
+    #[serde(default)]
+    pic: String,
+    owner: BiliOwner,
+}
+
+#[derive(Debug, Deserialize)]
+struct BiliOwner {
+    name: String,
+}
+
+/// 根据视频链接构建 API 地址，支持 BV 号和 av 号
+fn build_view_api_url(video_url: &str) -> Option<String> {
+    if let Some(caps) = BVID_REGEX.captures(video_url) {
+        return Some(format!("{}?bvid={}", VIEW_API_URL, &caps[1]));
+    }
+    AID_REGEX
+        .captures(video_url)
+        .map(|caps| format!("{}?aid={}", VIEW_API_URL, &caps[1]))
+}
+
+/// 解析 API 响应为视频信息
+fn parse_view_response(video_url: &str, text: &str) -> Result<BiliVideo> {
+    let response: BiliApiResponse = serde_json::from_str(text).map_err(|e| {
+        ProcessorError::new(format!("Failed to parse BiliBili API response: {}", e))
+            .with_kind(ProcessorErrorKind::Parse)
+    })?;
+
+    if response.code != 0 {
+        let kind = match response.code {
+            -404 | 62002 | 62004 => ProcessorErrorKind::NotFound,
+            -403 | 62012 => ProcessorErrorKind::Forbidden,
+            _ => ProcessorErrorKind::Unknown,
+        };
+        return Err(ProcessorError::new(format!(
+            "BiliBili API error {}: {}",
+            response.code, response.message
+        ))
+        .with_kind(kind)
+        .into());
+    }
+
+    let data = response
+        .data
+        .ok_or_else(|| anyhow!("BiliBili API response has no data"))?;
+
+    Ok(BiliVideo {
+        url: video_url.to_string(),
+        title: data.title,
+        up_name: data.owner.name,
+        duration: data.duration,
+        // API 返回的封面可能是 http 链接
+        cover: match data.pic.strip_prefix("http://") {
+            Some(rest) => format!("https://{}", rest),
+            None => data.pic,
+        },
+    })
+}
+
+/// 格式化视频时长，如 `4:05`、`1:02:03`
+pub fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// 构建视频封面的说明文字
+pub fn build_video_caption(video: &BiliVideo) -> String {
+    format!(
+        "<b>{}</b>\nUP: {} · {}\n{}",
+        escape_html(&video.title),
+        escape_html(&video.up_name),
+        format_duration(video.duration),
+        escape_html(&video.url)
+    )
+}
+
+/// 获取 b23.tv 短链接对应的视频信息
+pub async fn get_bili_video(short_url: &str) -> Result<BiliVideo> {
+    let video_url = get_b23_redirect(short_url).await?;
+    get_video_info(&video_url).await
+}
+
+/// 获取视频链接对应的视频信息
+pub async fn get_video_info(video_url: &str) -> Result<BiliVideo> {
+    let api_url = build_view_api_url(video_url)
+        .ok_or_else(|| anyhow!("Not a BiliBili video link: {}", video_url))?;
+    log::debug!("BiliBili API URL: {}", api_url);
+
+    let client = common::build_http_client(GENERAL_UA, common::ACCEPT_JSON)?;
+    let response = client.get(&api_url).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(
+            ProcessorError::new(format!("Failed to fetch BiliBili data: HTTP {}", status))
+                .with_kind(ProcessorErrorKind::from_status(status.as_u16()))
+                .into(),
+        );
+    }
+
+    let text = response.text().await?;
+    log::trace!("BiliBili API response: {}", text);
+    parse_view_response(video_url, &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_view_api_url() {
+        assert_eq!(
+            build_view_api_url("https://www.bilibili.com/video/BV1xx411c7mD/"),
+            Some(format!("{}?bvid=BV1xx411c7mD", VIEW_API_URL))
+        );
+        assert_eq!(
+            build_view_api_url("https://www.bilibili.com/video/av170001"),
+            Some(format!("{}?aid=170001", VIEW_API_URL))
+        );
+        assert_eq!(build_view_api_url("https://live.bilibili.com/123"), None);
+    }
+
+    #[test]
+    fn test_parse_view_response() {
+        let text = r#"{
+            "code": 0,
+            "message": "0",
+            "data": {
+                "bvid": "BV1xx411c7mD",
+                "title": "测试视频",
+                "pic": "http://i0.hdslb.com/bfs/archive/cover.jpg",
+                "duration": 245,
+                "owner": { "mid": 2, "name": "测试UP主" }
+            }
+        }"#;
+        let video = parse_view_response("https://www.bilibili.com/video/BV1xx411c7mD/", text)
+            .expect("应该成功解析");
+
+        assert_eq!(
+            video,
+            BiliVideo {
+                url: "https://www.bilibili.com/video/BV1xx411c7mD/".to_string(),
+                title: "测试视频".to_string(),
+                up_name: "测试UP主".to_string(),
+                duration: 245,
+                cover: "https://i0.hdslb.com/bfs/archive/cover.jpg".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_view_response_error() {
+        let text = r#"{ "code": -404, "message": "啥都木有", "data": null }"#;
+        let err =
+            parse_view_response("https://www.bilibili.com/video/BV1xx411c7mD/", text).unwrap_err();
+        assert_eq!(
+            ProcessorErrorKind::from_error(&err),
+            ProcessorErrorKind::NotFound
+        );
+        assert!(err.to_string().contains("啥都木有"));
+
+        let err = parse_view_response("https://www.bilibili.com/video/BV1xx411c7mD/", "not json")
+            .unwrap_err();
+        assert_eq!(
+            ProcessorErrorKind::from_error(&err),
+            ProcessorErrorKind::Parse
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0:00");
+        assert_eq!(format_duration(245), "4:05");
+        assert_eq!(format_duration(3723), "1:02:03");
+    }
+
+    #[test]
+    fn test_build_video_caption() {
+        let video = BiliVideo {
+            url: "https://www.bilibili.com/video/BV1xx411c7mD/".to_string(),
+            title: "<测试> & 视频".to_string(),
+            up_name: "UP主".to_string(),
+            duration: 245,
+            cover: "https://i0.hdslb.com/bfs/archive/cover.jpg".to_string(),
+        };
+        assert_eq!(
+            build_video_caption(&video),
+            "<b>&lt;测试&gt; &amp; 视频</b>\nUP: UP主 · 4:05\nhttps://www.bilibili.com/video/BV1xx411c7mD/"
+        );
+    }
+}
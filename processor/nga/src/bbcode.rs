@@ -13,10 +13,8 @@
 //! 3. 在 `ParamTag::base_name` 中添加映射
 //! 4. 如需特殊渲染，在 `BBCodeParser::render_tag` 中添加处理
 
-use regex::Regex;
-use std::sync::OnceLock;
+use common::escape_html;
 
-use crate::page::escape_html;
 use crate::utils::{img_link_process, normalize_newlines, replace_html_entities};
 
 // ============================================================================
@@ -79,6 +77,8 @@ pub enum ParamTag {
     Sticker(String),
     Size(String),
     Align(String),
+    /// 带尺寸属性的图片，如 `[img=100x100]`
+    Img(String),
 }
 
 impl ParamTag {
@@ -93,6 +93,7 @@ impl ParamTag {
             Self::Sticker(_) => "s",
             Self::Size(_) => "size",
             Self::Align(_) => "align",
+            Self::Img(_) => "img",
         }
     }
 }
@@ -202,6 +203,8 @@ impl BBCodeTag {
             ParamTag::Size(v.to_string())
         } else if let Some(v) = tag.strip_prefix("align=") {
             ParamTag::Align(v.to_string())
+        } else if let Some(v) = tag.strip_prefix("img=") {
+            ParamTag::Img(v.to_string())
         } else {
             return None;
         };
@@ -240,9 +243,8 @@ impl BBCodeTag {
     /// 是否需要移除标签内容
     pub fn should_remove_content(&self) -> bool {
         match self {
-            Self::Simple(idx) => TAG_REGISTRY.get(*idx).is_some_and(|d| d.remove_content),
             Self::Parameterized(ParamTag::Sticker(_)) => true,
-            _ => false,
+            _ => self.def().is_some_and(|d| d.remove_content),
         }
     }
 
@@ -252,6 +254,49 @@ impl BBCodeTag {
     }
 }
 
+// ============================================================================
+// 贴纸 emoji 映射
+// ============================================================================
+
+/// 常用贴纸对应的 emoji，键为去掉 `s:` 前缀的贴纸名
+///
+/// 设置 NGA_STICKER_EMOJI 时贴纸替换为对应的 emoji，
+/// 可通过 NGA_STICKER_EMOJI_MAP 覆盖或补充
+const DEFAULT_STICKER_EMOJI: &[(&str, &str)] = &[
+    ("ac:blink", "😉"),
+    ("ac:goodjob", "👍"),
+    ("ac:cry", "😭"),
+    ("ac:smile", "🙂"),
+    ("ac:lucky", "🍀"),
+    ("ac:赞同", "👍"),
+    ("ac:哭笑", "😂"),
+    ("ac:偷笑", "🤭"),
+    ("ac:怒", "😠"),
+    ("ac:汗", "😅"),
+    ("ac:晕", "😵"),
+    ("ac:惊", "😲"),
+    ("ac:囧", "😳"),
+    ("ac:冷", "🥶"),
+    ("ac:吻", "😘"),
+    ("ac:心", "❤️"),
+    ("ac:茶", "🍵"),
+    ("ac:无语", "😑"),
+];
+
+/// 查找贴纸对应的 emoji，`overrides` 优先于内置映射
+/// 没有对应的 emoji 或覆盖为空时返回 None，贴纸会被移除
+pub fn sticker_emoji<'a>(tag: &str, overrides: &'a [(String, String)]) -> Option<&'a str> {
+    let name = tag.strip_prefix("s:").unwrap_or(tag);
+    let emoji = match overrides.iter().find(|(key, _)| key == name) {
+        Some((_, emoji)) => emoji.as_str(),
+        None => DEFAULT_STICKER_EMOJI
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, emoji)| *emoji)?,
+    };
+    (!emoji.is_empty()).then_some(emoji)
+}
+
 // ============================================================================
 // Rich Message BBCode 解析器
 // ============================================================================
@@ -274,6 +319,10 @@ impl RichContentCleaner {
 pub struct RichBBCodeParser {
     chars: Vec<char>,
     pos: usize,
+    /// 贴纸替换为 emoji（NGA_STICKER_EMOJI），否则移除
+    sticker_emoji: bool,
+    /// 是否在表格单元格中，嵌套的表格展开为文本
+    in_table: bool,
 }
 
 impl RichBBCodeParser {
@@ -281,9 +330,31 @@ impl RichBBCodeParser {
         Self {
             chars: input.chars().collect(),
             pos: 0,
+            sticker_emoji: common::settings().nga_sticker_emoji,
+            in_table: false,
         }
     }
 
+    /// 设置是否将贴纸替换为 emoji
+    pub fn with_sticker_emoji(mut self, enabled: bool) -> Self {
+        self.sticker_emoji = enabled;
+        self
+    }
+
+    /// 使用相同的设置解析嵌套的内容
+    fn nested(&self, content: &str) -> String {
+        let mut parser = Self::new(content).with_sticker_emoji(self.sticker_emoji);
+        parser.in_table = self.in_table;
+        parser.parse()
+    }
+
+    /// 解析表格单元格的内容，其中的表格会展开为文本
+    fn nested_cell(&self, content: &str) -> String {
+        let mut parser = Self::new(content).with_sticker_emoji(self.sticker_emoji);
+        parser.in_table = true;
+        parser.parse()
+    }
+
     pub fn parse(&mut self) -> String {
         let mut result = String::new();
         while self.pos < self.chars.len() {
@@ -310,10 +381,17 @@ impl RichBBCodeParser {
             self.pos = tag_end;
 
             if tag.is_self_closing() {
-                if !tag.should_remove_content() {
-                    result.push_str(tag.to_html_open());
-                    result.push_str(tag.to_html_close());
-                }
+                self.render_tag(&tag, "", result);
+                return;
+            }
+
+            // 缺少 [/img] 的图片，链接截止到空白字符或下一个标签
+            if let Some(url_end) = self.find_unclosed_img_end(&tag) {
+                let content = self.extract_content(self.pos, url_end);
+                log::debug!("Recovered [img] without closing tag: {}", content);
+                let img_url = img_link_process(&content);
+                result.push_str(&format!("<img src=\"{}\"/>", img_url));
+                self.pos = url_end;
                 return;
             }
 
@@ -343,6 +421,11 @@ impl RichBBCodeParser {
 
     fn render_tag(&self, tag: &BBCodeTag, content: &str, result: &mut String) {
         match tag {
+            // 单元格中的表格 → 文本，Telegram 不支持嵌套表格
+            _ if tag.base_name() == "table" && self.in_table => {
+                result.push_str(&format!("\n{}\n", self.format_plain_table(content)));
+                return;
+            }
             // 表格 → <table>（前后加段落分隔）
             _ if tag.base_name() == "table" => {
                 result.push_str(&format!("\n\n{}\n\n", self.format_rich_table(content)));
@@ -350,7 +433,7 @@ impl RichBBCodeParser {
             }
             // [url=href] → <a>
             BBCodeTag::Parameterized(ParamTag::Url(href)) => {
-                let processed = Self::new(content).parse();
+                let processed = self.nested(content);
                 result.push_str(&format!(
                     "<a href=\"{}\">{}</a>",
                     escape_html_attr(href),
@@ -360,7 +443,7 @@ impl RichBBCodeParser {
             }
             // [collapse=title] → <details>（前后加段落分隔）
             BBCodeTag::Parameterized(ParamTag::Collapse(title)) => {
-                let processed = Self::new(content).parse();
+                let processed = self.nested(content);
                 result.push_str(&format!(
                     "\n\n<details><summary>{}</summary>{}</details>\n\n",
                     escape_html(title),
@@ -370,7 +453,7 @@ impl RichBBCodeParser {
             }
             // [size=N] → <b>
             BBCodeTag::Parameterized(ParamTag::Size(_)) => {
-                let processed = Self::new(content).parse();
+                let processed = self.nested(content);
                 result.push_str(&format!("<b>{}</b>", processed));
                 return;
             }
@@ -379,14 +462,22 @@ impl RichBBCodeParser {
             | BBCodeTag::Parameterized(ParamTag::Pid(_))
             | BBCodeTag::Parameterized(ParamTag::Uid(_))
             | BBCodeTag::Parameterized(ParamTag::Align(_)) => {
-                result.push_str(&Self::new(content).parse());
+                result.push_str(&self.nested(content));
+                return;
+            }
+            // 贴纸 → emoji（NGA_STICKER_EMOJI），没有对应的 emoji 时移除
+            BBCodeTag::Parameterized(ParamTag::Sticker(name)) => {
+                if self.sticker_emoji
+                    && let Some(emoji) =
+                        sticker_emoji(name, &common::settings().nga_sticker_emoji_map)
+                {
+                    result.push_str(emoji);
+                }
                 return;
             }
-            // 贴纸 → 移除
-            BBCodeTag::Parameterized(ParamTag::Sticker(_)) => return,
             // 表格单元格（由 format_rich_table 处理）
             BBCodeTag::Parameterized(ParamTag::TableCell(_)) => {
-                result.push_str(&Self::new(content).parse());
+                result.push_str(&self.nested(content));
                 return;
             }
             _ => {}
@@ -394,7 +485,7 @@ impl RichBBCodeParser {
 
         // 无参数 url → <a>
         if tag.base_name() == "url" {
-            let processed = Self::new(content).parse();
+            let processed = self.nested(content);
             result.push_str(&format!(
                 "<a href=\"{}\">{}</a>",
                 escape_html_attr(&processed),
@@ -405,41 +496,66 @@ impl RichBBCodeParser {
 
         // [quote] → <blockquote>（前后加段落分隔）
         if tag.base_name() == "quote" {
-            let processed = Self::new(content).parse();
+            let processed = self.nested(content);
             result.push_str(&format!("\n\n<blockquote>{}</blockquote>\n\n", processed));
             return;
         }
 
         // 普通标签
-        let processed = Self::new(content).parse();
+        let processed = self.nested(content);
         result.push_str(tag.to_html_open());
         result.push_str(&processed);
         result.push_str(tag.to_html_close());
     }
 
-    /// 格式化 Rich Message 表格
-    fn format_rich_table(&self, content: &str) -> String {
-        static TR_REGEX: OnceLock<Regex> = OnceLock::new();
-        static TD_REGEX: OnceLock<Regex> = OnceLock::new();
-
-        let tr_pattern = TR_REGEX.get_or_init(|| Regex::new(r"(?s)\[tr\](.*?)\[/tr\]").unwrap());
-        let td_pattern =
-            TD_REGEX.get_or_init(|| Regex::new(r"(?s)\[td[^]]*\](.*?)\[/td\]").unwrap());
-
-        let rows: Vec<Vec<String>> = tr_pattern
-            .find_iter(content)
-            .filter_map(|tr_match| {
-                let cells: Vec<String> = td_pattern
-                    .captures_iter(tr_match.as_str())
-                    .map(|cap| {
-                        let cell_content = cap.get(1).map_or("", |m| m.as_str());
-                        Self::new(cell_content).parse().trim().to_string()
-                    })
+    /// 解析表格的行和单元格，单元格中嵌套的表格不会影响外层的行和单元格
+    fn table_rows(&self, content: &str) -> Vec<Vec<String>> {
+        Self::top_level_tags(content, "tr")
+            .iter()
+            .filter_map(|row| {
+                let cells: Vec<String> = Self::top_level_tags(row, "td")
+                    .iter()
+                    .map(|cell| self.nested_cell(cell).trim().to_string())
                     .collect();
                 if cells.is_empty() { None } else { Some(cells) }
             })
-            .collect();
+            .collect()
+    }
 
+    /// 提取顶层 `[name]...[/name]` 标签的内容，按嵌套层级匹配结束标签
+    fn top_level_tags(content: &str, name: &str) -> Vec<String> {
+        let mut parser = Self::new(content);
+        let mut items = Vec::new();
+        let mut pos = 0;
+        while pos < parser.chars.len() {
+            match parser.parse_opening_tag_at(pos) {
+                Some((tag, tag_end)) if tag.base_name() == name => {
+                    parser.pos = tag_end;
+                    let Some(content_end) = parser.find_closing_tag(&tag) else {
+                        break;
+                    };
+                    items.push(parser.extract_content(tag_end, content_end));
+                    parser.skip_closing_tag_at(content_end);
+                    pos = parser.pos;
+                }
+                _ => pos += 1,
+            }
+        }
+        items
+    }
+
+    /// 将表格展开为文本，每行一条，单元格之间用 ` │ ` 分隔
+    fn format_plain_table(&self, content: &str) -> String {
+        self.table_rows(content)
+            .iter()
+            .map(|row| row.join(" │ "))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 格式化 Rich Message 表格
+    fn format_rich_table(&self, content: &str) -> String {
+        let rows = self.table_rows(content);
         if rows.is_empty() {
             return String::new();
         }
@@ -491,6 +607,25 @@ impl RichBBCodeParser {
         None
     }
 
+    /// 当前位置的 [img] 缺少对应的 [/img] 时，返回链接的结束位置
+    /// 标签完整或标签后没有链接时返回 None
+    fn find_unclosed_img_end(&self, tag: &BBCodeTag) -> Option<usize> {
+        if tag.base_name() != "img" {
+            return None;
+        }
+        let url_end = (self.pos..self.chars.len())
+            .find(|&i| self.chars[i].is_whitespace() || self.chars[i] == '[')
+            .unwrap_or(self.chars.len());
+        if url_end == self.pos || self.is_closing_tag_at(url_end, "img") {
+            return None;
+        }
+        // 后面的 [/img] 不在同一行时属于其他图片
+        match self.find_closing_tag(tag) {
+            Some(end) if !self.chars[self.pos..end].contains(&'\n') => None,
+            _ => Some(url_end),
+        }
+    }
+
     fn is_closing_tag_at(&self, pos: usize, expected: &str) -> bool {
         if pos + 2 >= self.chars.len() {
             return false;
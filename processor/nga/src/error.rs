@@ -23,6 +23,17 @@ impl std::fmt::Display for NGAError {
 
 impl std::error::Error for NGAError {}
 
+impl NGAError {
+    /// 对应的处理器错误类型
+    pub fn kind(&self) -> common::ProcessorErrorKind {
+        match self {
+            Self::Network(_) => common::ProcessorErrorKind::Network,
+            Self::Parse(_) => common::ProcessorErrorKind::Parse,
+            Self::Http { status, .. } => common::ProcessorErrorKind::from_status(*status),
+        }
+    }
+}
+
 impl From<reqwest::Error> for NGAError {
     fn from(error: reqwest::Error) -> Self {
         Self::Network(error)
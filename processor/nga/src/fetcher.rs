@@ -27,10 +27,9 @@ impl NGAFetcher {
 
     /// 获取页面 HTML
     pub async fn fetch_html(url: &str) -> NGAResult<String> {
-        let client = reqwest::Client::new();
+        let client = common::build_http_client(NGA_UA, common::ACCEPT_HTML)?;
         let response = client
             .get(url)
-            .header("User-Agent", NGA_UA)
             .header("Cookie", get_nga_cookie())
             .send()
             .await?;
@@ -26,7 +26,7 @@ mod utils;
 pub use bbcode::RichContentCleaner;
 pub use error::{NGAError, NGAResult};
 pub use fetcher::NGAFetcher;
-pub use page::NGAPage;
+pub use page::{NGAPage, NGASelectors};
 
 // ============================================================================
 // 链接处理器
@@ -44,7 +44,7 @@ static NGA_REGEX: OnceLock<Regex> = OnceLock::new();
 pub struct NGALinkProcessor;
 
 impl NGALinkProcessor {
-    const PATTERN: &'static str = r"(?:https?://(?:bbs\.nga\.cn|ngabbs\.com|nga\.178\.com|bbs\.gnacn\.cc)[-a-zA-Z0-9@:%_\+.~#?&//=]*)";
+    const PATTERN: &'static str = r"(?:(?:https?://)?\b(?:bbs\.nga\.cn|ngabbs\.com|nga\.178\.com|bbs\.gnacn\.cc)[-a-zA-Z0-9@:%_\+.~#?&//=]*)";
 }
 
 #[async_trait::async_trait]
@@ -57,16 +57,31 @@ impl LinkProcessor for NGALinkProcessor {
         NGA_REGEX.get_or_init(|| Regex::new(Self::PATTERN).expect("Invalid NGA regex pattern"))
     }
 
-    async fn process_captures(&self, captures: &regex::Captures<'_>) -> ProcessorResultType {
+    async fn process_captures(
+        &self,
+        captures: &regex::Captures<'_>,
+        _truncation: bool,
+    ) -> ProcessorResultType {
         let url = captures.get(0).unwrap().as_str();
-        NGAFetcher::parse(url)
-            .await
-            .map_err(|e| ProcessorError::with_source("处理NGA链接失败", e.to_string()))
+        NGAFetcher::parse(url).await.map_err(|e| {
+            ProcessorError::with_source("处理NGA链接失败", e.to_string()).with_kind(e.kind())
+        })
     }
 
     fn name(&self) -> &'static str {
         "NGA"
     }
+
+    fn link_hints(&self) -> &'static [&'static str] {
+        &["bbs.nga.cn", "ngabbs.com", "nga.178.com", "bbs.gnacn.cc"]
+    }
+
+    fn example_urls(&self) -> &'static [&'static str] {
+        &[
+            "https://bbs.nga.cn/read.php?tid=123456",
+            "https://ngabbs.com/read.php?tid=123456",
+        ]
+    }
 }
 
 // ============================================================================
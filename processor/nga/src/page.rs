@@ -1,14 +1,59 @@
 //! NGA 页面数据结构
 
+use common::{escape_html, settings};
 use scraper::{Html, Selector};
 
 use crate::bbcode::RichContentCleaner;
 
-/// 转义 HTML 特殊字符，防止 Telegram 将文本内容识别为 HTML 标签
-pub fn escape_html(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
+const DEFAULT_TITLE_SELECTOR: &str = "h3#postsubject0";
+const DEFAULT_CONTENT_SELECTOR: &str = "p#postcontent0";
+
+/// 页面解析使用的选择器
+#[derive(Debug, Clone)]
+pub struct NGASelectors {
+    pub title: Selector,
+    pub content: Selector,
+}
+
+impl Default for NGASelectors {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl NGASelectors {
+    /// 使用给定的选择器创建，未设置或无效时使用默认值
+    pub fn new(title: Option<&str>, content: Option<&str>) -> Self {
+        Self {
+            title: parse_selector_or_default(title, DEFAULT_TITLE_SELECTOR),
+            content: parse_selector_or_default(content, DEFAULT_CONTENT_SELECTOR),
+        }
+    }
+
+    /// 从配置 NGA_TITLE_SELECTOR、NGA_CONTENT_SELECTOR 读取
+    pub fn from_settings() -> Self {
+        let settings = settings();
+        Self::new(
+            settings.nga_title_selector.as_deref(),
+            settings.nga_content_selector.as_deref(),
+        )
+    }
+}
+
+/// 解析选择器，无效时记录日志并使用默认值
+fn parse_selector_or_default(value: Option<&str>, default: &str) -> Selector {
+    if let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) {
+        match Selector::parse(value) {
+            Ok(selector) => return selector,
+            Err(e) => log::warn!(
+                "Invalid NGA selector {:?}: {}, using default: {}",
+                value,
+                e,
+                default
+            ),
+        }
+    }
+    Selector::parse(default).expect("default selector should be valid")
 }
 
 /// NGA 页面数据
@@ -24,11 +69,23 @@ impl NGAPage {
     /// 从 HTML 解析页面数据
     pub fn from_html(url: &str, html: &str) -> Option<Self> {
         let document = Html::parse_document(html);
+        Self::from_document(url, &document)
+    }
+
+    /// 从已解析的 HTML 文档中提取页面数据，缺少标题或内容时返回 None
+    pub fn from_document(url: &str, document: &Html) -> Option<Self> {
+        Self::from_document_with(url, document, &NGASelectors::from_settings())
+    }
 
+    /// 使用指定的选择器提取页面数据
+    pub fn from_document_with(
+        url: &str,
+        document: &Html,
+        selectors: &NGASelectors,
+    ) -> Option<Self> {
         // 提取标题
-        let title_selector = Selector::parse("h3#postsubject0").ok()?;
         let title = document
-            .select(&title_selector)
+            .select(&selectors.title)
             .next()?
             .text()
             .collect::<String>()
@@ -36,8 +93,7 @@ impl NGAPage {
             .to_string();
 
         // 提取内容
-        let content_selector = Selector::parse("p#postcontent0").ok()?;
-        let raw_content = document.select(&content_selector).next()?.inner_html();
+        let raw_content = document.select(&selectors.content).next()?.inner_html();
 
         #[cfg(debug_assertions)]
         Self::debug_output(&title, &raw_content);
@@ -1,10 +1,9 @@
 #[cfg(test)]
 mod nga_tests {
-    use crate::bbcode::RichBBCodeParser;
-    use crate::page::escape_html;
+    use crate::bbcode::{RichBBCodeParser, sticker_emoji};
     use crate::utils::*;
     use crate::*;
-    use common::{SUMMARY_NORMAL_LIMIT, SUMMARY_TELEGRAM_LIMIT, substring_desc};
+    use common::{SUMMARY_NORMAL_LIMIT, SUMMARY_TELEGRAM_LIMIT, escape_html, substring_desc};
     use dotenv::dotenv;
 
     #[tokio::test]
@@ -88,6 +87,104 @@ mod nga_tests {
         assert_eq!(img_link_process(no_slash), expected_no_slash);
     }
 
+    #[test]
+    fn test_append_attach_token() {
+        let link = "https://img.nga.178.com/attachments/mon_202401/01/abc.jpg";
+
+        assert_eq!(
+            append_attach_token(link, "t0k3n"),
+            "https://img.nga.178.com/attachments/mon_202401/01/abc.jpg?token=t0k3n"
+        );
+
+        // 已有查询参数时追加
+        assert_eq!(
+            append_attach_token(&format!("{}?a=1", link), "t0k3n"),
+            "https://img.nga.178.com/attachments/mon_202401/01/abc.jpg?a=1&token=t0k3n"
+        );
+
+        // 幂等：重复处理不会重复追加
+        let signed = append_attach_token(link, "t0k3n");
+        assert_eq!(append_attach_token(&signed, "t0k3n"), signed);
+        assert_eq!(append_attach_token(&signed, "other"), signed);
+
+        // 空 token 或非 NGA 附件链接保持不变
+        assert_eq!(append_attach_token(link, ""), link);
+        assert_eq!(append_attach_token(link, "  "), link);
+        let external = "https://example.com/image.jpg";
+        assert_eq!(append_attach_token(external, "t0k3n"), external);
+    }
+
+    #[test]
+    fn test_proxy_attachment_link() {
+        let link = "https://img.nga.178.com/attachments/mon_202401/01/abc.jpg?token=t";
+
+        assert_eq!(
+            proxy_attachment_link(link, "https://nga-proxy.example/"),
+            "https://nga-proxy.example/attachments/mon_202401/01/abc.jpg?token=t"
+        );
+
+        // 未设置代理、代理无效或非 NGA 附件时保持不变
+        assert_eq!(proxy_attachment_link(link, " "), link);
+        assert_eq!(proxy_attachment_link(link, "not a url"), link);
+        assert_eq!(
+            proxy_attachment_link("https://example.com/a.jpg", "https://nga-proxy.example/"),
+            "https://example.com/a.jpg"
+        );
+    }
+
+    #[test]
+    fn test_strip_lowres_params() {
+        let link = "https://img.nga.178.com/attachments/mon_202401/01/abc.jpg";
+
+        assert_eq!(strip_lowres_params(&format!("{}?quality=30", link)), link);
+        assert_eq!(strip_lowres_params(&format!("{}?w=200&H=100", link)), link);
+        // 保留签名参数
+        assert_eq!(
+            strip_lowres_params(&format!("{}?quality=30&token=t0k3n", link)),
+            format!("{}?token=t0k3n", link)
+        );
+        assert_eq!(
+            strip_lowres_params(&append_attach_token(
+                &format!("{}?size=small", link),
+                "t0k3n"
+            )),
+            format!("{}?token=t0k3n", link)
+        );
+
+        // 没有低画质参数或不是 NGA 附件时不变
+        let signed = format!("{}?token=t0k3n", link);
+        assert_eq!(strip_lowres_params(&signed), signed);
+        assert_eq!(strip_lowres_params(link), link);
+        assert_eq!(
+            strip_lowres_params("https://example.com/a.jpg?quality=30"),
+            "https://example.com/a.jpg?quality=30"
+        );
+    }
+
+    #[test]
+    fn test_prefer_jpg_extension() {
+        // NGA 附件的 webp 替换为 jpg
+        assert_eq!(
+            prefer_jpg_extension("https://img.nga.178.com/attachments/mon_202401/01/abc.webp"),
+            "https://img.nga.178.com/attachments/mon_202401/01/abc.jpg"
+        );
+        assert_eq!(
+            prefer_jpg_extension("https://img.nga.178.com/attachments/mon_202401/01/abc.WEBP"),
+            "https://img.nga.178.com/attachments/mon_202401/01/abc.jpg"
+        );
+
+        // 其他扩展名保持不变
+        let png = "https://img.nga.178.com/attachments/mon_202401/01/abc.png";
+        assert_eq!(prefer_jpg_extension(png), png);
+        let jpg = "https://img.nga.178.com/attachments/mon_202401/01/abc.jpg";
+        assert_eq!(prefer_jpg_extension(jpg), jpg);
+
+        // 非 NGA 附件链接保持不变
+        let external = "https://example.com/image.webp";
+        assert_eq!(prefer_jpg_extension(external), external);
+        assert_eq!(prefer_jpg_extension(""), "");
+    }
+
     #[test]
     fn test_get_nga_guest_cookie() {
         let cookie = get_nga_guest_cookie();
@@ -104,6 +201,33 @@ mod nga_tests {
         assert!(cookie.starts_with("ngaPassportUid="));
     }
 
+    #[test]
+    fn test_build_nga_cookie_precedence() {
+        let raw = Some("ngaPassportUid=1;ngaPassportCid=abc;lastvisit=123".to_string());
+        let uid = Some("2".to_string());
+        let cid = Some("def".to_string());
+
+        // 完整 Cookie 优先
+        assert_eq!(
+            build_nga_cookie(raw.clone(), uid.clone(), cid.clone()),
+            "ngaPassportUid=1;ngaPassportCid=abc;lastvisit=123"
+        );
+
+        // 其次使用 UID/CID
+        assert_eq!(
+            build_nga_cookie(None, uid.clone(), cid.clone()),
+            "ngaPassportUid=2;ngaPassportCid=def"
+        );
+        assert_eq!(
+            build_nga_cookie(Some("  ".to_string()), uid.clone(), cid),
+            "ngaPassportUid=2;ngaPassportCid=def"
+        );
+
+        // 都没有或不完整时使用游客 Cookie
+        assert!(build_nga_cookie(None, None, None).starts_with("ngaPassportUid=guest0"));
+        assert!(build_nga_cookie(None, uid, None).starts_with("ngaPassportUid=guest0"));
+    }
+
     #[tokio::test]
     #[ignore = "需要网络，仅手动测试"]
     async fn test_get_nga_html() {
@@ -132,6 +256,86 @@ mod nga_tests {
         assert!(rich.contains("This is a test content."));
     }
 
+    #[test]
+    fn test_nga_fixture() {
+        let url = "https://bbs.nga.cn/read.php?tid=12345678";
+        let page =
+            parse_nga_page(url, include_str!("../fixtures/thread.html")).expect("应该成功解析");
+        assert_eq!(page.title, "[讨论] 新版本活动攒资源汇总");
+
+        assert_eq!(
+            page.to_rich_html(),
+            "<h3><a href=\"https://bbs.nga.cn/read.php?tid=12345678\">[讨论] 新版本活动攒资源汇总</a></h3>\
+             <p><b>活动时间</b>：7月1日 ~ 7月14日</p>\n\
+             <blockquote>以下内容来自官方公告</blockquote>\n\
+             <p>可获得资源：原石、摩拉<br/>\
+             <img src=\"https://img.nga.178.com/attachments/mon_202407/01/-abcdef/ABC123.jpg\"/><br/>\
+             <a href=\"https://example.com/notice\">官方公告</a></p>"
+        );
+    }
+
+    #[test]
+    fn test_nga_page_from_document() {
+        use scraper::Html;
+
+        let document = Html::parse_document(
+            r#"<h3 id="postsubject0"> Test Title </h3><p id="postcontent0">content</p>"#,
+        );
+        let page = NGAPage::from_document("test_url", &document).expect("应该成功解析");
+        assert_eq!(page.url, "test_url");
+        assert_eq!(page.title, "Test Title");
+
+        // 缺少标题
+        let document = Html::parse_document(r#"<p id="postcontent0">content</p>"#);
+        assert!(NGAPage::from_document("test_url", &document).is_none());
+
+        // 缺少内容
+        let document = Html::parse_document(r#"<h3 id="postsubject0">Test Title</h3>"#);
+        assert!(NGAPage::from_document("test_url", &document).is_none());
+
+        // 元素 id 不匹配
+        let document = Html::parse_document(
+            r#"<h3 id="postsubject1">Test Title</h3><p id="postcontent1">content</p>"#,
+        );
+        assert!(NGAPage::from_document("test_url", &document).is_none());
+
+        // 空文档
+        let document = Html::parse_document("");
+        assert!(NGAPage::from_document("test_url", &document).is_none());
+    }
+
+    #[test]
+    fn test_nga_selectors_override() {
+        use scraper::Html;
+
+        let document = Html::parse_document(
+            r#"<h1 class="subject">Mirror Title</h1><div class="content">content</div>"#,
+        );
+        assert!(
+            NGAPage::from_document_with("test_url", &document, &NGASelectors::default()).is_none()
+        );
+
+        let selectors = NGASelectors::new(Some("h1.subject"), Some("div.content"));
+        let page = NGAPage::from_document_with("test_url", &document, &selectors)
+            .expect("应该使用自定义选择器解析");
+        assert_eq!(page.title, "Mirror Title");
+    }
+
+    #[test]
+    fn test_nga_selectors_invalid_fallback() {
+        use scraper::Html;
+
+        let document = Html::parse_document(
+            r#"<h3 id="postsubject0">Test Title</h3><p id="postcontent0">content</p>"#,
+        );
+
+        // 无效或空白的选择器回退到默认值
+        let selectors = NGASelectors::new(Some("h3[[["), Some("  "));
+        let page = NGAPage::from_document_with("test_url", &document, &selectors)
+            .expect("应该回退到默认选择器");
+        assert_eq!(page.title, "Test Title");
+    }
+
     #[test]
     fn test_replace_html_entities() {
         // 测试 HTML 实体替换
@@ -188,6 +392,79 @@ mod nga_tests {
         assert_eq!(result, "Before <img src=\"test.jpg\"/> after");
     }
 
+    #[test]
+    fn test_bbcode_parser_img_with_attributes() {
+        // 带尺寸属性的图片标签，链接同样需要经过 img_link_process 处理
+        let input = "Before [img=100x100]./x.jpg[/img] after";
+        let mut parser = RichBBCodeParser::new(input);
+        let result = parser.parse();
+        assert_eq!(
+            result,
+            "Before <img src=\"https://img.nga.178.com/attachments/x.jpg\"/> after"
+        );
+
+        // 低画质后缀也应被移除
+        let input = "[img=640x480]./mon_202401/01/abc.jpg.medium.jpg[/img]";
+        let mut parser = RichBBCodeParser::new(input);
+        let result = parser.parse();
+        assert_eq!(
+            result,
+            "<img src=\"https://img.nga.178.com/attachments/mon_202401/01/abc.jpg\"/>"
+        );
+
+        // 带属性和不带属性的图片混合
+        let input = "[img]./a.jpg[/img][img=1x1]./b.jpg[/img]";
+        let mut parser = RichBBCodeParser::new(input);
+        let result = parser.parse();
+        assert_eq!(
+            result,
+            "<img src=\"https://img.nga.178.com/attachments/a.jpg\"/><img src=\"https://img.nga.178.com/attachments/b.jpg\"/>"
+        );
+    }
+
+    #[test]
+    fn test_bbcode_parser_unclosed_img() {
+        // 缺少 [/img] 时链接截止到换行
+        let input = "[img]./a.jpg\n正文";
+        let result = RichBBCodeParser::new(input).parse();
+        assert_eq!(
+            result,
+            "<img src=\"https://img.nga.178.com/attachments/a.jpg\"/>\n正文"
+        );
+
+        // 截止到空白字符，后面完整的图片不受影响
+        let input = "[img]./a.jpg [img]./b.jpg[/img]";
+        let result = RichBBCodeParser::new(input).parse();
+        assert_eq!(
+            result,
+            "<img src=\"https://img.nga.178.com/attachments/a.jpg\"/> <img src=\"https://img.nga.178.com/attachments/b.jpg\"/>"
+        );
+
+        // 截止到下一个标签
+        let input = "[img]./a.jpg[b]粗体[/b]";
+        let result = RichBBCodeParser::new(input).parse();
+        assert_eq!(
+            result,
+            "<img src=\"https://img.nga.178.com/attachments/a.jpg\"/><b>粗体</b>"
+        );
+
+        // 跨行的 [/img] 属于后面的图片
+        let input = "[img]./a.jpg\n[img]./b.jpg[/img]";
+        let result = RichBBCodeParser::new(input).parse();
+        assert_eq!(
+            result,
+            "<img src=\"https://img.nga.178.com/attachments/a.jpg\"/>\n<img src=\"https://img.nga.178.com/attachments/b.jpg\"/>"
+        );
+
+        // 完整的图片标签不受影响，包括链接中含有空格的情况
+        let input = "Before [img]test.jpg[/img] after";
+        let result = RichBBCodeParser::new(input).parse();
+        assert_eq!(result, "Before <img src=\"test.jpg\"/> after");
+        let input = "[img]a b.jpg[/img]";
+        let result = RichBBCodeParser::new(input).parse();
+        assert_eq!(result, "<img src=\"a b.jpg\"/>");
+    }
+
     #[test]
     fn test_bbcode_parser_nested() {
         // 测试嵌套标签 - 这是新功能的核心测试
@@ -254,6 +531,38 @@ mod nga_tests {
         assert_eq!(result, "<b>粗体继续粗体</b>");
     }
 
+    #[test]
+    fn test_bbcode_parser_sticker_emoji() {
+        let parse = |input: &str| {
+            RichBBCodeParser::new(input)
+                .with_sticker_emoji(true)
+                .parse()
+        };
+
+        // 已知贴纸替换为 emoji
+        assert_eq!(parse("Test [s:ac:cry] more text"), "Test 😭 more text");
+        assert_eq!(parse("Hello [s:ac:赞同] world"), "Hello 👍 world");
+        // 嵌套的标签中同样替换
+        assert_eq!(
+            parse("[b]粗体[s:ac:smile]继续粗体[/b]"),
+            "<b>粗体🙂继续粗体</b>"
+        );
+        // 未知贴纸仍然移除
+        assert_eq!(parse("Hello [s:a2:unknown] world"), "Hello  world");
+
+        // 自定义映射优先，为空时移除
+        let overrides = vec![
+            ("ac:cry".to_string(), "😢".to_string()),
+            ("ac:smile".to_string(), String::new()),
+            ("a2:new".to_string(), "🆕".to_string()),
+        ];
+        assert_eq!(sticker_emoji("s:ac:cry", &overrides), Some("😢"));
+        assert_eq!(sticker_emoji("s:ac:smile", &overrides), None);
+        assert_eq!(sticker_emoji("s:a2:new", &overrides), Some("🆕"));
+        assert_eq!(sticker_emoji("s:ac:赞同", &overrides), Some("👍"));
+        assert_eq!(sticker_emoji("s:ac:unknown", &[]), None);
+    }
+
     #[test]
     fn test_bbcode_parser_flash() {
         // 测试 flash 标签（应该被移除，只保留内容）
@@ -583,6 +892,15 @@ mod nga_tests {
         assert_eq!(result, url);
     }
 
+    #[test]
+    fn test_preprocess_url_adds_scheme() {
+        let result = preprocess_url("bbs.gnacn.cc/read.php?tid=1");
+        assert_eq!(result, "https://bbs.gnacn.cc/read.php?tid=1");
+
+        let url = "http://bbs.nga.cn/read.php?tid=1";
+        assert_eq!(preprocess_url(url), url);
+    }
+
     #[test]
     fn test_bbcode_url_parsing() {
         // 测试带参数的URL: [url=https://x.com]推特[/url]
@@ -687,6 +1005,25 @@ mod nga_tests {
         assert!(result.contains("第二列"));
     }
 
+    #[test]
+    fn test_table_nested() {
+        let input = "[table][tr][td]名称[/td][td]说明[/td][/tr][tr][td]A[/td][td][table][tr][td]x[/td][td]y[/td][/tr][tr][td]z[/td][td]w[/td][/tr][/table][/td][/tr][tr][td]B[/td][td]C[/td][/tr][/table]";
+
+        let mut parser = RichBBCodeParser::new(input);
+        let result = parser.parse();
+
+        println!("嵌套表格输入: {}", input);
+        println!("嵌套表格结果: {}", result);
+
+        // 内层表格展开为文本，外层表格的行和单元格不受影响
+        assert_eq!(result.matches("<table>").count(), 1);
+        assert_eq!(result.matches("<tr>").count(), 3);
+        assert!(result.contains("<td>A</td><td>x │ y\nz │ w</td>"));
+        assert!(result.contains("<tr><td>B</td><td>C</td></tr>"));
+        assert!(!result.contains("[/td]"));
+        assert!(!result.contains("[/tr]"));
+    }
+
     #[test]
     fn test_collapse_tags() {
         // 测试带标题的 collapse 标签（Rich 解析器用 <details>）
@@ -1,4 +1,4 @@
-use common::get_env_var;
+use common::{rewrite_through_proxy, settings};
 use regex::Regex;
 use std::{
     sync::LazyLock,
@@ -6,15 +6,124 @@ use std::{
 };
 
 pub const NGA_UA: &str = "NGA_skull/6.0.5(iPhone10,3;iOS 12.0.1)";
+const NGA_ATTACHMENT_PREFIX: &str = "https://img.nga.178.com/attachments/";
+
+/// 附件链接中会返回低画质图片的查询参数
+const LOWRES_QUERY_KEYS: &[&str] = &["quality", "q", "size", "w", "h", "width", "height", "thumb"];
 
 // ==== 图片 ====
 
 // 处理 NGA 图片链接
 pub fn img_link_process(img_link: &str) -> String {
+    let settings = settings();
+    let mut processed_link = normalize_img_link(img_link);
+
+    // 设置 NGA_FORCE_FULLRES 时，移除低画质的查询参数
+    if settings.nga_force_fullres {
+        processed_link = strip_lowres_params(&processed_link);
+    }
+
+    // 设置 NGA_PREFER_JPG 时，将 webp 附件替换为 jpg
+    if settings.nga_prefer_jpg {
+        processed_link = prefer_jpg_extension(&processed_link);
+    }
+
+    // 设置 NGA_ATTACH_TOKEN 时，为附件链接附加签名参数
+    if let Some(token) = &settings.nga_attach_token {
+        processed_link = append_attach_token(&processed_link, token);
+    }
+
+    // 设置 NGA_IMAGE_PROXY 时，通过反向代理访问附件
+    if let Some(proxy) = &settings.nga_image_proxy {
+        processed_link = proxy_attachment_link(&processed_link, proxy);
+    }
+
+    processed_link
+}
+
+/// 将 NGA 附件链接改写为经过代理的链接，非 NGA 附件或代理无效时保持不变
+pub fn proxy_attachment_link(img_link: &str, proxy: &str) -> String {
+    let proxy = proxy.trim();
+    if proxy.is_empty() || !img_link.starts_with(NGA_ATTACHMENT_PREFIX) {
+        return img_link.to_string();
+    }
+
+    rewrite_through_proxy(img_link, proxy).unwrap_or_else(|e| {
+        log::warn!("Failed to rewrite NGA image through proxy: {}", e);
+        img_link.to_string()
+    })
+}
+
+/// 为 NGA 附件链接附加 `token` 查询参数，已存在 token 时保持不变
+pub fn append_attach_token(img_link: &str, token: &str) -> String {
+    let token = token.trim();
+    if token.is_empty() || !img_link.starts_with(NGA_ATTACHMENT_PREFIX) {
+        return img_link.to_string();
+    }
+
+    let Ok(mut url) = url::Url::parse(img_link) else {
+        return img_link.to_string();
+    };
+    if url.query_pairs().any(|(key, _)| key == "token") {
+        return img_link.to_string();
+    }
+
+    url.query_pairs_mut().append_pair("token", token);
+    url.to_string()
+}
+
+/// 移除 NGA 附件链接中的画质和尺寸查询参数，保留 `token` 等其他参数
+/// 非 NGA 附件链接保持不变
+pub fn strip_lowres_params(img_link: &str) -> String {
+    if !img_link.starts_with(NGA_ATTACHMENT_PREFIX) {
+        return img_link.to_string();
+    }
+    let Ok(mut url) = url::Url::parse(img_link) else {
+        return img_link.to_string();
+    };
+
+    let is_lowres = |key: &str| {
+        LOWRES_QUERY_KEYS
+            .iter()
+            .any(|lowres| key.eq_ignore_ascii_case(lowres))
+    };
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if !pairs.iter().any(|(key, _)| is_lowres(key)) {
+        return img_link.to_string();
+    }
+
+    let kept: Vec<_> = pairs.iter().filter(|(key, _)| !is_lowres(key)).collect();
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    url.to_string()
+}
+
+/// 将 NGA 附件的 `.webp` 扩展名替换为 `.jpg`，非 NGA 附件链接保持不变
+pub fn prefer_jpg_extension(img_link: &str) -> String {
+    if !img_link.starts_with(NGA_ATTACHMENT_PREFIX) {
+        return img_link.to_string();
+    }
+
+    let len = img_link.len();
+    if len >= 5 && img_link[len - 5..].eq_ignore_ascii_case(".webp") {
+        format!("{}.jpg", &img_link[..len - 5])
+    } else {
+        img_link.to_string()
+    }
+}
+
+// 补全相对链接并转换为高画质链接
+fn normalize_img_link(img_link: &str) -> String {
     let processed_link = if img_link.starts_with("http://") || img_link.starts_with("https://") {
         img_link.to_string()
     } else if img_link.len() >= 2 && img_link.starts_with("./") {
-        format!("https://img.nga.178.com/attachments/{}", &img_link[2..])
+        format!("{}{}", NGA_ATTACHMENT_PREFIX, &img_link[2..])
     } else {
         img_link.to_string()
     };
@@ -88,27 +197,36 @@ pub fn get_nga_guest_cookie() -> String {
 }
 
 pub fn get_nga_cookie() -> String {
-    let uid = get_env_var("NGA_UID");
-    let cid = get_env_var("NGA_CID");
+    let settings = settings();
+    build_nga_cookie(
+        settings.nga_cookie.clone(),
+        settings.nga_uid.clone(),
+        settings.nga_cid.clone(),
+    )
+}
 
-    if uid.is_none() || cid.is_none() {
-        return get_nga_guest_cookie();
+/// 构建 NGA Cookie，优先级：完整 Cookie > UID/CID > 游客 Cookie
+pub fn build_nga_cookie(raw: Option<String>, uid: Option<String>, cid: Option<String>) -> String {
+    if let Some(raw) = raw.filter(|raw| !raw.trim().is_empty()) {
+        return raw.trim().to_string();
     }
 
-    format!(
-        "ngaPassportUid={};ngaPassportCid={}",
-        uid.unwrap(),
-        cid.unwrap()
-    )
+    match (uid, cid) {
+        (Some(uid), Some(cid)) => format!("ngaPassportUid={};ngaPassportCid={}", uid, cid),
+        _ => get_nga_guest_cookie(),
+    }
 }
 
 // ==== URL 处理 ====
 
+/// 为缺少协议的链接补全 https://
 /// 当链接参数同时存在pid和opt时，删除opt参数
 /// 删除可能存在的page参数
 pub fn preprocess_url(url: &str) -> String {
+    let url = common::normalize_url(url);
+
     // 解析URL
-    if let Ok(mut parsed_url) = url::Url::parse(url) {
+    if let Ok(mut parsed_url) = url::Url::parse(&url) {
         let mut has_pid = false;
         let mut has_opt = false;
         let mut has_page = false;
@@ -164,5 +282,5 @@ pub fn preprocess_url(url: &str) -> String {
     }
 
     // 如果解析失败或不需要处理，返回原URL
-    url.to_string()
+    url
 }
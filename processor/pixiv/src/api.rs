@@ -1,26 +1,24 @@
-use anyhow::{Result, anyhow};
-use common::get_env_var;
+use anyhow::Result;
+use common::{ProcessorError, ProcessorErrorKind, settings};
 
 use crate::constants::PIXIV_UA;
-use crate::models::{PixivApiResponse};
+use crate::models::{PixivAjaxStatus, PixivApiResponse, PixivSeriesApiResponse};
 
-/// 获取 Pixiv 作品信息（Ajax API）
-pub async fn get_pixiv_info(id: &str) -> Result<PixivApiResponse> {
-    log::debug!("Fetching Pixiv image with ID: {}", id);
+/// 需要登录才能访问的 API，用于检查 Cookie 是否有效
+const AUTH_CHECK_URL: &str = "https://www.pixiv.net/ajax/user/extra";
 
-    // 构建 Pixiv API URL
-    let api_url = format!("https://www.pixiv.net/ajax/illust/{}", id);
+/// 请求 Pixiv Ajax API 并返回响应文本
+async fn fetch_pixiv_ajax(api_url: &str) -> Result<String> {
     log::debug!("Pixiv API URL: {}", api_url);
 
     // 创建HTTP客户端，设置必要的请求头
-    let client = reqwest::Client::new();
+    let client = common::build_http_client(PIXIV_UA, common::ACCEPT_JSON)?;
     let request = client
-        .get(&api_url)
-        .header("User-Agent", PIXIV_UA)
+        .get(api_url)
         .header("Referer", "https://www.pixiv.net/");
 
     // 如果有PHPSESSID环境变量，添加到请求头
-    let request = if let Some(session_id) = get_env_var("PIXIV_COOKIE") {
+    let request = if let Some(session_id) = &settings().pixiv_cookie {
         request.header("Cookie", format!("PHPSESSID={}", session_id))
     } else {
         request
@@ -29,23 +27,150 @@ pub async fn get_pixiv_info(id: &str) -> Result<PixivApiResponse> {
     let response = request.send()
         .await?;
 
-    if !response.status().is_success() {
-        return Err(anyhow!(
-            "Failed to fetch Pixiv data: HTTP {}",
-            response.status()
-        ));
+    let status = response.status();
+    if !status.is_success() {
+        return Err(status_error(status));
     }
 
     let text = response.text().await?;
     log::trace!("Pixiv API response: {}", text);
 
-    // 解析JSON响应
-    let api_response: PixivApiResponse = serde_json::from_str(&text)
-        .map_err(|e| anyhow!("Failed to parse Pixiv API response: {}", e))?;
+    Ok(text)
+}
+
+/// 构建请求失败的错误，被限流时提示稍后再试
+pub(crate) fn status_error(status: reqwest::StatusCode) -> anyhow::Error {
+    let kind = ProcessorErrorKind::from_status(status.as_u16());
+    let message = match kind {
+        ProcessorErrorKind::RateLimited => {
+            format!("Pixiv 请求过于频繁，请稍后再试: HTTP {}", status)
+        }
+        _ => format!("Failed to fetch Pixiv data: HTTP {}", status),
+    };
+    ProcessorError::new(message).with_kind(kind).into()
+}
+
+/// 构建响应解析失败的错误
+fn parse_error(message: &str, error: serde_json::Error) -> anyhow::Error {
+    ProcessorError::new(format!("{}: {}", message, error))
+        .with_kind(ProcessorErrorKind::Parse)
+        .into()
+}
+
+/// 构建 API 返回的错误，通常是作品已删除或不存在
+fn api_error(message: &str) -> anyhow::Error {
+    ProcessorError::new(format!("Pixiv API error: {}", message))
+        .with_kind(ProcessorErrorKind::NotFound)
+        .into()
+}
+
+/// 检查 API 是否返回错误
+/// 出错时 body 为空数组而非对象，需要在完整解析之前检查
+fn check_api_status(text: &str, message: &str) -> Result<()> {
+    let status: PixivAjaxStatus =
+        serde_json::from_str(text).map_err(|e| parse_error(message, e))?;
+    if status.error {
+        return Err(api_error(&status.message));
+    }
+    Ok(())
+}
+
+/// 获取 Pixiv 作品信息（Ajax API）
+pub async fn get_pixiv_info(id: &str) -> Result<PixivApiResponse> {
+    log::debug!("Fetching Pixiv image with ID: {}", id);
+
+    // 构建 Pixiv API URL
+    let api_url = format!("https://www.pixiv.net/ajax/illust/{}", id);
+    let text = fetch_pixiv_ajax(&api_url).await?;
+    parse_pixiv_info(&text)
+}
+
+/// 解析作品信息的 Ajax API 响应
+pub(crate) fn parse_pixiv_info(text: &str) -> Result<PixivApiResponse> {
+    const MESSAGE: &str = "Failed to parse Pixiv API response";
+    check_api_status(text, MESSAGE)?;
+    serde_json::from_str(text).map_err(|e| parse_error(MESSAGE, e))
+}
+
+/// 获取 Pixiv 系列信息（Ajax API，仅第一页）
+pub async fn get_pixiv_series_info(series_id: &str) -> Result<PixivSeriesApiResponse> {
+    log::debug!("Fetching Pixiv series with ID: {}", series_id);
 
-    if api_response.error {
-        return Err(anyhow!("Pixiv API error: {}", api_response.message));
+    let api_url = format!("https://www.pixiv.net/ajax/series/{}?p=1", series_id);
+    let text = fetch_pixiv_ajax(&api_url).await?;
+    parse_pixiv_series_info(&text)
+}
+
+/// 解析系列信息的 Ajax API 响应
+pub(crate) fn parse_pixiv_series_info(text: &str) -> Result<PixivSeriesApiResponse> {
+    const MESSAGE: &str = "Failed to parse Pixiv series response";
+    check_api_status(text, MESSAGE)?;
+    serde_json::from_str(text).map_err(|e| parse_error(MESSAGE, e))
+}
+
+/// Pixiv 登录状态
+#[derive(Debug, Clone, PartialEq)]
+pub enum PixivAuthStatus {
+    /// 未设置 PIXIV_COOKIE
+    NotConfigured,
+    Valid,
+    /// Pixiv 拒绝了当前的 Cookie
+    Invalid(String),
+    /// 请求失败，无法确认登录状态
+    Unknown(String),
+}
+
+/// 检查 PIXIV_COOKIE 是否有效
+pub async fn check_pixiv_auth() -> PixivAuthStatus {
+    if settings().pixiv_cookie.is_none() {
+        return PixivAuthStatus::NotConfigured;
+    }
+
+    match fetch_pixiv_ajax(AUTH_CHECK_URL).await {
+        Ok(text) => parse_auth_response(&text),
+        Err(e) if ProcessorErrorKind::from_error(&e) == ProcessorErrorKind::Forbidden => {
+            PixivAuthStatus::Invalid(e.to_string())
+        }
+        Err(e) => PixivAuthStatus::Unknown(e.to_string()),
+    }
+}
+
+/// 解析登录检查的响应
+pub(crate) fn parse_auth_response(text: &str) -> PixivAuthStatus {
+    match serde_json::from_str::<PixivAjaxStatus>(text) {
+        Ok(status) if !status.error => PixivAuthStatus::Valid,
+        Ok(status) => PixivAuthStatus::Invalid(status.message),
+        Err(e) => PixivAuthStatus::Unknown(format!("Failed to parse Pixiv response: {}", e)),
     }
+}
+
+/// 生成登录状态的日志级别和内容
+pub fn describe_auth_status(status: &PixivAuthStatus) -> (log::Level, String) {
+    match status {
+        PixivAuthStatus::NotConfigured => (
+            log::Level::Info,
+            "Pixiv auth check skipped: PIXIV_COOKIE is not set".to_string(),
+        ),
+        PixivAuthStatus::Valid => (
+            log::Level::Info,
+            "Pixiv auth check passed: PIXIV_COOKIE is valid".to_string(),
+        ),
+        PixivAuthStatus::Invalid(message) => (
+            log::Level::Warn,
+            format!(
+                "Pixiv auth check failed: PIXIV_COOKIE was rejected ({}), restricted works will not be available",
+                message
+            ),
+        ),
+        PixivAuthStatus::Unknown(message) => (
+            log::Level::Warn,
+            format!("Pixiv auth check could not complete: {}", message),
+        ),
+    }
+}
 
-    Ok(api_response)
+/// 检查 Pixiv 登录状态并记录日志，失败不影响启动
+pub async fn log_pixiv_auth_status() {
+    let (level, message) = describe_auth_status(&check_pixiv_auth().await);
+    log::log!(level, "{}", message);
 }
@@ -1,2 +1,4 @@
 pub const PIXIV_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Safari/537.36";
 pub const REVERSE_PROXY_URL: &str = "https://www.phixiv.net/i/";
+/// 系列链接最多列出的作品数量
+pub const SERIES_MAX_WORKS: usize = 20;
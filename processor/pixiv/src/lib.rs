@@ -1,4 +1,6 @@
-use common::{LinkProcessor, ProcessorError, ProcessorResult, ProcessorResultType};
+use common::{
+    LinkProcessor, ProcessorError, ProcessorErrorKind, ProcessorResult, ProcessorResultType,
+};
 use regex::Regex;
 use std::sync::OnceLock;
 
@@ -9,9 +11,11 @@ mod processor;
 mod tests;
 mod utils;
 
-use processor::get_pixiv;
+pub use api::{PixivAuthStatus, check_pixiv_auth, describe_auth_status, log_pixiv_auth_status};
+use processor::{get_pixiv, get_pixiv_series};
 
 static PIXIV_REGEX: OnceLock<Regex> = OnceLock::new();
+static PIXIV_SERIES_REGEX: OnceLock<Regex> = OnceLock::new();
 
 /// Pixiv链接处理器
 pub struct PixivLinkProcessor;
@@ -30,27 +34,96 @@ impl LinkProcessor for PixivLinkProcessor {
         PIXIV_REGEX.get_or_init(|| Regex::new(Self::PATTERN).expect("Invalid Pixiv regex pattern"))
     }
 
-    async fn process_captures(&self, captures: &regex::Captures<'_>) -> ProcessorResultType {
+    async fn process_captures(
+        &self,
+        captures: &regex::Captures<'_>,
+        truncation: bool,
+    ) -> ProcessorResultType {
         if let Some(id_match) = captures.get(1) {
             let id = id_match.as_str();
-            match get_pixiv(id).await {
+            match get_pixiv(id, truncation).await {
                 Ok(parsed) => {
                     if parsed.urls.is_empty() {
                         return Ok(ProcessorResult::Text(parsed.caption));
                     }
                     Ok(ProcessorResult::Media(parsed))
                 }
-                Err(e) => Err(ProcessorError::with_source(
-                    "处理Pixiv链接失败",
-                    e.to_string(),
-                )),
+                Err(e) => Err(
+                    ProcessorError::with_source("处理Pixiv链接失败", e.to_string())
+                        .with_kind(ProcessorErrorKind::from_error(&e)),
+                ),
             }
         } else {
-            Err(ProcessorError::new("无法从Pixiv链接中提取作品ID"))
+            Err(ProcessorError::new("无法从Pixiv链接中提取作品ID")
+                .with_kind(ProcessorErrorKind::Parse))
         }
     }
 
     fn name(&self) -> &'static str {
         "Pixiv"
     }
+
+    fn footer(&self) -> String {
+        "🎨 Pixiv".to_string()
+    }
+
+    fn link_hints(&self) -> &'static [&'static str] {
+        &["pixiv.net"]
+    }
+
+    fn example_urls(&self) -> &'static [&'static str] {
+        &[
+            "https://www.pixiv.net/artworks/123456",
+            "https://www.pixiv.net/artworks/123456?p=1",
+        ]
+    }
+}
+
+/// Pixiv系列链接处理器
+pub struct PixivSeriesLinkProcessor;
+
+impl PixivSeriesLinkProcessor {
+    const PATTERN: &'static str = r"(?:https?://)?(?:www\.)?pixiv\.net/user/(\d+)/series/(\d+)";
+}
+
+#[async_trait::async_trait]
+impl LinkProcessor for PixivSeriesLinkProcessor {
+    fn pattern(&self) -> &'static str {
+        Self::PATTERN
+    }
+
+    fn regex(&self) -> &Regex {
+        PIXIV_SERIES_REGEX
+            .get_or_init(|| Regex::new(Self::PATTERN).expect("Invalid Pixiv series regex pattern"))
+    }
+
+    async fn process_captures(
+        &self,
+        captures: &regex::Captures<'_>,
+        _truncation: bool,
+    ) -> ProcessorResultType {
+        let (Some(user_id), Some(series_id)) = (captures.get(1), captures.get(2)) else {
+            return Err(ProcessorError::new("无法从Pixiv系列链接中提取系列ID")
+                .with_kind(ProcessorErrorKind::Parse));
+        };
+        match get_pixiv_series(user_id.as_str(), series_id.as_str()).await {
+            Ok(text) => Ok(ProcessorResult::Text(text)),
+            Err(e) => Err(
+                ProcessorError::with_source("处理Pixiv系列链接失败", e.to_string())
+                    .with_kind(ProcessorErrorKind::from_error(&e)),
+            ),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Pixiv Series"
+    }
+
+    fn link_hints(&self) -> &'static [&'static str] {
+        &["pixiv.net"]
+    }
+
+    fn example_urls(&self) -> &'static [&'static str] {
+        &["https://www.pixiv.net/user/123/series/456"]
+    }
 }
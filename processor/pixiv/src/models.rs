@@ -1,10 +1,8 @@
 use serde::Deserialize;
 
-/// Pixiv Ajax API 响应
+/// Pixiv Ajax API 响应，错误状态由 [`PixivAjaxStatus`] 检查
 #[derive(Debug, Deserialize)]
 pub struct PixivApiResponse {
-    pub error: bool,
-    pub message: String,
     pub body: Option<PixivIllustBody>,
 }
 
@@ -41,3 +39,59 @@ pub struct PixivUrls {
     pub regular: Option<String>,
     // pub original: Option<String>,
 }
+
+/// Pixiv 系列 Ajax API 响应
+#[derive(Debug, Deserialize)]
+pub struct PixivSeriesApiResponse {
+    pub body: Option<PixivSeriesBody>,
+}
+
+/// Pixiv 系列信息
+#[derive(Debug, Deserialize)]
+pub struct PixivSeriesBody {
+    #[serde(rename = "illustSeries", default)]
+    pub illust_series: Vec<PixivIllustSeries>,
+    pub page: PixivSeriesPage,
+    pub thumbnails: Option<PixivSeriesThumbnails>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PixivIllustSeries {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PixivSeriesPage {
+    #[serde(default)]
+    pub series: Vec<PixivSeriesWork>,
+    #[serde(default)]
+    pub total: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PixivSeriesWork {
+    #[serde(rename = "workId")]
+    pub work_id: String,
+    pub order: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PixivSeriesThumbnails {
+    #[serde(default)]
+    pub illust: Vec<PixivSeriesThumbnail>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PixivSeriesThumbnail {
+    pub id: String,
+    pub title: String,
+}
+
+/// 只关心是否出错的 Ajax API 响应
+#[derive(Debug, Deserialize)]
+pub struct PixivAjaxStatus {
+    pub error: bool,
+    #[serde(default)]
+    pub message: String,
+}
@@ -1,12 +1,17 @@
 use anyhow::Result;
 use common::ProcessorResultMedia;
 
-use crate::api::get_pixiv_info;
-use crate::utils::{build_pixiv_caption, convert_to_proxy_url, get_urls_from_count};
+use crate::api::{get_pixiv_info, get_pixiv_series_info};
+use crate::models::PixivIllustBody;
+use crate::utils::{
+    build_pixiv_caption, build_pixiv_media_caption, build_series_text, convert_to_proxy_url,
+    get_urls_from_count,
+};
 
 /// 获取Pixiv图片，支持代理URL转换
-pub async fn get_pixiv(id: &str) -> Result<ProcessorResultMedia> {
-    let mut result = get_pixiv_image(id).await?;
+/// `truncation` 控制是否截断过长的作品描述
+pub async fn get_pixiv(id: &str, truncation: bool) -> Result<ProcessorResultMedia> {
+    let mut result = get_pixiv_image(id, truncation).await?;
 
     let use_proxy = true;
 
@@ -22,31 +27,45 @@ pub async fn get_pixiv(id: &str) -> Result<ProcessorResultMedia> {
     Ok(result)
 }
 
-async fn get_pixiv_image(id: &str) -> Result<ProcessorResultMedia> {
+async fn get_pixiv_image(id: &str, truncation: bool) -> Result<ProcessorResultMedia> {
     let api_response = get_pixiv_info(id).await?;
 
     let body = api_response
         .body
         .ok_or_else(|| anyhow::anyhow!("Empty response body from Pixiv API"))?;
 
-    // 构建返回文本
-    let text = build_pixiv_caption(&body)?;
+    parse_pixiv_body(id, &body, truncation)
+}
 
+/// 将作品信息转换为媒体结果，图片链接尚未替换为代理链接
+pub(crate) fn parse_pixiv_body(
+    id: &str,
+    body: &PixivIllustBody,
+    truncation: bool,
+) -> Result<ProcessorResultMedia> {
     // 处理图片URL
     // HACK: Use regular quality instead of original to avoid telegram limit
     let Some(url) = body.urls.regular.as_ref() else {
         // 空图片URL，返回文本结果
         log::error!("No regular image URL found for Pixiv ID: {}", id);
-        return Ok(ProcessorResultMedia {
-            caption: text,
-            urls: Vec::new(),
-            spoiler: false,
-            original_urls: None,
-        });
+        let text = build_pixiv_caption(body, truncation)?;
+        return Ok(ProcessorResultMedia::new(text));
     };
 
-    let image_urls = if body.page_count > 1 {
-        get_urls_from_count(url, body.page_count)
+    // 构建返回文本，放不进说明文字的描述在图片之后发送
+    let (text, overflow_text) = build_pixiv_media_caption(body, truncation)?;
+
+    // 异常的响应中页数可能为 0，有图片链接时至少有一页
+    if body.page_count == 0 {
+        log::warn!(
+            "Pixiv ID {} has page count 0, treating as a single page",
+            id
+        );
+    }
+    let page_count = body.page_count.max(1);
+
+    let image_urls = if page_count > 1 {
+        get_urls_from_count(url, page_count)
     } else {
         vec![url.to_string()]
     };
@@ -54,10 +73,26 @@ async fn get_pixiv_image(id: &str) -> Result<ProcessorResultMedia> {
     // 检查 x_restrict 值
     let is_restrict = body.x_restrict > 0;
 
-    Ok(ProcessorResultMedia {
-        caption: text,
-        urls: image_urls.clone(),        // 这里会在后续被代理URL替换
-        spoiler: is_restrict,               // 如果是限制内容，设置 spoiler 为 true
-        original_urls: Some(image_urls), // 保存URL用于下载
+    let result = ProcessorResultMedia::new(text)
+        .with_urls(image_urls.clone()) // 这里会在后续被代理URL替换
+        .with_spoiler(is_restrict) // 如果是限制内容，设置 spoiler 为 true
+        .with_original_urls(image_urls) // 保存URL用于下载
+        // regular 尺寸（master1200）的图片都是 JPEG，代理链接没有扩展名时也不需要 HEAD 请求
+        .with_content_type("image/jpeg");
+
+    Ok(match overflow_text {
+        Some(overflow_text) => result.with_overflow_text(overflow_text),
+        None => result,
     })
 }
+
+/// 获取Pixiv系列，返回系列标题和作品链接列表
+pub async fn get_pixiv_series(user_id: &str, series_id: &str) -> Result<String> {
+    let api_response = get_pixiv_series_info(series_id).await?;
+
+    let body = api_response
+        .body
+        .ok_or_else(|| anyhow::anyhow!("Empty response body from Pixiv API"))?;
+
+    build_series_text(user_id, series_id, &body)
+}
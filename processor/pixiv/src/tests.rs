@@ -13,7 +13,7 @@ mod pixiv_tests {
         let id = "116383713"; // normal
         // let id = "132616032"; // R18
 
-        match get_pixiv(id).await {
+        match get_pixiv(id, true).await {
             Ok(result) => {
                 println!("获取成功:");
                 println!("文本: {}", result.caption);
@@ -34,7 +34,7 @@ mod pixiv_tests {
         // 测试多张图片的R18内容
         let id = "126189425"; // 多张图片的R18作品
 
-        match get_pixiv(id).await {
+        match get_pixiv(id, true).await {
             Ok(result) => {
                 println!("多张R18图片测试:");
                 println!("图片数量: {}", result.urls.len());
@@ -150,7 +150,7 @@ mod pixiv_tests {
             x_restrict: 0,
         };
 
-        let result = build_pixiv_caption(&body_with_all_info).expect("应该成功构建文本");
+        let result = build_pixiv_caption(&body_with_all_info, true).expect("应该成功构建文本");
         println!("完整信息测试结果:\n{}", result);
 
         assert!(result.contains("测试标题"));
@@ -171,7 +171,7 @@ mod pixiv_tests {
             x_restrict: 0,
         };
 
-        let result_basic = build_pixiv_caption(&body_basic).expect("应该成功构建基本文本");
+        let result_basic = build_pixiv_caption(&body_basic, true).expect("应该成功构建基本文本");
         println!("\n基本信息测试结果:\n{}", result_basic);
 
         assert!(result_basic.contains("简单标题"));
@@ -192,10 +192,329 @@ mod pixiv_tests {
             x_restrict: 0,
         };
 
-        let result_empty_tags = build_pixiv_caption(&body_empty_tags).expect("应该成功构建文本");
+        let result_empty_tags =
+            build_pixiv_caption(&body_empty_tags, true).expect("应该成功构建文本");
         println!("\n空标签测试结果:\n{}", result_empty_tags);
 
         assert!(result_empty_tags.contains("有描述但无标签"));
         assert!(!result_empty_tags.contains("标签:")); // 不应该包含标签行
+
+        // 不截断时长描述原样保留，不放入可折叠引用
+        let body_long_desc = PixivIllustBody {
+            description: "长".repeat(common::SUMMARY_NORMAL_LIMIT + 10),
+            ..body_empty_tags
+        };
+        let result_full = build_pixiv_caption(&body_long_desc, false).expect("应该成功构建文本");
+        assert!(!result_full.contains("<blockquote"));
+        assert!(result_full.contains(&"长".repeat(common::SUMMARY_NORMAL_LIMIT + 10)));
+        let result_truncated =
+            build_pixiv_caption(&body_long_desc, true).expect("应该成功构建文本");
+        assert!(result_truncated.contains("<blockquote expandable>"));
+    }
+
+    #[test]
+    fn test_build_pixiv_caption_escape() {
+        use crate::models::{PixivIllustBody, PixivTag, PixivTags, PixivUrls};
+
+        // 标题和作者名包含HTML特殊字符时应被转义
+        let body = PixivIllustBody {
+            id: "123456".to_string(),
+            title: "<b>Tom & Jerry</b>".to_string(),
+            user_id: "654321".to_string(),
+            user_name: "a<b>c".to_string(),
+            description: "".to_string(),
+            page_count: 1,
+            urls: PixivUrls::default(),
+            tags: Some(PixivTags {
+                tags: vec![PixivTag {
+                    tag: "R&D".to_string(),
+                }],
+            }),
+            x_restrict: 0,
+        };
+
+        let result = build_pixiv_caption(&body, true).expect("应该成功构建文本");
+        println!("特殊字符测试结果:\n{}", result);
+
+        assert!(result.contains("&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;"));
+        assert!(result.contains("a&lt;b&gt;c"));
+        assert!(result.contains("#R&amp;D"));
+        assert!(!result.contains("<b>Tom"));
+        assert!(!result.contains("a<b>c"));
+    }
+
+    #[test]
+    fn test_series_regex() {
+        use crate::PixivSeriesLinkProcessor;
+        use common::LinkProcessor;
+
+        let processor = PixivSeriesLinkProcessor;
+        let test_cases = vec![
+            (
+                "https://www.pixiv.net/user/123456/series/7890",
+                "123456",
+                "7890",
+            ),
+            ("https://pixiv.net/user/1/series/2", "1", "2"),
+            ("pixiv.net/user/42/series/99", "42", "99"),
+        ];
+
+        for (url, user_id, series_id) in test_cases {
+            let captures = processor.regex().captures(url).expect("应该匹配系列链接");
+            assert_eq!(&captures[1], user_id, "Failed for URL: {}", url);
+            assert_eq!(&captures[2], series_id, "Failed for URL: {}", url);
+        }
+
+        // 作品链接不应被系列处理器匹配
+        assert!(
+            !processor
+                .regex()
+                .is_match("https://www.pixiv.net/artworks/123456")
+        );
+    }
+
+    #[test]
+    fn test_build_series_text() {
+        use crate::constants::SERIES_MAX_WORKS;
+        use crate::models::PixivSeriesApiResponse;
+        use crate::utils::build_series_text;
+
+        let json = r#"{
+            "error": false,
+            "message": "",
+            "body": {
+                "illustSeries": [
+                    {"id": "7890", "userId": "123456", "title": "测试<系列>", "total": 3}
+                ],
+                "page": {
+                    "series": [
+                        {"workId": "1002", "order": 2},
+                        {"workId": "1001", "order": 1},
+                        {"workId": "1003", "order": 3}
+                    ],
+                    "total": 3
+                },
+                "thumbnails": {
+                    "illust": [
+                        {"id": "1001", "title": "第一话"},
+                        {"id": "1002", "title": "第二话"}
+                    ]
+                }
+            }
+        }"#;
+
+        let response: PixivSeriesApiResponse = serde_json::from_str(json).expect("应该成功解析");
+        let body = response.body.expect("应该包含body");
+        let text = build_series_text("123456", "7890", &body).expect("应该成功构建文本");
+        println!("系列测试结果:\n{}", text);
+
+        assert!(text.contains("https://www.pixiv.net/user/123456/series/7890"));
+        assert!(text.contains("测试&lt;系列&gt;"));
+        assert!(text.contains("1. <a href=\"https://www.pixiv.net/artworks/1001\">第一话</a>"));
+        assert!(text.contains("2. <a href=\"https://www.pixiv.net/artworks/1002\">第二话</a>"));
+        // 缺少缩略图信息时使用作品ID作为标题
+        assert!(text.contains("3. <a href=\"https://www.pixiv.net/artworks/1003\">1003</a>"));
+        assert!(!text.contains("共"));
+
+        // 超过上限时截断并显示总数
+        let works: Vec<String> = (1..=SERIES_MAX_WORKS + 5)
+            .map(|i| format!(r#"{{"workId": "{}", "order": {}}}"#, i, i))
+            .collect();
+        let json = format!(
+            r#"{{"illustSeries": [], "page": {{"series": [{}], "total": {}}}}}"#,
+            works.join(","),
+            SERIES_MAX_WORKS + 5
+        );
+        let body = serde_json::from_str(&json).expect("应该成功解析");
+        let text = build_series_text("1", "2", &body).expect("应该成功构建文本");
+
+        assert_eq!(text.matches("<a href=").count(), SERIES_MAX_WORKS + 1);
+        assert!(text.contains(&format!("共 {} 个作品", SERIES_MAX_WORKS + 5)));
+    }
+
+    #[test]
+    fn test_pixiv_status_error() {
+        use crate::api::status_error;
+        use common::ProcessorErrorKind;
+        use reqwest::StatusCode;
+
+        let err = status_error(StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            ProcessorErrorKind::from_error(&err),
+            ProcessorErrorKind::RateLimited
+        );
+        assert!(err.to_string().contains("请稍后再试"));
+
+        let err = status_error(StatusCode::NOT_FOUND);
+        assert_eq!(
+            ProcessorErrorKind::from_error(&err),
+            ProcessorErrorKind::NotFound
+        );
+        assert!(err.to_string().contains("HTTP 404 Not Found"));
+
+        let err = status_error(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            ProcessorErrorKind::from_error(&err),
+            ProcessorErrorKind::Network
+        );
+    }
+
+    #[test]
+    fn test_pixiv_zero_page_count() {
+        use crate::models::{PixivIllustBody, PixivUrls};
+        use crate::processor::parse_pixiv_body;
+        use crate::utils::get_urls_from_count;
+
+        let url =
+            "https://i.pximg.net/img-master/img/2024/07/01/00/00/00/123456789_p0_master1200.jpg";
+        assert_eq!(get_urls_from_count(url, 0), vec![url.to_string()]);
+        assert_eq!(get_urls_from_count(url, 2).len(), 2);
+
+        // 页数为 0 但有图片链接时按一页处理
+        let body = PixivIllustBody {
+            id: "123456789".to_string(),
+            title: "标题".to_string(),
+            user_id: "11111".to_string(),
+            user_name: "作者".to_string(),
+            description: String::new(),
+            page_count: 0,
+            urls: PixivUrls {
+                regular: Some(url.to_string()),
+            },
+            tags: None,
+            x_restrict: 0,
+        };
+        let result = parse_pixiv_body("123456789", &body, true).expect("应该成功转换");
+        assert_eq!(result.urls, vec![url.to_string()]);
+        assert_eq!(result.original_urls, Some(vec![url.to_string()]));
+    }
+
+    #[test]
+    fn test_pixiv_long_description_overflow() {
+        use crate::models::{PixivIllustBody, PixivTag, PixivTags, PixivUrls};
+        use crate::processor::parse_pixiv_body;
+
+        let url =
+            "https://i.pximg.net/img-master/img/2024/07/01/00/00/00/123456789_p0_master1200.jpg";
+        let body = |description: String, regular: Option<&str>| PixivIllustBody {
+            id: "123456789".to_string(),
+            title: "标题".to_string(),
+            user_id: "11111".to_string(),
+            user_name: "作者".to_string(),
+            description,
+            page_count: 1,
+            urls: PixivUrls {
+                regular: regular.map(str::to_string),
+            },
+            tags: Some(PixivTags {
+                tags: vec![PixivTag {
+                    tag: "标签".to_string(),
+                }],
+            }),
+            x_restrict: 0,
+        };
+
+        // 描述放不进媒体说明时在图片之后单独发送，说明文字保留标题和标签
+        let long_desc = "描".repeat(common::MEDIA_CAPTION_LIMIT + 100);
+        let result = parse_pixiv_body("123456789", &body(long_desc.clone(), Some(url)), true)
+            .expect("应该成功转换");
+        assert!(result.caption.contains("标题"));
+        assert!(result.caption.contains("#标签"));
+        assert!(!result.caption.contains('描'));
+        assert!(result.caption.chars().count() <= common::MEDIA_CAPTION_LIMIT);
+        let overflow = result.overflow_text.expect("应该有后续文本");
+        assert!(overflow.starts_with("<blockquote expandable>"));
+        assert!(overflow.contains(&long_desc));
+
+        // 放得下时不拆分
+        let result = parse_pixiv_body("123456789", &body("短描述".to_string(), Some(url)), true)
+            .expect("应该成功转换");
+        assert!(result.caption.contains("短描述"));
+        assert_eq!(result.overflow_text, None);
+
+        // 没有图片时作为文本发送，不需要拆分
+        let result = parse_pixiv_body("123456789", &body(long_desc.clone(), None), true)
+            .expect("应该成功转换");
+        assert!(result.caption.contains(&long_desc));
+        assert_eq!(result.overflow_text, None);
+    }
+
+    #[test]
+    fn test_pixiv_fixture() {
+        use crate::api::parse_pixiv_info;
+        use crate::processor::parse_pixiv_body;
+        use common::{ProcessorErrorKind, ProcessorResultMedia};
+
+        let response =
+            parse_pixiv_info(include_str!("../fixtures/illust.json")).expect("应该成功解析");
+        let body = response.body.expect("应该包含body");
+        let result = parse_pixiv_body("123456789", &body, true).expect("应该成功转换");
+
+        let urls = (0..3)
+            .map(|i| {
+                format!(
+                    "https://i.pximg.net/img-master/img/2024/07/01/00/00/00/123456789_p{}_master1200.jpg",
+                    i
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            ProcessorResultMedia::new(
+                "<b><u><a href=\"https://www.pixiv.net/artworks/123456789\">夏の&lt;海&gt;</a></u></b> / \
+                 <b><u><a href=\"https://www.pixiv.net/users/11111\">テスト作者</a></u></b>\n\n\
+                 夏の海を描きました\nTwitter: @example\n\n\
+                 #オリジナル, #海, #夏"
+            )
+            .with_urls(urls.clone())
+            .with_original_urls(urls)
+            .with_content_type("image/jpeg")
+        );
+
+        // 出错时 body 为空数组
+        let err = parse_pixiv_info(include_str!("../fixtures/illust_not_found.json")).unwrap_err();
+        assert_eq!(
+            ProcessorErrorKind::from_error(&err),
+            ProcessorErrorKind::NotFound
+        );
+        assert!(err.to_string().contains("尚无此页"));
+    }
+
+    #[test]
+    fn test_pixiv_auth_status_reporting() {
+        use crate::api::parse_auth_response;
+        use crate::{PixivAuthStatus, describe_auth_status};
+
+        assert_eq!(
+            parse_auth_response(r#"{"error":false,"message":"","body":{"following":1}}"#),
+            PixivAuthStatus::Valid
+        );
+        assert_eq!(
+            parse_auth_response(r#"{"error":true,"message":"Unauthorized","body":[]}"#),
+            PixivAuthStatus::Invalid("Unauthorized".to_string())
+        );
+        assert!(matches!(
+            parse_auth_response("<html>"),
+            PixivAuthStatus::Unknown(_)
+        ));
+
+        let (level, message) = describe_auth_status(&PixivAuthStatus::Valid);
+        assert_eq!(level, log::Level::Info);
+        assert!(message.contains("passed"));
+
+        let (level, message) = describe_auth_status(&PixivAuthStatus::NotConfigured);
+        assert_eq!(level, log::Level::Info);
+        assert!(message.contains("PIXIV_COOKIE is not set"));
+
+        let (level, message) =
+            describe_auth_status(&PixivAuthStatus::Invalid("Unauthorized".to_string()));
+        assert_eq!(level, log::Level::Warn);
+        assert!(message.contains("rejected"));
+        assert!(message.contains("Unauthorized"));
+
+        let (level, message) =
+            describe_auth_status(&PixivAuthStatus::Unknown("timeout".to_string()));
+        assert_eq!(level, log::Level::Warn);
+        assert!(message.contains("timeout"));
     }
 }
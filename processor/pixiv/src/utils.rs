@@ -1,22 +1,15 @@
 use anyhow::{Result, anyhow};
-use common::{get_env_var, join_url};
+use common::{MEDIA_CAPTION_LIMIT, escape_html, join_url, rewrite_through_proxy, settings};
 use regex::Regex;
 use url::Url;
 
-use crate::constants::REVERSE_PROXY_URL;
-use crate::models::PixivIllustBody;
-
-/// 转义HTML特殊字符，防止Telegram将文本内容识别为HTML标签
-fn escape_html(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-}
+use crate::constants::{REVERSE_PROXY_URL, SERIES_MAX_WORKS};
+use crate::models::{PixivIllustBody, PixivSeriesBody};
 
 /// 获取反向代理URL
 fn get_reverse_proxy_url() -> Result<String> {
-    let url = get_env_var("PIXIV_IMAGE_PROXY").unwrap_or_else(|| {
-        // 如果环境变量未设置，使用默认值
+    let url = settings().pixiv_image_proxy.clone().unwrap_or_else(|| {
+        // 如果未设置代理，使用默认值
         REVERSE_PROXY_URL.to_string()
     });
 
@@ -28,26 +21,46 @@ fn get_reverse_proxy_url() -> Result<String> {
 
 /// 将Pixiv原始URL转换为代理URL
 pub fn convert_to_proxy_url(original_url: &str) -> Result<String> {
-    let original_url = Url::parse(original_url)?;
-    let proxy_url = Url::parse(get_reverse_proxy_url()?.as_str())?;
-
-    let relative_path = original_url
-        .path()
-        .strip_prefix("/")
-        .unwrap_or(original_url.path());
+    rewrite_through_proxy(original_url, &get_reverse_proxy_url()?)
+}
 
-    let mut final_url = proxy_url.join(relative_path)?;
+/// 构建Pixiv作品的标题文本
+pub fn build_pixiv_caption(body: &PixivIllustBody, truncation: bool) -> Result<String> {
+    let (header, description, tags) = build_caption_parts(body, truncation)?;
+    Ok(join_caption(
+        header,
+        description.as_deref(),
+        tags.as_deref(),
+    ))
+}
 
-    // 将原始 URL 的查询参数（?后面的部分）附加到新 URL 上
-    if let Some(query) = original_url.query() {
-        final_url.set_query(Some(query));
+/// 构建Pixiv作品的媒体说明文字，超过媒体说明长度上限时描述不放入说明文字
+/// 返回说明文字和需要在媒体之后单独发送的描述
+pub fn build_pixiv_media_caption(
+    body: &PixivIllustBody,
+    truncation: bool,
+) -> Result<(String, Option<String>)> {
+    let (header, description, tags) = build_caption_parts(body, truncation)?;
+    let caption = join_caption(header.clone(), description.as_deref(), tags.as_deref());
+    if caption.chars().count() <= MEDIA_CAPTION_LIMIT || description.is_none() {
+        return Ok((caption, None));
     }
+    Ok((join_caption(header, None, tags.as_deref()), description))
+}
 
-    Ok(final_url.to_string())
+/// 按顺序拼接标题行、描述和标签，空的部分不显示
+fn join_caption(mut text: String, description: Option<&str>, tags: Option<&str>) -> String {
+    for part in [description, tags].into_iter().flatten() {
+        text.push_str(&format!("\n\n{}", part));
+    }
+    text
 }
 
-/// 构建Pixiv作品的标题文本
-pub fn build_pixiv_caption(body: &PixivIllustBody) -> Result<String> {
+/// 构建作品说明的各部分：标题行、截取后的描述和标签
+fn build_caption_parts(
+    body: &PixivIllustBody,
+    truncation: bool,
+) -> Result<(String, Option<String>, Option<String>)> {
     // 转义标题和作者名，防止HTML字符导致发送失败
     let escaped_title = escape_html(&body.title);
     let escaped_user_name = escape_html(&body.user_name);
@@ -87,7 +100,7 @@ pub fn build_pixiv_caption(body: &PixivIllustBody) -> Result<String> {
     };
 
     // 构建文本，只显示非空字段
-    let mut text = format!(
+    let header = format!(
         "<b><u><a href=\"{}\">{}</a></u></b> / <b><u><a href=\"{}\">{}</a></u></b>",
         join_url("https://www.pixiv.net/artworks/", &body.id)?,
         escaped_title,
@@ -95,14 +108,61 @@ pub fn build_pixiv_caption(body: &PixivIllustBody) -> Result<String> {
         escaped_user_name
     );
 
-    if let Some(desc) = &description_text {
-        // 截取
-        let truncated_desc = common::substring_desc(desc);
-        text.push_str(&format!("\n\n{}", truncated_desc));
+    // 截取
+    let description =
+        description_text.map(|desc| common::substring_desc_with_truncation(&desc, truncation));
+
+    Ok((header, description, tags_text))
+}
+
+/// 构建Pixiv系列的文本，列出系列标题和其中的作品链接
+pub fn build_series_text(user_id: &str, series_id: &str, body: &PixivSeriesBody) -> Result<String> {
+    let series_url = format!(
+        "https://www.pixiv.net/user/{}/series/{}",
+        user_id, series_id
+    );
+
+    // 系列标题，找不到时退回系列ID
+    let title = body
+        .illust_series
+        .iter()
+        .find(|s| s.id == series_id)
+        .map(|s| s.title.as_str())
+        .unwrap_or(series_id);
+
+    let mut text = format!(
+        "<b><u><a href=\"{}\">{}</a></u></b>",
+        series_url,
+        escape_html(title)
+    );
+
+    // 按顺序排列作品
+    let mut works: Vec<_> = body.page.series.iter().collect();
+    works.sort_by_key(|w| w.order);
+
+    if !works.is_empty() {
+        text.push('\n');
+    }
+
+    for work in works.iter().take(SERIES_MAX_WORKS) {
+        let work_url = join_url("https://www.pixiv.net/artworks/", &work.work_id)?;
+        let work_title = body
+            .thumbnails
+            .as_ref()
+            .and_then(|t| t.illust.iter().find(|i| i.id == work.work_id))
+            .map(|i| escape_html(&i.title))
+            .unwrap_or_else(|| work.work_id.clone());
+        text.push_str(&format!(
+            "\n{}. <a href=\"{}\">{}</a>",
+            work.order, work_url, work_title
+        ));
     }
 
-    if let Some(tags) = tags_text {
-        text.push_str(&format!("\n\n{}", tags));
+    // 超出上限的作品只显示数量
+    let total = (body.page.total as usize).max(works.len());
+    let listed = works.len().min(SERIES_MAX_WORKS);
+    if total > listed {
+        text.push_str(&format!("\n……共 {} 个作品", total));
     }
 
     Ok(text)
@@ -124,12 +184,13 @@ fn strip_html_tags(text: &str) -> String {
 // Build real image URLs directly from the first page URL and total page count
 // https://i.pixiv.net/img-original/img/2024/11/30/00/00/47/124748386_p0.png
 // https://i.pixiv.net/img-original/img/2024/11/30/00/00/47/124748386_p1.png
+// A count of 0 is treated as a single page
 pub fn get_urls_from_count(url: &str, count: u32) -> Vec<String> {
     if !url.contains("_p0") {
         return vec![url.to_string()];
     }
     let mut urls = Vec::new();
-    for i in 0..count {
+    for i in 0..count.max(1) {
         let page_url = url.replace("_p0", &format!("_p{}", i));
         urls.push(page_url);
     }
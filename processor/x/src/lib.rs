@@ -1,8 +1,25 @@
-use common::{LinkProcessor, ProcessorError, ProcessorResult, ProcessorResultType};
+use common::{
+    LinkProcessor, ProcessorError, ProcessorErrorKind, ProcessorResult, ProcessorResultType,
+    settings,
+};
 use regex::Regex;
 use std::sync::OnceLock;
 
 static X_REGEX: OnceLock<Regex> = OnceLock::new();
+static X_UNSUPPORTED_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// 不支持的 X 链接（Spaces、社群、列表），不匹配推文链接，识别后不做处理
+const UNSUPPORTED_PATTERN: &str =
+    r"(?:https?://)?\b(?:x\.com|(?:www\.)?twitter\.com)/i/(spaces|communities|lists)/\w+";
+
+/// 识别文本中不支持的 X 链接，返回链接类型（spaces/communities/lists）
+pub fn unsupported_x_link_kind(text: &str) -> Option<&str> {
+    X_UNSUPPORTED_REGEX
+        .get_or_init(|| Regex::new(UNSUPPORTED_PATTERN).expect("Invalid X regex pattern"))
+        .captures(text)
+        .and_then(|captures| captures.get(1))
+        .map(|kind| kind.as_str())
+}
 
 /// X/Twitter链接处理器
 pub struct XLinkProcessor;
@@ -22,7 +39,11 @@ impl LinkProcessor for XLinkProcessor {
         X_REGEX.get_or_init(|| Regex::new(Self::PATTERN).expect("Invalid X regex pattern"))
     }
 
-    async fn process_captures(&self, captures: &regex::Captures<'_>) -> ProcessorResultType {
+    async fn process_captures(
+        &self,
+        captures: &regex::Captures<'_>,
+        _truncation: bool,
+    ) -> ProcessorResultType {
         if captures.len() >= 3 {
             let username = &captures[1];
             let status_id = &captures[2];
@@ -33,14 +54,82 @@ impl LinkProcessor for XLinkProcessor {
                 status_id
             );
 
-            let processed = format!("https://fxtwitter.com/{}/status/{}", username, status_id);
+            let processed = format_x_link(username, status_id, settings().x_show_username);
             Ok(ProcessorResult::Text(processed))
         } else {
-            Err(ProcessorError::new("无法解析X链接"))
+            Err(ProcessorError::new("无法解析X链接").with_kind(ProcessorErrorKind::Parse))
         }
     }
 
     fn name(&self) -> &'static str {
         "X/Twitter"
     }
+
+    fn link_hints(&self) -> &'static [&'static str] {
+        &["x.com", "twitter.com"]
+    }
+
+    fn example_urls(&self) -> &'static [&'static str] {
+        &[
+            "https://x.com/rustlang/status/1234567890",
+            "https://twitter.com/rustlang/status/1234567890",
+        ]
+    }
+}
+
+/// 生成 fxtwitter 链接，`show_username` 为 true 时在前面附加 `@用户名: `
+fn format_x_link(username: &str, status_id: &str, show_username: bool) -> String {
+    let url = format!("https://fxtwitter.com/{}/status/{}", username, status_id);
+    if show_username {
+        format!("@{}: {}", username, url)
+    } else {
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsupported_links() {
+        let regex = XLinkProcessor.regex();
+        let cases = [
+            ("https://x.com/i/spaces/1YqKDqDXAbbJV", "spaces"),
+            ("https://twitter.com/i/spaces/1YqKDqDXAbbJV?s=20", "spaces"),
+            (
+                "https://x.com/i/communities/1493446837214187523",
+                "communities",
+            ),
+            ("https://x.com/i/lists/1234567890", "lists"),
+        ];
+        for (url, kind) in cases {
+            // 不匹配推文链接
+            assert!(!regex.is_match(url), "should not match: {}", url);
+            assert_eq!(unsupported_x_link_kind(url), Some(kind));
+        }
+
+        // 推文链接不视为不支持
+        let status = "https://x.com/user/status/123";
+        assert!(regex.is_match(status));
+        assert_eq!(unsupported_x_link_kind(status), None);
+    }
+
+    #[test]
+    fn test_format_x_link() {
+        let processor = XLinkProcessor;
+        let captures = processor
+            .regex()
+            .captures("https://x.com/rustlang/status/1234567890?s=20")
+            .unwrap();
+
+        assert_eq!(
+            format_x_link(&captures[1], &captures[2], false),
+            "https://fxtwitter.com/rustlang/status/1234567890"
+        );
+        assert_eq!(
+            format_x_link(&captures[1], &captures[2], true),
+            "@rustlang: https://fxtwitter.com/rustlang/status/1234567890"
+        );
+    }
 }
@@ -1,4 +1,7 @@
 use anyhow::Result;
+use common::AttemptBudget;
+use common::MediaKind;
+use common::content_type_essence;
 use common::convert_bytes;
 
 /// 按字符边界安全截断字符串
@@ -14,6 +17,7 @@ fn truncate_str(s: &str, max_bytes: usize) -> &str {
     &s[..end]
 }
 use common::extract_filename_from_url;
+use common::get_file_extension_from_content_type;
 use common::guess_content_type_from_url;
 use teloxide::payloads::SendAnimation;
 use teloxide::payloads::SendPhoto;
@@ -21,8 +25,20 @@ use teloxide::prelude::*;
 use teloxide::requests::MultipartRequest;
 use teloxide::types::FileId;
 use teloxide::types::{
-    InputFile, InputMedia, InputMediaPhoto, Message, MessageId, ParseMode, ReplyParameters,
+    InputFile, InputMedia, InputMediaPhoto, InputMediaVideo, LinkPreviewOptions, Message,
+    MessageId, ParseMode, ReplyParameters,
 };
+use teloxide::{ApiError, RequestError};
+
+use crate::download::{self, Downloader, HttpDownloader};
+use crate::file_cache;
+use crate::i18n::{Lang, Text, tf};
+
+/// Telegram 媒体说明文字的长度上限
+const CAPTION_LIMIT: usize = common::MEDIA_CAPTION_LIMIT;
+
+/// Telegram 单个媒体组的媒体数量上限
+const MEDIA_GROUP_LIMIT: usize = 10;
 
 /// 通用的请求配置 trait
 trait ApplyMessageSettings<T> {
@@ -65,6 +81,13 @@ pub struct MessageSenderBuilder {
     urls: Vec<String>,
     spoiler: bool,
     original_urls: Option<Vec<String>>,
+    content_type: Option<String>,
+    source_url: Option<String>,
+    disable_link_preview: bool,
+    budget: AttemptBudget,
+    lang: Lang,
+    /// 说明文字中已经包含媒体统计（拆分发送时按总数附加），发送时不再附加
+    media_stats_added: bool,
 }
 
 impl MessageSenderBuilder {
@@ -79,6 +102,12 @@ impl MessageSenderBuilder {
             urls: Vec::new(),
             spoiler: false,
             original_urls: None,
+            content_type: None,
+            source_url: None,
+            disable_link_preview: false,
+            budget: AttemptBudget::default(),
+            lang: Lang::default(),
+            media_stats_added: false,
         }
     }
 
@@ -89,15 +118,9 @@ impl MessageSenderBuilder {
     }
 
     /// 设置媒体链接 (可选)
+    /// 超过媒体组上限时在发送时拆分为多个媒体组
     pub fn urls(mut self, urls: Vec<String>) -> Self {
-        // 如果图片多于10张，截断到前10张
-        let photo_urls = if urls.len() > 10 {
-            urls.into_iter().take(10).collect()
-        } else {
-            urls
-        };
-
-        self.urls = photo_urls;
+        self.urls = urls;
         self
     }
 
@@ -113,6 +136,30 @@ impl MessageSenderBuilder {
         self
     }
 
+    /// 设置媒体的 content-type 提示，用于判断是否为GIF (可选)
+    pub fn content_type(mut self, content_type: Option<String>) -> Self {
+        self.content_type = content_type;
+        self
+    }
+
+    /// 设置媒体来源的页面链接，开启 APPEND_SOURCE_URL 时附加到说明文字 (可选)
+    pub fn source_url(mut self, source_url: Option<String>) -> Self {
+        self.source_url = source_url;
+        self
+    }
+
+    /// 设置重试次数预算，同一条消息的多个响应应共享同一预算 (可选)
+    pub fn budget(mut self, budget: AttemptBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// 设置机器人附加的提示信息使用的语言 (可选)
+    pub fn lang(mut self, lang: Lang) -> Self {
+        self.lang = lang;
+        self
+    }
+
     pub async fn send_message(self, bot: &Bot) -> Result<Message> {
         send_message(self, bot).await
     }
@@ -133,13 +180,173 @@ async fn send_message(msg: MessageSenderBuilder, bot: &Bot) -> Result<Message> {
         request = request.reply_parameters(ReplyParameters::new(message_id));
     }
 
+    if msg.disable_link_preview {
+        request = request.link_preview_options(disabled_link_preview());
+    }
+
     Ok(request.await?)
 }
 
+/// 禁用链接预览的选项
+fn disabled_link_preview() -> LinkPreviewOptions {
+    LinkPreviewOptions {
+        is_disabled: true,
+        url: None,
+        prefer_small_media: false,
+        prefer_large_media: false,
+        show_above_text: false,
+    }
+}
+
+/// 媒体消息的说明文字是否不生成链接预览（SUPPRESS_CAPTION_PREVIEW）
+///
+/// Telegram 不会为媒体和媒体组的说明文字生成预览，此选项用于没有媒体可发送、
+/// 回退为文本消息发送说明文字的情况
+fn is_suppress_caption_preview_enabled() -> bool {
+    common::settings().suppress_caption_preview
+}
+
+/// 过滤相册中过小的图片（如表情图），阈值由 MIN_IMAGE_BYTES 设置
+/// 使用 HEAD 请求的 Content-Length 判断，全部被过滤时保留原列表
+async fn filter_small_images(mut msg: MessageSenderBuilder) -> MessageSenderBuilder {
+    let Some(min_bytes) = common::get_min_image_bytes() else {
+        return msg;
+    };
+
+    let mut keep = Vec::with_capacity(msg.urls.len());
+    for url in &msg.urls {
+        let referer = is_pixiv_related_url(url).then_some(common::PIXIV_REFERER);
+        let content_length = common::fetch_content_length(url, referer).await;
+        let too_small = common::is_image_too_small(content_length, min_bytes);
+        if too_small {
+            log::info!(
+                "Dropping small image from album: {} ({} < {})",
+                url,
+                convert_bytes(content_length.unwrap_or_default() as f64),
+                convert_bytes(min_bytes as f64)
+            );
+        }
+        keep.push(!too_small);
+    }
+
+    if !keep.contains(&true) {
+        log::warn!("All images are below MIN_IMAGE_BYTES, keeping original album");
+        return msg;
+    }
+
+    msg.urls = retain_by_mask(msg.urls, &keep);
+    // 原始URL与媒体链接按索引对应，需要同步过滤
+    msg.original_urls = msg
+        .original_urls
+        .map(|original_urls| retain_by_mask(original_urls, &keep));
+    msg
+}
+
+/// 按掩码保留对应位置的元素
+fn retain_by_mask<T>(items: Vec<T>, keep: &[bool]) -> Vec<T> {
+    items
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(item, &keep)| keep.then_some(item))
+        .collect()
+}
+
+/// 是否在媒体说明后附加来源链接（APPEND_SOURCE_URL）
+fn is_append_source_url_enabled() -> bool {
+    common::settings().append_source_url
+}
+
+/// 在说明文字末尾附加来源链接，超出长度上限时保持原样
+pub fn append_source_url(caption: &str, source_url: &str) -> String {
+    let source = format!(
+        "\n<a href=\"{}\">source</a>",
+        common::escape_html(source_url).replace('"', "&quot;")
+    );
+    if caption.chars().count() + source.chars().count() > CAPTION_LIMIT {
+        log::debug!(
+            "Caption too long, skip appending source url: {}",
+            source_url
+        );
+        return caption.to_string();
+    }
+    format!("{}{}", caption, source)
+}
+
+/// 选择要发送的媒体URL，没有媒体URL时（如代理转换失败）回退到原始URL
+pub fn select_media_urls(urls: Vec<String>, original_urls: Option<&[String]>) -> Vec<String> {
+    match original_urls {
+        Some(original_urls) if urls.is_empty() && !original_urls.is_empty() => {
+            log::debug!("No media urls, falling back to original urls");
+            original_urls.to_vec()
+        }
+        _ => urls,
+    }
+}
+
+/// 在说明文字末尾附加来源标识，超出长度上限时不附加
+pub fn append_source_footer(caption: &str, footer: &str) -> String {
+    let footer = format!("\n{}", common::escape_html(footer));
+    if caption.chars().count() + footer.chars().count() > CAPTION_LIMIT {
+        log::debug!("Caption too long, skip appending source footer");
+        return caption.to_string();
+    }
+    format!("{}{}", caption, footer)
+}
+
+/// 媒体数量及总大小的说明，如 `(media: 3, 1.2 MB)`，大小未知时只有数量
+/// 相册中可能同时有图片和视频，不区分媒体类型
+pub fn format_media_stats(count: usize, total_bytes: Option<usize>, lang: Lang) -> String {
+    match total_bytes {
+        Some(bytes) => tf(
+            lang,
+            Text::MediaStatsWithSize,
+            &[&count, &convert_bytes(bytes as f64)],
+        ),
+        None => tf(lang, Text::MediaStats, &[&count]),
+    }
+}
+
+/// 在说明文字末尾附加媒体统计（SHOW_MEDIA_STATS），超出长度上限时不附加
+pub fn append_media_stats(
+    caption: &str,
+    count: usize,
+    total_bytes: Option<usize>,
+    lang: Lang,
+) -> String {
+    let stats = format_media_stats(count, total_bytes, lang);
+    let separator = if caption.is_empty() { "" } else { "\n" };
+    if caption.chars().count() + separator.len() + stats.chars().count() > CAPTION_LIMIT {
+        log::debug!("Caption too long, skip appending media stats");
+        return caption.to_string();
+    }
+    format!("{}{}{}", caption, separator, stats)
+}
+
 /// 发送图片
 /// 自动处理单张图片和多张图片的情况
-async fn send_photo(msg: MessageSenderBuilder, bot: &Bot) -> Result<Message> {
+async fn send_photo(mut msg: MessageSenderBuilder, bot: &Bot) -> Result<Message> {
+    msg.urls = select_media_urls(std::mem::take(&mut msg.urls), msg.original_urls.as_deref());
+
+    let mut msg = if msg.urls.len() > 1 {
+        filter_small_images(msg).await
+    } else {
+        msg
+    };
+
+    if is_append_source_url_enabled()
+        && let Some(source_url) = msg.source_url.take()
+    {
+        msg.text = append_source_url(&msg.text, &source_url);
+    }
+
+    if msg.urls.len() > MEDIA_GROUP_LIMIT {
+        // 超过媒体组上限时拆分发送
+        return send_photo_chunks(msg, bot).await;
+    }
+
     if msg.urls.is_empty() {
+        // 没有媒体时说明文字作为文本发送
+        msg.disable_link_preview = is_suppress_caption_preview_enabled();
         send_message(msg, bot).await
     } else if msg.urls.len() == 1 {
         // 如果只有一个链接，使用统一的媒体发送策略
@@ -150,6 +357,120 @@ async fn send_photo(msg: MessageSenderBuilder, bot: &Bot) -> Result<Message> {
     }
 }
 
+/// 按媒体组上限拆分媒体链接
+/// 原始URL与媒体链接一一对应时按相同方式拆分，否则原样保留给第一组
+pub fn chunk_media_urls(
+    urls: Vec<String>,
+    original_urls: Option<Vec<String>>,
+) -> Vec<(Vec<String>, Option<Vec<String>>)> {
+    let aligned = original_urls
+        .as_ref()
+        .is_some_and(|original_urls| original_urls.len() == urls.len());
+    let mut original_urls = original_urls;
+    let mut chunks = Vec::new();
+    let mut urls = urls.into_iter().peekable();
+    while urls.peek().is_some() {
+        let chunk = urls.by_ref().take(MEDIA_GROUP_LIMIT).collect::<Vec<_>>();
+        let chunk_original_urls = match original_urls.as_mut() {
+            Some(original_urls) if aligned => Some(original_urls.drain(..chunk.len()).collect()),
+            _ => original_urls.take(),
+        };
+        chunks.push((chunk, chunk_original_urls));
+    }
+    chunks
+}
+
+/// 将超过媒体组上限的媒体拆分为多组，说明文字只附在其中一组上
+/// 开启媒体统计时按全部媒体的数量附加到带说明文字的一组，其他组不附加
+fn split_photo_chunks(
+    mut msg: MessageSenderBuilder,
+    caption_on_last: bool,
+    show_media_stats: bool,
+) -> Vec<MessageSenderBuilder> {
+    let total = msg.urls.len();
+    let chunks = chunk_media_urls(std::mem::take(&mut msg.urls), msg.original_urls.take());
+    let caption_index = if caption_on_last {
+        chunks.len().saturating_sub(1)
+    } else {
+        0
+    };
+
+    if show_media_stats {
+        msg.text = append_media_stats(&msg.text, total, None, msg.lang);
+    }
+    msg.media_stats_added = true;
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, (urls, original_urls))| {
+            let mut chunk = msg.clone();
+            chunk.urls = urls;
+            chunk.original_urls = original_urls;
+            if index != caption_index {
+                chunk.text = String::new();
+            }
+            chunk
+        })
+        .collect()
+}
+
+/// 媒体超过媒体组上限时拆分为多个媒体组依次发送，说明文字只附在其中一组上
+async fn send_photo_chunks(msg: MessageSenderBuilder, bot: &Bot) -> Result<Message> {
+    let settings = common::settings();
+    let chunks = split_photo_chunks(msg, settings.caption_on_last, settings.show_media_stats);
+    log::debug!("Splitting media into {} groups", chunks.len());
+
+    let mut messages = Vec::new();
+    for chunk in chunks {
+        let message = if chunk.urls.len() == 1 {
+            send_single_media(chunk, bot).await?
+        } else {
+            send_photo_group(chunk, bot).await?
+        };
+        messages.push(message);
+    }
+
+    first_message(messages)
+}
+
+/// 生成已发送媒体的摘要，用于日志记录
+/// 每个媒体为 (URL, 文件大小)，直接通过 URL 发送时大小未知
+pub fn format_media_summary(media: &[(&str, Option<usize>)]) -> String {
+    let hosts = media
+        .iter()
+        .map(|(url, _)| {
+            url::Url::parse(&common::normalize_url(url))
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_else(|| "unknown".to_string())
+        })
+        .collect::<Vec<_>>();
+    let sizes = media
+        .iter()
+        .filter_map(|(_, size)| *size)
+        .collect::<Vec<_>>();
+    let total = if sizes.is_empty() {
+        "unknown".to_string()
+    } else {
+        convert_bytes(sizes.iter().sum::<usize>() as f64)
+    };
+    format!(
+        "count={} hosts=[{}] total={}",
+        media.len(),
+        hosts.join(", "),
+        total
+    )
+}
+
+/// 判断媒体是否为GIF，有 content-type 时以其为准，否则根据URL扩展名判断
+pub fn is_gif_media(url: &str, content_type: Option<&str>) -> bool {
+    match content_type {
+        Some(content_type) => content_type.starts_with("image/gif"),
+        None => url.ends_with(".gif"),
+    }
+}
+
 /// 发送单张媒体文件，根据URL或内容类型智能选择发送方式
 /// 如果直接发送URL失败，则下载文件并上传
 async fn send_single_media(msg: MessageSenderBuilder, bot: &Bot) -> Result<Message> {
@@ -162,11 +483,33 @@ async fn send_single_media(msg: MessageSenderBuilder, bot: &Bot) -> Result<Messa
 
     let url = &msg.urls[0];
 
-    // 根据URL扩展名判断媒体类型
-    let is_gif = url.ends_with(".gif");
+    // 之前发送过的链接直接复用 file_id，失败时移除缓存并按正常流程发送
+    if let Some((file_id, method)) = file_cache::cached_file_id(url) {
+        match send_cached_media(bot, &msg, file_id, method).await {
+            Ok(message) => {
+                log::info!("Sent media from cached file_id: {}", url);
+                return Ok(message);
+            }
+            Err(e) => {
+                log::warn!("Failed to send cached file_id for {}: {}", url, e);
+                file_cache::invalidate_file_id(url);
+            }
+        }
+    }
+
+    // 优先使用处理器提供的类型提示，URL无法判断类型时通过 HEAD 请求获取
+    let content_type = match &msg.content_type {
+        Some(content_type) => Some(content_type.clone()),
+        None if guess_content_type_from_url(url).is_none() => {
+            let referer = is_pixiv_related_url(url).then_some(common::PIXIV_REFERER);
+            common::fetch_content_type(url, referer).await
+        }
+        None => None,
+    };
+    let is_gif = is_gif_media(url, content_type.as_deref());
 
     // 第一次尝试：直接使用URL
-    let input_file = InputFile::url(url.parse().unwrap());
+    let input_file = InputFile::url(common::normalize_url(url).parse()?);
     let direct_result = if is_gif {
         bot.send_animation(msg.chat_id, input_file)
             .apply_settings(&msg)
@@ -178,7 +521,11 @@ async fn send_single_media(msg: MessageSenderBuilder, bot: &Bot) -> Result<Messa
     };
 
     match direct_result {
-        Ok(message) => return Ok(message),
+        Ok(message) => {
+            log::info!("Sent media: {}", format_media_summary(&[(url, None)]));
+            file_cache::remember_file_id(url, &message);
+            return Ok(message);
+        }
         Err(e) => {
             log::warn!("Direct send failed: {}, trying to download and upload", e);
         }
@@ -186,65 +533,193 @@ async fn send_single_media(msg: MessageSenderBuilder, bot: &Bot) -> Result<Messa
 
     // 第二次尝试：下载文件并上传
     // 如果有原始URL且当前URL疑似为Pixiv代理URL，则使用原始URL下载
-    let download_url = if let Some(ref original_urls) = msg.original_urls {
-        if is_pixiv_related_url(url) && !original_urls.is_empty() {
-            &original_urls[0]
-        } else {
-            url
-        }
-    } else {
-        url
-    };
-
-    let data = if is_pixiv_related_url(download_url) {
-        log::debug!("Using Pixiv-specific download for: {}", download_url);
-        common::download_pixiv(download_url).await
-    } else {
-        common::download_file(download_url).await
-    };
-
-    if let Err(e) = data {
-        return Err(anyhow::anyhow!("Failed to download and send media: {}", e));
-    }
-
-    let (file_bytes, content_type) = data.unwrap();
-
-    // 记录下载的文件大小
-    log::info!(
-        "Downloaded single file with size: {} for URL: {}",
-        convert_bytes(file_bytes.len() as f64),
-        download_url
-    );
-
-    // 如果是 application/octet-stream，尝试从URL推断实际的内容类型
-    let actual_content_type = match content_type.as_str() {
-        "application/octet-stream" => guess_content_type_from_url(url).unwrap_or(content_type),
-        _ => content_type,
-    };
-
-    // 如果是图片，验证尺寸
-    if actual_content_type.starts_with("image/")
-        && let Err(e) = common::validate_image_dimensions(&file_bytes)
-    {
-        return Err(anyhow::anyhow!("Image has invalid dimensions: {}", e));
-    }
+    let (file_bytes, actual_content_type, file_name, download_url) =
+        download::download_single_media(
+            &HttpDownloader,
+            url,
+            msg.original_urls.as_deref(),
+            &msg.budget,
+        )
+        .await?;
+
+    // 类型提示为 GIF 时，下载得到的 MP4 作为动画发送（GIF_MP4_AS_ANIMATION）
+    // 动画 WEBP 也作为动画发送
+    let method = SendMethod::sniff(&file_bytes).unwrap_or_else(|| {
+        SendMethod::with_animation_hint(
+            &actual_content_type,
+            is_gif && common::settings().gif_mp4_as_animation,
+        )
+    });
 
     // 使用统一的发送函数
-    send_file_upload(
+    let size = file_bytes.len();
+    let message = send_file_upload_as(
         bot,
         msg.chat_id,
-        msg.message_id.unwrap_or(MessageId(0)),
+        msg.message_id,
         file_bytes,
         &actual_content_type,
         url,
+        file_name.as_deref(),
         &msg.text,
+        Some(method),
+        msg.lang,
     )
     .await
-    .map_err(|e| anyhow::anyhow!("Failed to send media: {}", e))
+    .map_err(|e| anyhow::anyhow!("Failed to send media: {}", e))?;
+    log::info!(
+        "Sent media: {}",
+        format_media_summary(&[(download_url, Some(size))])
+    );
+    file_cache::remember_file_id(url, &message);
+    Ok(message)
+}
+
+/// 使用缓存的 file_id 发送媒体
+async fn send_cached_media(
+    bot: &Bot,
+    msg: &MessageSenderBuilder,
+    file_id: FileId,
+    method: SendMethod,
+) -> ResponseResult<Message> {
+    let input_file = InputFile::file_id(file_id);
+    match method {
+        SendMethod::Photo => {
+            bot.send_photo(msg.chat_id, input_file)
+                .apply_settings(msg)
+                .await
+        }
+        SendMethod::Animation => {
+            bot.send_animation(msg.chat_id, input_file)
+                .apply_settings(msg)
+                .await
+        }
+        method => {
+            send_media_by_method(
+                bot,
+                msg.chat_id,
+                msg.message_id,
+                input_file,
+                method,
+                &msg.text,
+            )
+            .await
+        }
+    }
+}
+
+/// 是否为说明文字过长导致的发送失败
+fn is_caption_too_long_error(err: &RequestError) -> bool {
+    match err {
+        RequestError::Api(ApiError::MessageIsTooLong | ApiError::EditedMessageIsTooLong) => true,
+        // "Bad Request: message caption is too long"
+        // 媒体组中为 "Bad Request: MEDIA_CAPTION_TOO_LONG"
+        RequestError::Api(ApiError::Unknown(message)) => {
+            message.contains("caption is too long") || message.contains("MEDIA_CAPTION_TOO_LONG")
+        }
+        _ => false,
+    }
+}
+
+/// 是否为图片尺寸超出 Telegram 限制导致的发送失败
+fn is_photo_dimensions_error(err: &RequestError) -> bool {
+    match err {
+        // "Bad Request: PHOTO_INVALID_DIMENSIONS"
+        // 媒体组中为 "Bad Request: failed to send message #1 with the error message \"PHOTO_INVALID_DIMENSIONS\""
+        RequestError::Api(ApiError::Unknown(message)) => {
+            message.contains("PHOTO_INVALID_DIMENSIONS")
+        }
+        _ => false,
+    }
+}
+
+/// 媒体组因图片尺寸被拒绝且开启 OVERSIZE_PHOTO_AS_DOCUMENT 时，超出尺寸的图片改为文件发送
+fn should_send_oversize_as_document(enabled: bool, err: &RequestError) -> bool {
+    enabled && is_photo_dimensions_error(err)
+}
+
+/// 是否为超出 Telegram 图片尺寸限制的图片，无法解析的图片不算
+fn is_oversize_photo(file_bytes: &[u8], content_type: &str) -> bool {
+    content_type.starts_with("image/") && common::is_oversize_image(file_bytes)
+}
+
+/// 将 HTML 说明文字拆分为标签、实体和字符，返回每一段及其显示长度（UTF-16）
+/// 标签的显示长度为 0，实体（如 `&amp;`）算作一个字符
+fn caption_tokens(caption: &str) -> impl Iterator<Item = (&str, usize)> {
+    let mut rest = caption;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        let (len, width) = match c {
+            '<' => match rest.find('>') {
+                Some(end) => (end + 1, 0),
+                None => (1, 1),
+            },
+            '&' => match rest.find(';') {
+                Some(end)
+                    if end > 1
+                        && rest[1..end]
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '#') =>
+                {
+                    (end + 1, 1)
+                }
+                _ => (1, 1),
+            },
+            _ => (c.len_utf8(), c.len_utf16()),
+        };
+        let (token, remaining) = rest.split_at(len);
+        rest = remaining;
+        Some((token, width))
+    })
+}
+
+/// 截断说明文字到长度上限，保留 HTML 格式
+/// 长度按显示的文字计算，不会截断在标签或实体中间，截断后补全未闭合的标签
+fn trim_caption(caption: &str) -> String {
+    let visible: usize = caption_tokens(caption).map(|(_, width)| width).sum();
+    if visible <= CAPTION_LIMIT {
+        return caption.to_string();
+    }
+
+    // 留出省略号的位置
+    let budget = CAPTION_LIMIT - 1;
+    let mut trimmed = String::with_capacity(caption.len());
+    let mut open_tags: Vec<&str> = Vec::new();
+    let mut used = 0;
+    for (token, width) in caption_tokens(caption) {
+        if width == 0 {
+            if token.starts_with("</") {
+                open_tags.pop();
+            } else if let Some(name) = token[1..token.len() - 1].split_whitespace().next()
+                && !token.ends_with("/>")
+            {
+                open_tags.push(name);
+            }
+        } else if used + width > budget {
+            break;
+        }
+        used += width;
+        trimmed.push_str(token);
+    }
+
+    trimmed.push('…');
+    for name in open_tags.iter().rev() {
+        trimmed.push_str(&format!("</{}>", name));
+    }
+    trimmed
+}
+
+/// 根据发送错误决定是否截断说明文字后重试，返回截断后的说明文字
+fn trimmed_caption_for_retry(err: &RequestError, caption: &str) -> Option<String> {
+    if !is_caption_too_long_error(err) {
+        return None;
+    }
+    let trimmed = trim_caption(caption);
+    (trimmed != caption).then_some(trimmed)
 }
 
 /// 发送多张图片，如果失败则尝试下载并上传
-async fn send_photo_group(msg: MessageSenderBuilder, bot: &Bot) -> Result<Message> {
+async fn send_photo_group(mut msg: MessageSenderBuilder, bot: &Bot) -> Result<Message> {
     log::debug!(
         "send_media_group: {}\n{}\n{}",
         msg.chat_id,
@@ -252,256 +727,448 @@ async fn send_photo_group(msg: MessageSenderBuilder, bot: &Bot) -> Result<Messag
         msg.urls.join(", ")
     );
 
+    // 直接发送时只知道媒体数量，下载上传时再附加总大小
+    // 拆分发送时统计已按总数附加在说明文字中
+    let show_media_stats = common::settings().show_media_stats && !msg.media_stats_added;
+    let direct_caption = |text: &str| {
+        if show_media_stats {
+            append_media_stats(text, msg.urls.len(), None, msg.lang)
+        } else {
+            text.to_string()
+        }
+    };
+
     // 先尝试直接发送URL媒体组
     let direct_result = send_media_group_direct(
         bot,
         msg.chat_id,
-        msg.message_id.unwrap_or(MessageId(0)),
+        msg.message_id,
         &msg.urls,
-        &msg.text,
+        &direct_caption(&msg.text),
         msg.spoiler,
+        &msg.budget,
     )
     .await;
 
+    // 说明文字过长时截断后重试一次
+    let retry_caption = direct_result
+        .as_ref()
+        .err()
+        .and_then(|e| trimmed_caption_for_retry(e, &msg.text))
+        .filter(|_| msg.budget.try_acquire());
+    let direct_result = match retry_caption {
+        Some(caption) => {
+            log::warn!("Media group caption too long, retrying with trimmed caption");
+            msg.text = caption;
+            send_media_group_direct(
+                bot,
+                msg.chat_id,
+                msg.message_id,
+                &msg.urls,
+                &direct_caption(&msg.text),
+                msg.spoiler,
+                &msg.budget,
+            )
+            .await
+        }
+        None => direct_result,
+    };
+
     match direct_result {
-        Ok(mut messages) => {
-            log::info!(
-                "Successfully sent media group, total {} files",
-                messages.len()
-            );
-            Ok(messages.remove(0))
+        Ok(messages) => {
+            let media = msg
+                .urls
+                .iter()
+                .map(|url| (url.as_str(), None))
+                .collect::<Vec<_>>();
+            log::info!("Sent media group: {}", format_media_summary(&media));
+            first_message(messages)
         }
         Err(e) => {
             log::warn!(
                 "Failed to send media group directly: {}, trying to download and upload",
                 e
             );
+            let oversize_as_document =
+                should_send_oversize_as_document(common::settings().oversize_photo_as_document, &e);
 
             // 逐个下载并发送文件
-            Ok(send_media_group_with_download(
+            let messages = send_media_group_with_download(
                 bot,
                 msg.chat_id,
-                msg.message_id.unwrap_or(MessageId(0)),
+                msg.message_id,
                 msg.urls,
                 msg.original_urls,
                 msg.text,
                 msg.spoiler,
+                oversize_as_document,
+                &msg.budget,
+                &HttpDownloader,
+                show_media_stats,
+                msg.lang,
             )
-            .await
-            .map(|mut messages| messages.remove(0))?)
+            .await?;
+            first_message(messages)
         }
     }
 }
 
+/// 取出发送结果中的第一条消息，Telegram 没有返回消息时返回错误
+fn first_message(messages: Vec<Message>) -> Result<Message> {
+    messages
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Telegram returned no messages for media group"))
+}
+
 /// 用file_id发送GIF
+/// 说明文字为纯文本，为 None 时不带说明文字
 pub async fn send_gif_from_fileid(
     bot: &Bot,
     chat_id: ChatId,
     file_id: FileId,
+    caption: Option<&str>,
 ) -> ResponseResult<Message> {
     log::debug!("send_gif_from_fileid: {}\n\t{}", chat_id, file_id);
-    bot.send_animation(chat_id, InputFile::file_id(file_id))
-        .await
+    let mut request = bot.send_animation(chat_id, InputFile::file_id(file_id));
+    request.caption = caption.map(str::to_string);
+    request.await
+}
+
+/// 上传文件时使用的发送方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendMethod {
+    Animation,
+    Photo,
+    Video,
+    Audio,
+    Document,
+}
+
+impl SendMethod {
+    /// 根据content-type选择发送方式，按媒体类型划分
+    /// Telegram 无法作为图片显示的格式（SVG、AVIF、HEIC 等）作为文件发送
+    pub fn from_content_type(content_type: &str) -> Self {
+        let essence = content_type_essence(content_type);
+        if essence == "image/gif" {
+            return Self::Animation;
+        }
+
+        match MediaKind::from_content_type(&essence) {
+            MediaKind::Image if MediaKind::is_unsupported_image(&essence) => Self::Document,
+            MediaKind::Image => Self::Photo,
+            MediaKind::Video => Self::Video,
+            MediaKind::Audio => Self::Audio,
+            MediaKind::Other => Self::Document,
+        }
+    }
+
+    /// 根据content-type选择发送方式，`animation_hint` 为 true 时 `video/mp4` 作为动画发送
+    /// 用于 GIF 被转换为 MP4 的情况
+    pub fn with_animation_hint(content_type: &str, animation_hint: bool) -> Self {
+        if animation_hint && content_type_essence(content_type) == "video/mp4" {
+            return Self::Animation;
+        }
+        Self::from_content_type(content_type)
+    }
+
+    /// 根据文件内容判断需要特殊处理的发送方式，动画 WEBP 作为动画发送
+    /// 无法从内容判断时返回 None
+    pub fn sniff(file_bytes: &[u8]) -> Option<Self> {
+        common::is_animated_webp(file_bytes).then_some(Self::Animation)
+    }
+
+    /// 根据名称解析发送方式（photo/video/document），用于命令参数
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "photo" => Some(Self::Photo),
+            "video" => Some(Self::Video),
+            "document" => Some(Self::Document),
+            _ => None,
+        }
+    }
+
+    /// 决定最终的发送方式，指定了强制方式时忽略content-type
+    pub fn resolve(content_type: &str, forced: Option<SendMethod>) -> Self {
+        forced.unwrap_or_else(|| Self::from_content_type(content_type))
+    }
 }
 
-/// 根据文件类型和内容上传文件到Telegram
-async fn send_media_by_content_type(
+/// 根据发送方式上传文件到Telegram
+async fn send_media_by_method(
     bot: &Bot,
     chat_id: ChatId,
-    message_id: MessageId,
-    file_bytes: Vec<u8>,
-    content_type: &str,
-    original_url: &str,
+    message_id: Option<MessageId>,
+    input_file: InputFile,
+    method: SendMethod,
     caption: &str,
 ) -> ResponseResult<Message> {
-    log::debug!(
-        "send_media_by_content_type: {}\n\tContent-Type: {}\n\tURL: {}",
-        chat_id,
-        content_type,
-        original_url
-    );
+    log::debug!("send_media_by_method: {}\n\tMethod: {:?}", chat_id, method);
 
-    // 根据URL提取文件名，如果无法提取则使用默认名称
-    let file_name = extract_filename_from_url(original_url, content_type);
-    let input_file = InputFile::memory(file_bytes).file_name(file_name.clone());
-    let reply_params = ReplyParameters::new(message_id);
+    // 没有消息ID时不引用原消息
+    let reply_params = message_id.map(ReplyParameters::new);
 
-    match content_type {
-        // 图片类型
-        "image/gif" => {
-            bot.send_animation(chat_id, input_file)
-                .reply_parameters(reply_params)
+    match method {
+        SendMethod::Animation => {
+            let mut request = bot
+                .send_animation(chat_id, input_file)
                 .parse_mode(ParseMode::Html)
-                .caption(caption)
-                .await
+                .caption(caption);
+            request.reply_parameters = reply_params;
+            request.await
         }
-        ct if ct.starts_with("image/") => {
-            bot.send_photo(chat_id, input_file)
-                .reply_parameters(reply_params)
+        SendMethod::Photo => {
+            let mut request = bot
+                .send_photo(chat_id, input_file)
                 .parse_mode(ParseMode::Html)
-                .caption(caption)
-                .await
+                .caption(caption);
+            request.reply_parameters = reply_params;
+            request.await
         }
-        // 视频类型
-        ct if ct.starts_with("video/") => {
-            bot.send_video(chat_id, input_file)
-                .reply_parameters(reply_params)
+        SendMethod::Video => {
+            let mut request = bot
+                .send_video(chat_id, input_file)
                 .parse_mode(ParseMode::Html)
-                .caption(caption)
-                .await
+                .caption(caption);
+            request.reply_parameters = reply_params;
+            request.await
         }
-        // 音频类型
-        ct if ct.starts_with("audio/") => {
-            bot.send_audio(chat_id, input_file)
-                .reply_parameters(reply_params)
+        SendMethod::Audio => {
+            let mut request = bot
+                .send_audio(chat_id, input_file)
                 .parse_mode(ParseMode::Html)
-                .caption(caption)
-                .await
+                .caption(caption);
+            request.reply_parameters = reply_params;
+            request.await
         }
         // 其他文件类型作为文档发送
-        _ => {
-            bot.send_document(chat_id, input_file)
-                .reply_parameters(reply_params)
+        SendMethod::Document => {
+            let mut request = bot
+                .send_document(chat_id, input_file)
                 .parse_mode(ParseMode::Html)
-                .caption(caption)
-                .await
+                .caption(caption);
+            request.reply_parameters = reply_params;
+            request.await
         }
     }
 }
 
-/// 根据文件类型和内容上传文件到Telegram（公共接口）
-pub async fn send_file_upload(
+/// 上传文件到Telegram，可强制指定发送方式（为 None 时根据content-type判断）
+/// `file_name` 为服务器提供的文件名（Content-Disposition），没有时从URL提取
+#[allow(clippy::too_many_arguments)]
+pub async fn send_file_upload_as(
     bot: &Bot,
     chat_id: ChatId,
-    message_id: MessageId,
+    message_id: Option<MessageId>,
     file_bytes: Vec<u8>,
     content_type: &str,
     original_url: &str,
+    file_name: Option<&str>,
     caption: &str,
+    forced: Option<SendMethod>,
+    lang: Lang,
 ) -> ResponseResult<Message> {
     let size = file_bytes.len();
-    let file_name = extract_filename_from_url(original_url, content_type);
+    // 根据URL提取文件名，如果无法提取则使用默认名称
+    let file_name = file_name
+        .map(str::to_string)
+        .unwrap_or_else(|| extract_filename_from_url(original_url, content_type));
+    let method = SendMethod::resolve(
+        content_type,
+        forced.or_else(|| SendMethod::sniff(&file_bytes)),
+    );
+    let caption = match unsupported_image_note(content_type, method, lang) {
+        Some(note) => format!("{}{}", caption, note),
+        None => caption.to_string(),
+    };
 
     log::info!(
-        "Downloading and sending file {} with size: {}",
+        "Downloading and sending file {} with size: {} as {:?}",
         file_name,
-        convert_bytes(size as f64)
+        convert_bytes(size as f64),
+        method
     );
-
-    send_media_by_content_type(
-        bot,
+    log::debug!(
+        "send_file_upload: {}\n\tContent-Type: {}\n\tURL: {}",
         chat_id,
-        message_id,
-        file_bytes,
         content_type,
-        original_url,
-        caption,
-    )
-    .await
+        original_url
+    );
+
+    let input_file = InputFile::memory(file_bytes).file_name(file_name);
+    send_media_by_method(bot, chat_id, message_id, input_file, method, &caption).await
+}
+
+/// 无法作为图片显示的图片以文件发送时，在说明文字后附加的提示
+pub fn unsupported_image_note(
+    content_type: &str,
+    method: SendMethod,
+    lang: Lang,
+) -> Option<String> {
+    let essence = content_type_essence(content_type);
+    if method != SendMethod::Document || !MediaKind::is_unsupported_image(&essence) {
+        return None;
+    }
+    let format = get_file_extension_from_content_type(&essence)
+        .unwrap_or_else(|| essence.trim_start_matches("image/").to_string());
+    let note = tf(lang, Text::UnsupportedImageNote, &[&format.to_uppercase()]);
+    Some(format!("\n<i>{}</i>", note))
+}
+
+/// 构建媒体组中的单个媒体，图片和视频都会设置剧透标记
+fn build_group_media(input_file: InputFile, is_video: bool, spoiler: bool) -> InputMedia {
+    if is_video {
+        let mut video = InputMediaVideo::new(input_file);
+        video.has_spoiler = spoiler;
+        InputMedia::Video(video)
+    } else {
+        let mut photo = InputMediaPhoto::new(input_file);
+        photo.has_spoiler = spoiler;
+        InputMedia::Photo(photo)
+    }
+}
+
+/// 为媒体组设置说明文字，`on_last` 为 true 时放在最后一个媒体上，否则放在第一个
+fn set_group_caption(media_group: &mut [InputMedia], caption: String, on_last: bool) {
+    let media = if on_last {
+        media_group.last_mut()
+    } else {
+        media_group.first_mut()
+    };
+    match media {
+        Some(InputMedia::Photo(photo)) => {
+            photo.caption = Some(caption);
+            photo.parse_mode = Some(ParseMode::Html);
+        }
+        Some(InputMedia::Video(video)) => {
+            video.caption = Some(caption);
+            video.parse_mode = Some(ParseMode::Html);
+        }
+        _ => {}
+    }
+}
+
+/// 媒体组遇到限流时的最大重试次数
+const MEDIA_GROUP_MAX_RETRIES: u32 = 3;
+/// 限流要求等待的时间超过此上限时不再重试
+const MEDIA_GROUP_MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// 第 `attempt` 次发送失败后的重试等待时间，只重试限流错误，不应重试时返回 None
+fn media_group_retry_delay(err: &RequestError, attempt: u32) -> Option<Duration> {
+    if attempt >= MEDIA_GROUP_MAX_RETRIES {
+        return None;
+    }
+    match err {
+        RequestError::RetryAfter(seconds) => {
+            Some(seconds.duration()).filter(|delay| *delay <= MEDIA_GROUP_MAX_RETRY_DELAY)
+        }
+        _ => None,
+    }
+}
+
+/// 在 [`media_group_retry_delay`] 的基础上消耗一次重试预算，预算用尽时不再重试
+fn budgeted_retry_delay(
+    err: &RequestError,
+    attempt: u32,
+    budget: &AttemptBudget,
+) -> Option<Duration> {
+    let delay = media_group_retry_delay(err, attempt)?;
+    if !budget.try_acquire() {
+        log::warn!("Retry budget exhausted, giving up on media group send");
+        return None;
+    }
+    Some(delay)
+}
+
+/// 发送媒体组，遇到限流时按 Telegram 指示的时间等待后重试
+async fn send_media_group_with_retry(
+    bot: &Bot,
+    chat_id: ChatId,
+    message_id: Option<MessageId>,
+    media_group: Vec<InputMedia>,
+    budget: &AttemptBudget,
+) -> ResponseResult<Vec<Message>> {
+    let mut attempt = 0;
+    loop {
+        let mut request = bot.send_media_group(chat_id, media_group.clone());
+        // 没有消息ID时不引用原消息
+        request.reply_parameters = message_id.map(ReplyParameters::new);
+        let result = request.await;
+        match result {
+            Err(err) => match budgeted_retry_delay(&err, attempt, budget) {
+                Some(delay) => {
+                    attempt += 1;
+                    log::warn!(
+                        "Media group send rate limited, retrying in {:?} ({}/{}, {} retries left)",
+                        delay,
+                        attempt,
+                        MEDIA_GROUP_MAX_RETRIES,
+                        budget.remaining()
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                None => return Err(err),
+            },
+            ok => return ok,
+        }
+    }
 }
 
 /// 直接发送URL媒体组
 async fn send_media_group_direct(
     bot: &Bot,
     chat_id: ChatId,
-    message_id: MessageId,
+    message_id: Option<MessageId>,
     media_urls: &[String],
     caption: &str,
     spoiler: bool,
+    budget: &AttemptBudget,
 ) -> ResponseResult<Vec<Message>> {
     let mut media_group = media_urls
         .iter()
-        .map(|url| {
-            let mut photo = InputMediaPhoto::new(InputFile::url(url.parse().unwrap()));
-            photo.has_spoiler = spoiler;
-            InputMedia::Photo(photo)
+        .filter_map(|url| match common::normalize_url(url).parse() {
+            Ok(url) => Some(build_group_media(InputFile::url(url), false, spoiler)),
+            Err(e) => {
+                log::warn!("Skipping invalid media url {}: {}", url, e);
+                None
+            }
         })
         .collect::<Vec<_>>();
+    set_group_caption(
+        &mut media_group,
+        caption.to_string(),
+        common::settings().caption_on_last,
+    );
 
-    if let Some(InputMedia::Photo(media)) = media_group.first_mut() {
-        media.caption = Some(caption.to_string());
-        media.parse_mode = Some(ParseMode::Html);
-    }
-
-    bot.send_media_group(chat_id, media_group)
-        .reply_parameters(ReplyParameters::new(message_id))
-        .await
+    send_media_group_with_retry(bot, chat_id, message_id, media_group, budget).await
 }
 
 /// 通过下载上传的方式发送媒体组
+/// `oversize_as_document` 为 true 时超出尺寸限制的图片不再跳过，而是在媒体组之后作为文件发送
+#[allow(clippy::too_many_arguments)]
 async fn send_media_group_with_download(
     bot: &Bot,
     chat_id: ChatId,
-    message_id: MessageId,
+    message_id: Option<MessageId>,
     media_urls: Vec<String>,
     original_urls: Option<Vec<String>>,
     caption: String,
     spoiler: bool,
+    oversize_as_document: bool,
+    budget: &AttemptBudget,
+    downloader: &dyn Downloader,
+    show_media_stats: bool,
+    lang: Lang,
 ) -> ResponseResult<Vec<Message>> {
-    let mut downloaded_files = Vec::new();
-
     // 先下载所有文件
-    for (index, url) in media_urls.iter().enumerate() {
-        log::debug!(
-            "Downloading {}/{} file: {}",
-            index + 1,
-            media_urls.len(),
-            url
-        );
-
-        // 确定要下载的URL
-        let download_url = if let Some(ref orig_urls) = original_urls {
-            if is_pixiv_related_url(url) && index < orig_urls.len() {
-                &orig_urls[index]
-            } else {
-                url
-            }
-        } else {
-            url
-        };
-
-        let download_result = if is_pixiv_related_url(download_url) {
-            log::debug!("Using Pixiv-specific download for: {}", download_url);
-            common::download_pixiv(download_url).await
-        } else {
-            common::download_file(download_url).await
-        };
-
-        match download_result {
-            Ok((file_bytes, content_type)) => {
-                log::debug!(
-                    "Successfully downloaded file {}: {} bytes, content-type: {}",
-                    index + 1,
-                    file_bytes.len(),
-                    content_type
-                );
-
-                // 如果是图片，验证尺寸
-                if content_type.starts_with("image/") {
-                    match common::validate_image_dimensions(&file_bytes) {
-                        Ok(_) => {
-                            log::debug!("Image dimensions valid for: {}", url);
-                        }
-                        Err(e) => {
-                            log::warn!("Skipping image with invalid dimensions: {} - {}", url, e);
-                            continue; // 跳过这个图片
-                        }
-                    }
-                }
-
-                // 提取文件名
-                let file_name = extract_filename_from_url(url, &content_type);
-                downloaded_files.push((file_bytes, content_type, file_name, url.clone()));
-            }
-            Err(_e) => {
-                // 存在失败不直接结束，跳过
-                log::warn!("Failed to download media file: {url}");
-                // return Err(RequestError::Api(ApiError::Unknown(
-                //     "Download media group failed".to_string(),
-                // )));
-            }
-        }
-    }
+    let downloaded_files = download::download_media_files(
+        downloader,
+        &media_urls,
+        original_urls.as_deref(),
+        oversize_as_document,
+        budget,
+    )
+    .await;
 
     // 计算总文件大小并记录日志
     let total_size: usize = downloaded_files
@@ -514,49 +1181,146 @@ async fn send_media_group_with_download(
         convert_bytes(total_size as f64)
     );
 
-    let caption = if downloaded_files.len() != media_urls.len() {
-        // 如果下载的文件数量和URL数量不一致，添加警告信息到caption
-        log::warn!(
-            "Not all media files were downloaded successfully: {}/{}",
-            downloaded_files.len(),
-            media_urls.len()
-        );
-        caption
-            + format!(
-                "\n[{}/{} Media Downloaded]",
-                downloaded_files.len(),
-                media_urls.len()
-            )
-            .as_str()
+    // 如果下载的文件数量和URL数量不一致，添加警告信息到caption
+    let caption =
+        download::partial_download_caption(caption, downloaded_files.len(), media_urls.len());
+    let caption = if show_media_stats {
+        append_media_stats(&caption, downloaded_files.len(), Some(total_size), lang)
     } else {
         caption
     };
 
-    // 构建媒体组
-    let mut media_group = Vec::new();
-    for (file_bytes, _content_type, file_name, _url) in downloaded_files {
-        let input_file = InputFile::memory(file_bytes).file_name(file_name);
+    // 发送成功后记录的摘要
+    let summary = format_media_summary(
+        &downloaded_files
+            .iter()
+            .map(|(bytes, _, _, url)| (url.as_str(), Some(bytes.len())))
+            .collect::<Vec<_>>(),
+    );
 
-        let mut photo = InputMediaPhoto::new(input_file);
-        photo.has_spoiler = spoiler;
+    // 超出尺寸限制的图片无法放入媒体组，之后单独作为文件发送
+    let (downloaded_files, oversize_files): (Vec<_>, Vec<_>) = downloaded_files
+        .into_iter()
+        .partition(|(bytes, content_type, ..)| !is_oversize_photo(bytes, content_type));
+    if !oversize_files.is_empty() {
+        log::info!(
+            "Sending {} oversized images as documents",
+            oversize_files.len()
+        );
+    }
 
-        media_group.push(InputMedia::Photo(photo));
+    // 总大小超过 MAX_ALBUM_BYTES 时拆分为多个媒体组发送
+    let mut chunks = match common::settings().max_album_bytes {
+        Some(max_bytes) => chunk_by_bytes(downloaded_files, |(bytes, ..)| bytes.len(), max_bytes),
+        None => vec![downloaded_files],
+    };
+    if !oversize_files.is_empty() {
+        chunks.retain(|chunk| !chunk.is_empty());
+    }
+    if chunks.len() > 1 {
+        log::info!("Splitting media group into {} parts by size", chunks.len());
     }
 
-    // 为第一个媒体添加caption
-    let media_count = media_group.len();
-    if let Some(first_media) = media_group.first_mut()
-        && let InputMedia::Photo(photo) = first_media
-    {
-        photo.caption = Some(caption);
-        photo.parse_mode = Some(ParseMode::Html);
+    // 说明文字放在第一个或最后一个媒体组上（CAPTION_ON_LAST）
+    let caption_on_last = common::settings().caption_on_last;
+    let caption_index = if caption_on_last {
+        chunks.len().saturating_sub(1)
+    } else {
+        0
+    };
+
+    let mut messages = Vec::new();
+    let mut caption = Some(caption);
+    for (index, mut chunk) in chunks.into_iter().enumerate() {
+        let chunk_caption = if index == caption_index {
+            caption.take()
+        } else {
+            None
+        };
+
+        // 媒体组至少需要两个媒体，拆分后只剩一个时单独上传
+        if chunk.len() == 1 {
+            let (file_bytes, content_type, file_name, url) = chunk.remove(0);
+            let caption = chunk_caption.unwrap_or_default();
+            messages.push(
+                send_file_upload_as(
+                    bot,
+                    chat_id,
+                    message_id,
+                    file_bytes,
+                    &content_type,
+                    &url,
+                    Some(&file_name),
+                    &caption,
+                    None,
+                    lang,
+                )
+                .await?,
+            );
+            continue;
+        }
+
+        // 构建媒体组
+        let mut media_group = Vec::new();
+        for (file_bytes, content_type, file_name, _url) in chunk {
+            let input_file = InputFile::memory(file_bytes).file_name(file_name);
+            let is_video = content_type.starts_with("video/");
+            media_group.push(build_group_media(input_file, is_video, spoiler));
+        }
+
+        if let Some(caption) = chunk_caption {
+            set_group_caption(&mut media_group, caption, caption_on_last);
+        }
+
+        // 发送媒体组
+        log::info!("Sending media group with {} files", media_group.len());
+        messages.extend(
+            send_media_group_with_retry(bot, chat_id, message_id, media_group, budget).await?,
+        );
     }
 
-    // 发送媒体组
-    log::info!("Sending media group with {} files", media_count);
-    bot.send_media_group(chat_id, media_group)
-        .reply_parameters(ReplyParameters::new(message_id))
-        .await
+    // 没有剩余媒体组时说明文字放在第一个文件上
+    for (file_bytes, content_type, file_name, url) in oversize_files {
+        let caption = caption.take().unwrap_or_default();
+        messages.push(
+            send_file_upload_as(
+                bot,
+                chat_id,
+                message_id,
+                file_bytes,
+                &content_type,
+                &url,
+                Some(&file_name),
+                &caption,
+                Some(SendMethod::Document),
+                lang,
+            )
+            .await?,
+        );
+    }
+    log::info!("Sent media group: {}", summary);
+    Ok(messages)
+}
+
+/// 按累计大小拆分文件列表，每组总大小不超过 `max_bytes`
+/// 单个文件超过上限时单独成组
+fn chunk_by_bytes<T>(items: Vec<T>, size: impl Fn(&T) -> usize, max_bytes: usize) -> Vec<Vec<T>> {
+    let mut chunks: Vec<Vec<T>> = Vec::new();
+    let mut current = Vec::new();
+    let mut current_bytes = 0;
+    for item in items {
+        let item_bytes = size(&item);
+        if !current.is_empty() && current_bytes + item_bytes > max_bytes {
+            chunks.push(std::mem::take(&mut current));
+            current_bytes = 0;
+        }
+        current_bytes += item_bytes;
+        current.push(item);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
 }
 
 // 简单的发送文本回复
@@ -574,19 +1338,23 @@ pub async fn send_reply_text(
 }
 
 /// 判断URL是否为Pixiv相关URL（包括代理URL和原始URL）
-fn is_pixiv_related_url(url: &str) -> bool {
+pub fn is_pixiv_related_url(url: &str) -> bool {
+    is_pixiv_related_url_with(url, common::settings().pixiv_image_proxy.as_deref())
+}
+
+/// 判断URL是否为Pixiv相关URL，`image_proxy` 为配置的图片代理（PIXIV_IMAGE_PROXY）
+/// 只比较主机名，域名出现在路径中不算
+fn is_pixiv_related_url_with(url: &str, image_proxy: Option<&str>) -> bool {
     const PIXIV_DOMAINS: &[&str] = &[
         "pixiv.net",
         "pximg.net",
         processor_pixiv::constants::REVERSE_PROXY_URL,
     ];
 
-    let from_env = common::get_env_var("PIXIV_IMAGE_PROXY");
-    let env_domain_check = from_env
-        .as_ref()
-        .is_some_and(|domain| !domain.is_empty() && url.contains(domain));
-
-    PIXIV_DOMAINS.iter().any(|domain| url.contains(domain)) || env_domain_check
+    PIXIV_DOMAINS
+        .iter()
+        .chain(image_proxy.as_ref())
+        .any(|domain| common::host_matches(url, domain))
 }
 
 // ==================== frankenstein: sendRichMessage 支持 (Bot API 10.1+) ====================
@@ -595,16 +1363,33 @@ use frankenstein::AsyncTelegramApi;
 use frankenstein::types::ChatId as FrankChatId;
 use frankenstein::types::ReplyParameters as FrankReplyParameters;
 use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 /// 全局 frankenstein Bot 实例（懒初始化）
 static FRANKENSTEIN_BOT: OnceLock<frankenstein::client_reqwest::Bot> = OnceLock::new();
 
+/// 代理不可用时改为直连 Telegram
+static DIRECT_CONNECTION: AtomicBool = AtomicBool::new(false);
+
+/// 之后创建的 Telegram 客户端不使用代理
+pub fn use_direct_connection() {
+    DIRECT_CONNECTION.store(true, Ordering::Relaxed);
+}
+
 /// 初始化 frankenstein Bot，复用 common 的代理配置
 fn get_frankenstein_bot() -> &'static frankenstein::client_reqwest::Bot {
     FRANKENSTEIN_BOT.get_or_init(|| {
         dotenv::dotenv().ok();
-        let token = std::env::var("TELEGRAM_TOKEN").expect("TELEGRAM_TOKEN must be set");
-        let client = common::build_reqwest_client();
+        let token = common::settings()
+            .telegram_token
+            .clone()
+            .expect("TELEGRAM_TOKEN must be set");
+        let client = if DIRECT_CONNECTION.load(Ordering::Relaxed) {
+            common::build_reqwest_client_with_proxy(None)
+        } else {
+            common::build_reqwest_client()
+        };
         let api_url = format!("{}{}", frankenstein::BASE_API_URL, token);
         frankenstein::client_reqwest::Bot::builder()
             .api_url(api_url)
@@ -875,4 +1660,513 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_retain_by_mask() {
+        let urls = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let keep = [true, false, true];
+        assert_eq!(retain_by_mask(urls, &keep), vec!["a", "c"]);
+
+        // 原始URL较少时只保留对应位置
+        let original_urls = vec!["a0".to_string(), "b0".to_string()];
+        assert_eq!(retain_by_mask(original_urls, &keep), vec!["a0"]);
+    }
+
+    #[test]
+    fn test_is_gif_media() {
+        // 根据扩展名判断
+        assert!(is_gif_media("https://example.com/a.gif", None));
+        assert!(!is_gif_media("https://example.com/a.jpg", None));
+
+        // 没有扩展名时使用 content-type 提示
+        assert!(!is_gif_media("https://proxy.example.com/media/123", None));
+        assert!(is_gif_media(
+            "https://proxy.example.com/media/123",
+            Some("image/gif")
+        ));
+        assert!(is_gif_media(
+            "https://proxy.example.com/media/123",
+            Some("image/gif; charset=binary")
+        ));
+        assert!(!is_gif_media(
+            "https://proxy.example.com/media/123",
+            Some("image/jpeg")
+        ));
+    }
+
+    #[test]
+    fn test_is_pixiv_related_url_with_proxy() {
+        let proxy = Some("https://i.pixiv.re/");
+
+        assert!(is_pixiv_related_url_with(
+            "https://i.pixiv.re/img-original/1.png",
+            proxy
+        ));
+        assert!(is_pixiv_related_url_with(
+            "https://i.pximg.net/1.png",
+            proxy
+        ));
+        // 未配置代理时代理域名不算
+        assert!(!is_pixiv_related_url_with(
+            "https://i.pixiv.re/img-original/1.png",
+            None
+        ));
+
+        // 域名出现在路径中不算
+        assert!(!is_pixiv_related_url_with(
+            "https://evil.com/pixiv.net/x.jpg",
+            proxy
+        ));
+        assert!(!is_pixiv_related_url_with(
+            "https://evil.com/i.pixiv.re/x.jpg",
+            proxy
+        ));
+    }
+
+    #[test]
+    fn test_chunk_media_urls() {
+        let urls = (0..15)
+            .map(|i| format!("https://proxy.example.com/{}.jpg", i))
+            .collect::<Vec<_>>();
+        let original_urls = (0..15)
+            .map(|i| format!("https://i.pximg.net/{}.jpg", i))
+            .collect::<Vec<_>>();
+
+        let chunks = chunk_media_urls(urls.clone(), Some(original_urls.clone()));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].0, urls[..10]);
+        assert_eq!(chunks[0].1.as_deref(), Some(&original_urls[..10]));
+        assert_eq!(chunks[1].0, urls[10..]);
+        assert_eq!(chunks[1].1.as_deref(), Some(&original_urls[10..]));
+
+        // 原始URL数量不一致时原样保留给第一组
+        let chunks = chunk_media_urls(urls.clone(), Some(original_urls[..1].to_vec()));
+        assert_eq!(chunks[0].1.as_deref(), Some(&original_urls[..1]));
+        assert_eq!(chunks[1].1, None);
+
+        assert_eq!(
+            chunk_media_urls(urls[..3].to_vec(), None),
+            vec![(urls[..3].to_vec(), None)]
+        );
+        assert!(chunk_media_urls(Vec::new(), None).is_empty());
+    }
+
+    #[test]
+    fn test_split_photo_chunks_media_stats() {
+        let urls = (0..15)
+            .map(|i| format!("https://proxy.example.com/{}.jpg", i))
+            .collect::<Vec<_>>();
+        let msg = MessageSenderBuilder::new(ChatId(1), "<b>标题</b>".to_string())
+            .urls(urls)
+            .lang(Lang::Zh);
+
+        // 统计按全部媒体数量只附加在带说明文字的一组
+        let chunks = split_photo_chunks(msg.clone(), false, true);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].text, "<b>标题</b>\n(媒体：15)");
+        assert_eq!(chunks[1].text, "");
+        assert_eq!(
+            chunks.iter().filter(|c| c.text.contains("(媒体：")).count(),
+            1
+        );
+        assert!(chunks.iter().all(|c| c.media_stats_added));
+
+        let chunks = split_photo_chunks(msg.clone(), true, true);
+        assert_eq!(chunks[0].text, "");
+        assert_eq!(chunks[1].text, "<b>标题</b>\n(媒体：15)");
+
+        let chunks = split_photo_chunks(msg, false, false);
+        assert_eq!(chunks[0].text, "<b>标题</b>");
+        assert_eq!(chunks[1].text, "");
+    }
+
+    #[test]
+    fn test_select_media_urls() {
+        let urls = vec!["https://i.pixiv.re/a.jpg".to_string()];
+        let original_urls = vec!["https://i.pximg.net/a.jpg".to_string()];
+
+        // 有媒体URL时优先使用
+        assert_eq!(select_media_urls(urls.clone(), Some(&original_urls)), urls);
+        assert_eq!(select_media_urls(urls.clone(), None), urls);
+
+        // 没有媒体URL时回退到原始URL
+        assert_eq!(
+            select_media_urls(Vec::new(), Some(&original_urls)),
+            original_urls
+        );
+
+        // 都没有时保持为空
+        assert!(select_media_urls(Vec::new(), Some(&[])).is_empty());
+        assert!(select_media_urls(Vec::new(), None).is_empty());
+    }
+
+    #[test]
+    fn test_media_stats() {
+        assert_eq!(format_media_stats(3, None, Lang::En), "(media: 3)");
+        assert_eq!(format_media_stats(1, None, Lang::Zh), "(媒体：1)");
+        assert_eq!(
+            format_media_stats(2, Some(1536), Lang::En),
+            format!("(media: 2, {})", convert_bytes(1536.0))
+        );
+
+        assert_eq!(
+            append_media_stats("<b>标题</b>", 3, None, Lang::Zh),
+            "<b>标题</b>\n(媒体：3)"
+        );
+        // 没有说明文字时不加换行
+        assert_eq!(append_media_stats("", 2, None, Lang::En), "(media: 2)");
+
+        // 超出长度上限时不附加
+        let long_caption = "字".repeat(CAPTION_LIMIT - 5);
+        assert_eq!(
+            append_media_stats(&long_caption, 3, None, Lang::En),
+            long_caption
+        );
+        // 恰好达到上限时仍然附加
+        let caption = "字".repeat(CAPTION_LIMIT - "\n(media: 3)".chars().count());
+        assert_eq!(
+            append_media_stats(&caption, 3, None, Lang::En)
+                .chars()
+                .count(),
+            CAPTION_LIMIT
+        );
+    }
+
+    #[test]
+    fn test_append_source_footer() {
+        assert_eq!(
+            append_source_footer("<b>标题</b>", "🎨 Pixiv"),
+            "<b>标题</b>\n🎨 Pixiv"
+        );
+        // 标识中的 HTML 特殊字符会被转义
+        assert_eq!(append_source_footer("", "A&B"), "\nA&amp;B");
+
+        // 超出长度上限时不附加
+        let long_caption = "字".repeat(CAPTION_LIMIT - 5);
+        assert_eq!(
+            append_source_footer(&long_caption, "🔗 X/Twitter"),
+            long_caption
+        );
+        // 恰好达到上限时仍然附加
+        let caption = "字".repeat(CAPTION_LIMIT - 8);
+        assert_eq!(
+            append_source_footer(&caption, "🎨 Pixiv").chars().count(),
+            CAPTION_LIMIT
+        );
+    }
+
+    #[test]
+    fn test_append_source_url() {
+        let result = append_source_url(
+            "<b>标题</b>",
+            "https://www.pixiv.net/artworks/123?lang=zh&p=2",
+        );
+        assert_eq!(
+            result,
+            "<b>标题</b>\n<a href=\"https://www.pixiv.net/artworks/123?lang=zh&amp;p=2\">source</a>"
+        );
+
+        // 空说明也可以附加
+        assert_eq!(
+            append_source_url("", "https://x.com/user/status/1"),
+            "\n<a href=\"https://x.com/user/status/1\">source</a>"
+        );
+
+        // 超出长度上限时不附加
+        let long_caption = "字".repeat(CAPTION_LIMIT - 10);
+        assert_eq!(
+            append_source_url(&long_caption, "https://www.pixiv.net/artworks/123"),
+            long_caption
+        );
+    }
+
+    #[test]
+    fn test_build_group_media_spoiler() {
+        let file = || InputFile::url("https://example.com/a".parse().unwrap());
+
+        let mut media_group = vec![
+            build_group_media(file(), false, true),
+            build_group_media(file(), true, true),
+        ];
+        match &media_group[..] {
+            [InputMedia::Photo(photo), InputMedia::Video(video)] => {
+                assert!(photo.has_spoiler);
+                assert!(video.has_spoiler);
+            }
+            other => panic!("应该依次为图片和视频，实际: {:?}", other),
+        }
+
+        assert!(matches!(
+            build_group_media(file(), true, false),
+            InputMedia::Video(video) if !video.has_spoiler
+        ));
+
+        // 视频作为第一个媒体时也能设置说明文字
+        media_group.swap(0, 1);
+        set_group_caption(&mut media_group, "caption".to_string(), false);
+        match &media_group[0] {
+            InputMedia::Video(video) => assert_eq!(video.caption.as_deref(), Some("caption")),
+            other => panic!("应该为视频，实际: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_set_group_caption_position() {
+        let file = || InputFile::url("https://example.com/a.jpg".parse().unwrap());
+        let captions = |media_group: &[InputMedia]| {
+            media_group
+                .iter()
+                .map(|media| match media {
+                    InputMedia::Photo(photo) => photo.caption.clone(),
+                    other => panic!("应该为图片，实际: {:?}", other),
+                })
+                .collect::<Vec<_>>()
+        };
+        let new_group = || {
+            (0..3)
+                .map(|_| build_group_media(file(), false, false))
+                .collect::<Vec<_>>()
+        };
+
+        // 默认放在第一个媒体上
+        let mut media_group = new_group();
+        set_group_caption(&mut media_group, "caption".to_string(), false);
+        assert_eq!(
+            captions(&media_group),
+            vec![Some("caption".to_string()), None, None]
+        );
+
+        // CAPTION_ON_LAST 时放在最后一个媒体上
+        let mut media_group = new_group();
+        set_group_caption(&mut media_group, "caption".to_string(), true);
+        assert_eq!(
+            captions(&media_group),
+            vec![None, None, Some("caption".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_caption_link_preview_config() {
+        use teloxide::payloads::{SendMediaGroup, SendMessage};
+
+        // 媒体组请求没有链接预览选项，说明文字中的链接不会单独生成预览
+        let mut media_group = vec![build_group_media(
+            InputFile::url("https://example.com/a.jpg".parse().unwrap()),
+            false,
+            false,
+        )];
+        set_group_caption(
+            &mut media_group,
+            "<a href=\"https://example.com/post\">title</a>".to_string(),
+            false,
+        );
+        let payload = serde_json::to_value(SendMediaGroup::new(ChatId(1), media_group)).unwrap();
+        assert!(payload.get("link_preview_options").is_none());
+        assert!(
+            payload["media"][0].get("link_preview_options").is_none(),
+            "媒体说明文字不应包含链接预览选项: {}",
+            payload
+        );
+
+        // 回退为文本发送时禁用预览
+        let mut payload = SendMessage::new(ChatId(1), "https://example.com/post");
+        payload.link_preview_options = Some(disabled_link_preview());
+        let payload = serde_json::to_value(payload).unwrap();
+        assert_eq!(payload["link_preview_options"]["is_disabled"], true);
+    }
+
+    #[test]
+    fn test_first_message() {
+        // Telegram 没有返回消息时返回错误而不是 panic
+        let err = first_message(Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("no messages"));
+
+        let message = |id: i32| -> Message {
+            serde_json::from_value(serde_json::json!({
+                "message_id": id,
+                "date": 0,
+                "chat": { "id": 1, "type": "private", "first_name": "test" },
+                "text": "media"
+            }))
+            .unwrap()
+        };
+        let first = first_message(vec![message(1), message(2)]).unwrap();
+        assert_eq!(first.id, MessageId(1));
+    }
+
+    #[test]
+    fn test_chunk_by_bytes() {
+        // 未超过上限时不拆分
+        assert_eq!(
+            chunk_by_bytes(vec![10, 20, 30], |s| *s, 100),
+            vec![vec![10, 20, 30]]
+        );
+        // 恰好等于上限时不拆分
+        assert_eq!(
+            chunk_by_bytes(vec![50, 50], |s| *s, 100),
+            vec![vec![50, 50]]
+        );
+        // 超过上限时拆分
+        assert_eq!(
+            chunk_by_bytes(vec![40, 40, 40, 40, 40], |s| *s, 100),
+            vec![vec![40, 40], vec![40, 40], vec![40]]
+        );
+        // 单个文件超过上限时单独成组
+        assert_eq!(
+            chunk_by_bytes(vec![10, 150, 10], |s| *s, 100),
+            vec![vec![10], vec![150], vec![10]]
+        );
+        assert!(chunk_by_bytes(Vec::<usize>::new(), |s| *s, 100).is_empty());
+    }
+
+    #[test]
+    fn test_media_group_retry_delay() {
+        use teloxide::types::Seconds;
+
+        let flood = RequestError::RetryAfter(Seconds::from_seconds(5));
+        assert_eq!(
+            media_group_retry_delay(&flood, 0),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            media_group_retry_delay(&flood, MEDIA_GROUP_MAX_RETRIES - 1),
+            Some(Duration::from_secs(5))
+        );
+        // 超过重试次数后不再重试
+        assert_eq!(
+            media_group_retry_delay(&flood, MEDIA_GROUP_MAX_RETRIES),
+            None
+        );
+
+        // 等待时间过长时不重试
+        let long_flood = RequestError::RetryAfter(Seconds::from_seconds(600));
+        assert_eq!(media_group_retry_delay(&long_flood, 0), None);
+
+        // 其他错误不重试
+        let err = RequestError::Api(ApiError::MessageIsTooLong);
+        assert_eq!(media_group_retry_delay(&err, 0), None);
+    }
+
+    #[test]
+    fn test_budgeted_retry_delay() {
+        use teloxide::types::Seconds;
+
+        let flood = RequestError::RetryAfter(Seconds::from_seconds(5));
+        let budget = AttemptBudget::new(1);
+        assert_eq!(
+            budgeted_retry_delay(&flood, 0, &budget),
+            Some(Duration::from_secs(5))
+        );
+        // 预算用尽后即使未达到重试次数上限也不再重试
+        assert_eq!(budgeted_retry_delay(&flood, 1, &budget), None);
+        // 共享预算的其他发送同样不再重试
+        assert_eq!(budgeted_retry_delay(&flood, 0, &budget.clone()), None);
+
+        // 不重试的错误不消耗预算
+        let budget = AttemptBudget::new(1);
+        let err = RequestError::Api(ApiError::MessageIsTooLong);
+        assert_eq!(budgeted_retry_delay(&err, 0, &budget), None);
+        assert_eq!(budget.remaining(), 1);
+    }
+
+    #[test]
+    fn test_trimmed_caption_for_retry() {
+        let too_long = RequestError::Api(ApiError::Unknown(
+            "Bad Request: message caption is too long".to_string(),
+        ));
+        let long_caption = format!("<b>标题</b>\n{}", "a&amp;b ".repeat(400));
+        let visible_len = |text: &str| caption_tokens(text).map(|(_, width)| width).sum::<usize>();
+
+        let trimmed = trimmed_caption_for_retry(&too_long, &long_caption).expect("应该截断重试");
+        assert_eq!(visible_len(&trimmed), CAPTION_LIMIT);
+        // 保留格式
+        assert!(trimmed.starts_with("<b>标题</b>\n"));
+        assert!(trimmed.ends_with('…'));
+        // 不会截断在实体中间
+        let last_entity = &trimmed[trimmed.rfind('&').unwrap()..];
+        assert!(last_entity.contains(';'));
+
+        // 截断在标签内部时补全未闭合的标签
+        let quoted = format!(
+            "<b>标题</b>\n<blockquote expandable>{}<a href=\"https://example.com/?a=1&amp;b=2\">链接</a></blockquote>",
+            "字".repeat(CAPTION_LIMIT)
+        );
+        let trimmed = trimmed_caption_for_retry(&too_long, &quoted).expect("应该截断重试");
+        assert!(trimmed.starts_with("<b>标题</b>\n<blockquote expandable>字"));
+        assert!(trimmed.ends_with("字…</blockquote>"));
+        assert!(!trimmed.contains("<a "));
+        assert!(visible_len(&trimmed) <= CAPTION_LIMIT);
+
+        let media_group = RequestError::Api(ApiError::Unknown(
+            "Bad Request: MEDIA_CAPTION_TOO_LONG".to_string(),
+        ));
+        assert!(trimmed_caption_for_retry(&media_group, &long_caption).is_some());
+
+        assert!(
+            trimmed_caption_for_retry(
+                &RequestError::Api(ApiError::MessageIsTooLong),
+                &long_caption
+            )
+            .is_some()
+        );
+
+        // 其他错误不重试
+        let other = RequestError::Api(ApiError::Unknown(
+            "Bad Request: wrong file identifier".to_string(),
+        ));
+        assert_eq!(trimmed_caption_for_retry(&other, &long_caption), None);
+        let other = RequestError::Api(ApiError::Unknown(
+            "Bad Request: file name too long".to_string(),
+        ));
+        assert_eq!(trimmed_caption_for_retry(&other, &long_caption), None);
+
+        // 无法再截断时不重试
+        assert_eq!(trimmed_caption_for_retry(&too_long, "short"), None);
+    }
+
+    #[test]
+    fn test_photo_dimensions_error() {
+        let single = RequestError::Api(ApiError::Unknown(
+            "Bad Request: PHOTO_INVALID_DIMENSIONS".to_string(),
+        ));
+        let group = RequestError::Api(ApiError::Unknown(
+            "Bad Request: failed to send message #2 with the error message \"PHOTO_INVALID_DIMENSIONS\""
+                .to_string(),
+        ));
+        let other = RequestError::Api(ApiError::Unknown(
+            "Bad Request: wrong file identifier".to_string(),
+        ));
+
+        assert!(is_photo_dimensions_error(&single));
+        assert!(is_photo_dimensions_error(&group));
+        assert!(!is_photo_dimensions_error(&other));
+        assert!(!is_photo_dimensions_error(&RequestError::Api(
+            ApiError::MessageIsTooLong
+        )));
+
+        // 只有开启选项且为尺寸错误时改为文件发送
+        assert!(should_send_oversize_as_document(true, &group));
+        assert!(!should_send_oversize_as_document(false, &group));
+        assert!(!should_send_oversize_as_document(true, &other));
+    }
+
+    #[test]
+    fn test_is_oversize_photo() {
+        // 1x1 的 PNG 头部
+        let small_png = [
+            0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00,
+            0x00,
+        ];
+        // 100x12000 的长图
+        let mut long_png = small_png;
+        long_png[16..24].copy_from_slice(&[0, 0, 0, 100, 0, 0, 0x2E, 0xE0]);
+
+        assert!(!is_oversize_photo(&small_png, "image/png"));
+        assert!(is_oversize_photo(&long_png, "image/png"));
+        // 无法解析的数据不是超出尺寸的图片
+        assert!(!is_oversize_photo(b"not an image", "image/png"));
+        // 非图片不检查尺寸
+        assert!(!is_oversize_photo(b"video", "video/mp4"));
+    }
 }
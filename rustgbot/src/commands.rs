@@ -1,88 +1,510 @@
-use teloxide::{prelude::*, utils::command::BotCommands};
+use common::{
+    LinkProcessor, LinkResolution, MediaKind, ProcessorRegistry, ProcessorResult, escape_html,
+    guess_content_type_from_url, settings,
+};
+use std::sync::Arc;
+use teloxide::{prelude::*, types::MessageId, utils::command::BotCommands};
 use url::Url;
 
-use crate::bot;
+use crate::bot::{self, SendMethod};
+use crate::i18n::{Lang, LangStore, Text, t, tf};
 use crate::{process_links_full, send_bot_responses};
 
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
 pub enum BotCommand {
-    /// Download a media with given URL.
+    /// Download media from one or more URLs, resolving supported links via processors.
     Download(String),
-    /// Process links in full text without truncation.
+    /// Process links in full text without truncation, or in the replied message.
     Full(String),
+    /// Manage caches, e.g. `/cache clear` (admin only).
+    Cache(String),
+    /// Process links in the replied message.
+    Process,
+    /// Set the bot language for this chat, e.g. `/lang en`.
+    Lang(String),
+    /// Run a single processor against a URL and dump the result (admin only).
+    Test(String),
 }
 
-pub async fn bot_command_handler(bot: Bot, msg: Message, cmd: BotCommand) -> ResponseResult<()> {
-    match cmd {
-        BotCommand::Download(url) => {
-            let url = match Url::parse(&url) {
-                Ok(url) => url,
-                Err(_) => {
-                    bot::send_reply_text(&bot, msg.chat.id, msg.id, "无效的URL格式。".to_string())
-                        .await?;
-                    return Ok(());
+/// 判断用户是否为管理员（ADMIN_USER_IDS，逗号分隔）
+pub fn is_admin(user_id: u64) -> bool {
+    settings().is_admin(user_id)
+}
+
+/// 检查消息发送者是否为管理员，不是则回复拒绝信息
+async fn ensure_admin(bot: &Bot, msg: &Message, lang: Lang) -> ResponseResult<bool> {
+    if msg.from.as_ref().is_some_and(|user| is_admin(user.id.0)) {
+        return Ok(true);
+    }
+
+    log::info!(
+        "Rejected admin command from non-admin user: {:?}",
+        msg.from.as_ref().map(|user| user.id)
+    );
+    bot::send_reply_text(
+        bot,
+        msg.chat.id,
+        msg.id,
+        t(lang, Text::AdminOnly).to_string(),
+    )
+    .await?;
+    Ok(false)
+}
+
+/// 清除所有处理器的缓存，返回有缓存的处理器名称及清除的条目数量
+pub fn clear_caches(processors: &[Arc<dyn LinkProcessor>]) -> Vec<(&'static str, usize)> {
+    processors
+        .iter()
+        .filter_map(|p| p.clear_cache().map(|count| (p.name(), count)))
+        .collect()
+}
+
+/// 格式化缓存清除结果
+fn format_clear_result(cleared: &[(&'static str, usize)], lang: Lang) -> String {
+    if cleared.is_empty() {
+        return t(lang, Text::NoCache).to_string();
+    }
+    let lines = cleared
+        .iter()
+        .map(|(name, count)| tf(lang, Text::CacheEntry, &[name, count]))
+        .collect::<Vec<_>>()
+        .join("\n");
+    tf(lang, Text::CacheCleared, &[&lines])
+}
+
+/// 拆分 /download 命令参数，按空白分隔出多个URL
+pub fn split_download_args(args: &str) -> Vec<&str> {
+    args.split_whitespace().collect()
+}
+
+/// /download 命令参数
+#[derive(Debug, PartialEq)]
+pub struct DownloadArgs<'a> {
+    pub urls: Vec<&'a str>,
+    /// 只接受指定类型的媒体（--type=）
+    pub media_type: Option<MediaKind>,
+    /// 强制指定发送方式（--as=）
+    pub send_as: Option<SendMethod>,
+    /// 发送结果时不引用命令消息（--no-reply）
+    pub no_reply: bool,
+}
+
+impl DownloadArgs<'_> {
+    /// 发送结果时要引用的消息，指定 --no-reply 时为 None
+    pub fn reply_to(&self, message_id: MessageId) -> Option<MessageId> {
+        (!self.no_reply).then_some(message_id)
+    }
+}
+
+/// 解析 /download 命令参数，以 `--` 开头的为选项，其余为URL
+pub fn parse_download_args(args: &str, lang: Lang) -> Result<DownloadArgs<'_>, String> {
+    let usage = t(lang, Text::DownloadUsage);
+    let mut parsed = DownloadArgs {
+        urls: Vec::new(),
+        media_type: None,
+        send_as: None,
+        no_reply: false,
+    };
+
+    for arg in split_download_args(args) {
+        if let Some(value) = arg.strip_prefix("--type=") {
+            let kind = MediaKind::from_name(value)
+                .ok_or_else(|| tf(lang, Text::InvalidMediaType, &[&value, &usage]))?;
+            parsed.media_type = Some(kind);
+        } else if let Some(value) = arg.strip_prefix("--as=") {
+            let method = SendMethod::from_name(value)
+                .ok_or_else(|| tf(lang, Text::InvalidSendMethod, &[&value, &usage]))?;
+            parsed.send_as = Some(method);
+        } else if arg == "--no-reply" {
+            parsed.no_reply = true;
+        } else if arg.starts_with("--") {
+            return Err(tf(lang, Text::UnknownOption, &[&arg, &usage]));
+        } else {
+            parsed.urls.push(arg);
+        }
+    }
+
+    if parsed.urls.is_empty() {
+        return Err(usage.to_string());
+    }
+
+    Ok(parsed)
+}
+
+/// 检查下载文件的content-type是否符合要求的媒体类型
+pub fn check_media_type(
+    expected: Option<MediaKind>,
+    content_type: &str,
+    url: &str,
+    lang: Lang,
+) -> Result<(), String> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+
+    // 如果是 application/octet-stream，尝试从URL推断实际的内容类型
+    let actual_content_type = match content_type {
+        "application/octet-stream" => {
+            guess_content_type_from_url(url).unwrap_or(content_type.to_string())
+        }
+        _ => content_type.to_string(),
+    };
+
+    if MediaKind::from_content_type(&actual_content_type) == expected {
+        Ok(())
+    } else {
+        Err(tf(
+            lang,
+            Text::MediaTypeMismatch,
+            &[&expected.as_str(), &actual_content_type],
+        ))
+    }
+}
+
+/// 下载单个URL并上传到Telegram，失败时返回错误描述
+async fn download_and_send(
+    bot: &Bot,
+    msg: &Message,
+    url: &str,
+    args: &DownloadArgs<'_>,
+    lang: Lang,
+) -> Result<(), String> {
+    let url = Url::parse(&common::normalize_url(url))
+        .map_err(|_| t(lang, Text::InvalidUrl).to_string())?;
+
+    // 下载文件
+    let download_result = if bot::is_pixiv_related_url(url.as_str()) {
+        common::download_pixiv(url.as_str()).await
+    } else {
+        common::download_file(url.as_str()).await
+    };
+    let (file_bytes, content_type, file_name) = download_result.map_err(|e| {
+        log::error!("Failed to download file from {}: {}", url, e);
+        tf(lang, Text::DownloadFailed, &[&e])
+    })?;
+
+    log::info!(
+        "Successfully downloaded file: {} bytes, content-type: {}",
+        file_bytes.len(),
+        content_type
+    );
+
+    check_media_type(args.media_type, &content_type, url.as_str(), lang)?;
+
+    // 上传到Telegram
+    bot::send_file_upload_as(
+        bot,
+        msg.chat.id,
+        args.reply_to(msg.id),
+        file_bytes,
+        &content_type,
+        url.as_str(),
+        file_name.as_deref(),
+        "",
+        args.send_as,
+        lang,
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to upload file to Telegram: {}", e);
+        tf(lang, Text::UploadFailed, &[&e])
+    })?;
+
+    log::info!("Successfully uploaded file to Telegram");
+    Ok(())
+}
+
+/// /download 参数的下载来源
+#[derive(Debug, PartialEq)]
+pub enum DownloadSource {
+    /// 处理器解析出的媒体URL
+    Media(Vec<String>),
+    /// 没有处理器返回媒体，直接下载原始URL
+    Raw,
+}
+
+/// 根据处理器的解析结果选择下载来源
+///
+/// 有媒体结果时使用媒体URL（优先使用原始URL），处理器全部失败时返回错误信息
+pub fn select_download_source(resolutions: Vec<LinkResolution>) -> Result<DownloadSource, String> {
+    let mut media_urls = Vec::new();
+    let mut errors = Vec::new();
+
+    for resolution in resolutions {
+        match resolution.result {
+            Ok(ProcessorResult::Media(media)) => match media.original_urls {
+                Some(original_urls) if original_urls.len() == media.urls.len() => {
+                    media_urls.extend(original_urls)
                 }
+                _ => media_urls.extend(media.urls),
+            },
+            Ok(_) => {}
+            Err(e) => errors.push(format!("{}: {}", resolution.processor, e)),
+        }
+    }
+
+    if !media_urls.is_empty() {
+        Ok(DownloadSource::Media(media_urls))
+    } else if !errors.is_empty() {
+        Err(errors.join("\n"))
+    } else {
+        Ok(DownloadSource::Raw)
+    }
+}
+
+/// 下载单个 /download 参数，先尝试使用处理器解析媒体，没有媒体时直接下载
+async fn download_arg(
+    bot: &Bot,
+    msg: &Message,
+    registry: &ProcessorRegistry,
+    arg: &str,
+    args: &DownloadArgs<'_>,
+    lang: Lang,
+) -> Result<(), String> {
+    let media_urls = match select_download_source(registry.resolve_links(arg, true).await)? {
+        DownloadSource::Media(urls) => urls,
+        DownloadSource::Raw => return download_and_send(bot, msg, arg, args, lang).await,
+    };
+
+    log::info!("Resolved {} media files from {}", media_urls.len(), arg);
+    let mut errors = Vec::new();
+    for url in &media_urls {
+        if let Err(e) = download_and_send(bot, msg, url, args, lang).await {
+            errors.push(format!("{}\n{}", url, e));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// 汇总 /download 的处理结果，全部成功时返回 None
+///
+/// 单个URL时直接返回错误信息，多个URL时列出每个失败的URL
+pub fn summarize_download_results(
+    results: &[(&str, Result<(), String>)],
+    lang: Lang,
+) -> Option<String> {
+    let failures: Vec<_> = results
+        .iter()
+        .filter_map(|(url, result)| result.as_ref().err().map(|e| (url, e)))
+        .collect();
+
+    if failures.is_empty() {
+        return None;
+    }
+
+    if results.len() == 1 {
+        return Some(failures[0].1.clone());
+    }
+
+    let details = failures
+        .iter()
+        .map(|(url, e)| format!("{}\n{}", url, e))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Some(tf(
+        lang,
+        Text::DownloadSummary,
+        &[&failures.len(), &results.len(), &details],
+    ))
+}
+
+/// 选择命令要处理的文本，参数为空时使用被回复消息的文本
+pub fn select_command_text<'a>(arg: &'a str, reply_text: Option<&'a str>) -> Option<&'a str> {
+    if !arg.trim().is_empty() {
+        return Some(arg);
+    }
+    reply_text.filter(|text| !text.trim().is_empty())
+}
+
+/// 获取被回复消息的文本或说明文字
+pub fn reply_target_text(msg: &Message) -> Option<&str> {
+    msg.reply_to_message()
+        .and_then(|reply| reply.text().or(reply.caption()))
+}
+
+/// 解析 /test 命令参数，最后一项为URL，其余为处理器名称（名称可包含空格）
+pub fn parse_test_args(args: &str) -> Option<(&str, &str)> {
+    let (name, url) = args.trim().rsplit_once(char::is_whitespace)?;
+    let name = name.trim();
+    (!name.is_empty()).then_some((name, url))
+}
+
+/// Telegram 消息的长度上限，留出 HTML 标签的余量
+const TEST_DUMP_LIMIT: usize = 3800;
+
+/// 格式化 /test 命令的详细结果
+pub fn format_test_result(resolutions: &[LinkResolution]) -> String {
+    resolutions
+        .iter()
+        .map(|resolution| {
+            let dump = format!("{:#?}", resolution.result);
+            let dump: String = if dump.chars().count() > TEST_DUMP_LIMIT {
+                dump.chars().take(TEST_DUMP_LIMIT).collect::<String>() + "\n..."
+            } else {
+                dump
             };
+            format!(
+                "<b>{}</b>: {}\n<pre>{}</pre>",
+                escape_html(resolution.processor),
+                escape_html(&resolution.matched),
+                escape_html(&dump)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
 
-            // 下载文件
-            match common::download_file(url.as_str()).await {
-                Ok((file_bytes, content_type)) => {
-                    log::info!(
-                        "Successfully downloaded file: {} bytes, content-type: {}",
-                        file_bytes.len(),
-                        content_type
-                    );
-
-                    // 上传到Telegram
-                    match bot::send_file_upload(
-                        &bot,
-                        msg.chat.id,
-                        msg.id,
-                        file_bytes,
-                        &content_type,
-                        url.as_str(),
-                        "",
-                    )
-                    .await
-                    {
-                        Ok(_) => {
-                            log::info!("Successfully uploaded file to Telegram");
-                        }
-                        Err(e) => {
-                            log::error!("Failed to upload file to Telegram: {}", e);
-                            bot::send_reply_text(
-                                &bot,
-                                msg.chat.id,
-                                msg.id,
-                                format!("上传文件到Telegram时出错: {}", e),
-                            )
-                            .await?;
-                        }
-                    }
-                }
+pub async fn bot_command_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: BotCommand,
+    registry: Arc<ProcessorRegistry>,
+    lang_store: Arc<LangStore>,
+) -> ResponseResult<()> {
+    let lang = lang_store.get(msg.chat.id);
+
+    match cmd {
+        BotCommand::Download(args) => {
+            let args = match parse_download_args(&args, lang) {
+                Ok(args) => args,
                 Err(e) => {
-                    log::error!("Failed to download file from {}: {}", url, e);
-                    bot::send_reply_text(&bot, msg.chat.id, msg.id, format!("下载文件失败: {}", e))
-                        .await?;
+                    bot::send_reply_text(&bot, msg.chat.id, msg.id, e).await?;
+                    return Ok(());
                 }
+            };
+
+            // 逐个下载，单个失败不影响其他URL
+            let mut results = Vec::with_capacity(args.urls.len());
+            for &url in &args.urls {
+                let result = download_arg(&bot, &msg, &registry, url, &args, lang).await;
+                results.push((url, result));
+            }
+
+            if let Some(report) = summarize_download_results(&results, lang) {
+                bot::send_reply_text(&bot, msg.chat.id, msg.id, report).await?;
             }
         }
         BotCommand::Full(text) => {
             let chat_id = msg.chat.id;
 
-            if let Some(responses) = process_links_full(&text).await {
-                send_bot_responses(&bot, chat_id, msg.id, responses).await;
+            let reply_text = msg.reply_to_message().and_then(|m| m.text());
+            let Some(text) = select_command_text(&text, reply_text) else {
+                bot::send_reply_text(&bot, chat_id, msg.id, t(lang, Text::FullUsage).to_string())
+                    .await?;
+                return Ok(());
+            };
+
+            if let Some(responses) = process_links_full(&registry, text).await {
+                send_bot_responses(&bot, chat_id, crate::reply_target(&msg), responses, lang).await;
+            } else {
+                bot::send_reply_text(
+                    &bot,
+                    msg.chat.id,
+                    msg.id,
+                    t(lang, Text::NoLinksFound).to_string(),
+                )
+                .await?;
+            }
+        }
+        BotCommand::Cache(action) => {
+            if !ensure_admin(&bot, &msg, lang).await? {
+                return Ok(());
+            }
+
+            let reply = match action.trim() {
+                "clear" => {
+                    let cleared = clear_caches(&registry.processors());
+                    log::info!("Cleared processor caches: {:?}", cleared);
+                    format_clear_result(&cleared, lang)
+                }
+                _ => t(lang, Text::CacheUsage).to_string(),
+            };
+            bot::send_reply_text(&bot, msg.chat.id, msg.id, reply).await?;
+        }
+        BotCommand::Process => {
+            let Some(text) = reply_target_text(&msg) else {
+                bot::send_reply_text(
+                    &bot,
+                    msg.chat.id,
+                    msg.id,
+                    t(lang, Text::ProcessUsage).to_string(),
+                )
+                .await?;
+                return Ok(());
+            };
+
+            // 回复到被处理的消息上
+            let target_id = msg.reply_to_message().map_or(msg.id, |reply| reply.id);
+            if let Some(responses) = process_links_full(&registry, text).await {
+                send_bot_responses(&bot, msg.chat.id, Some(target_id), responses, lang).await;
             } else {
                 bot::send_reply_text(
                     &bot,
                     msg.chat.id,
                     msg.id,
-                    "未在文本中找到支持的链接。".to_string(),
+                    t(lang, Text::NoLinksInReply).to_string(),
                 )
                 .await?;
             }
         }
+        BotCommand::Lang(code) => {
+            let reply = match Lang::from_code(&code) {
+                Some(new_lang) => {
+                    lang_store.set(msg.chat.id, new_lang);
+                    tf(new_lang, Text::LangChanged, &[&new_lang.code()])
+                }
+                None => tf(lang, Text::LangUsage, &[&lang.code()]),
+            };
+            bot::send_reply_text(&bot, msg.chat.id, msg.id, reply).await?;
+        }
+        BotCommand::Test(args) => {
+            if !ensure_admin(&bot, &msg, lang).await? {
+                return Ok(());
+            }
+
+            let Some((name, url)) = parse_test_args(&args) else {
+                bot::send_reply_text(
+                    &bot,
+                    msg.chat.id,
+                    msg.id,
+                    t(lang, Text::TestUsage).to_string(),
+                )
+                .await?;
+                return Ok(());
+            };
+
+            let reply = match registry.find_processor(name) {
+                Some(processor) => {
+                    // 只回复结果，不发送处理后的媒体
+                    let resolutions = registry.resolve_with(processor.as_ref(), url, true).await;
+                    if resolutions.is_empty() {
+                        tf(lang, Text::TestNoMatch, &[&escape_html(processor.name())])
+                    } else {
+                        format_test_result(&resolutions)
+                    }
+                }
+                None => {
+                    let names = registry
+                        .processors()
+                        .iter()
+                        .map(|p| p.name())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    tf(
+                        lang,
+                        Text::UnknownProcessor,
+                        &[&escape_html(name), &escape_html(&names)],
+                    )
+                }
+            };
+            bot::send_reply_text(&bot, msg.chat.id, msg.id, reply).await?;
+        }
     };
 
     Ok(())
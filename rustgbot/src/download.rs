@@ -0,0 +1,161 @@
+//! 发送媒体时的下载
+//!
+//! 直接发送链接失败时会下载文件后上传，下载通过 [`Downloader`] 进行，
+//! 测试中可以替换为不访问网络的实现。下载的重试与发送共享消息的重试预算。
+
+use anyhow::Result;
+use common::{AttemptBudget, extract_filename_from_url, guess_content_type_from_url};
+
+use crate::bot::is_pixiv_related_url;
+
+/// 下载结果：文件内容、content-type 和服务器提供的文件名
+pub type DownloadResult = Result<(Vec<u8>, String, Option<String>)>;
+
+/// 媒体组中下载成功的文件：文件内容、content-type、文件名和发送的链接
+pub type DownloadedFile = (Vec<u8>, String, String, String);
+
+#[async_trait::async_trait]
+pub trait Downloader: Send + Sync {
+    /// 下载文件，重试时消耗 `budget`
+    async fn download(&self, url: &str, budget: &AttemptBudget) -> DownloadResult;
+}
+
+/// 通过 HTTP 下载，Pixiv 相关链接带上 Referer
+pub struct HttpDownloader;
+
+#[async_trait::async_trait]
+impl Downloader for HttpDownloader {
+    async fn download(&self, url: &str, budget: &AttemptBudget) -> DownloadResult {
+        let referer = if is_pixiv_related_url(url) {
+            log::debug!("Using Pixiv-specific download for: {}", url);
+            Some(common::PIXIV_REFERER)
+        } else {
+            None
+        };
+        common::download_file_with_budget(url, referer, budget).await
+    }
+}
+
+/// 第 `index` 个媒体下载时使用的链接
+/// Pixiv 代理链接有对应的原始链接时使用原始链接下载
+pub fn download_url_for<'a>(
+    url: &'a str,
+    index: usize,
+    original_urls: Option<&'a [String]>,
+) -> &'a str {
+    match original_urls.and_then(|urls| urls.get(index)) {
+        Some(original) if is_pixiv_related_url(url) => original,
+        _ => url,
+    }
+}
+
+/// 下载单个媒体，返回文件内容、content-type、文件名和实际下载的链接
+/// content-type 为 application/octet-stream 时从链接推断，图片尺寸无效时返回错误
+pub async fn download_single_media<'a>(
+    downloader: &dyn Downloader,
+    url: &'a str,
+    original_urls: Option<&'a [String]>,
+    budget: &AttemptBudget,
+) -> Result<(Vec<u8>, String, Option<String>, &'a str)> {
+    let download_url = download_url_for(url, 0, original_urls);
+    let (file_bytes, content_type, file_name) = downloader
+        .download(download_url, budget)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to download and send media: {}", e))?;
+
+    // 记录下载的文件大小
+    log::info!(
+        "Downloaded single file with size: {} for URL: {}",
+        common::convert_bytes(file_bytes.len() as f64),
+        download_url
+    );
+
+    // 如果是 application/octet-stream，尝试从URL推断实际的内容类型
+    let content_type = match content_type.as_str() {
+        "application/octet-stream" => guess_content_type_from_url(url).unwrap_or(content_type),
+        _ => content_type,
+    };
+
+    // 如果是图片，验证尺寸
+    if content_type.starts_with("image/")
+        && let Err(e) = common::validate_image_dimensions(&file_bytes)
+    {
+        return Err(anyhow::anyhow!("Image has invalid dimensions: {}", e));
+    }
+
+    Ok((file_bytes, content_type, file_name, download_url))
+}
+
+/// 逐个下载媒体组的文件，下载失败或图片尺寸无效的文件会被跳过
+/// `keep_oversize` 为 true 时保留超出尺寸限制的图片，由调用方作为文件发送，
+/// 无法解析的图片仍会被跳过
+pub async fn download_media_files(
+    downloader: &dyn Downloader,
+    media_urls: &[String],
+    original_urls: Option<&[String]>,
+    keep_oversize: bool,
+    budget: &AttemptBudget,
+) -> Vec<DownloadedFile> {
+    let mut downloaded_files = Vec::new();
+
+    for (index, url) in media_urls.iter().enumerate() {
+        log::debug!(
+            "Downloading {}/{} file: {}",
+            index + 1,
+            media_urls.len(),
+            url
+        );
+
+        let download_url = download_url_for(url, index, original_urls);
+        match downloader.download(download_url, budget).await {
+            Ok((file_bytes, content_type, file_name)) => {
+                log::debug!(
+                    "Successfully downloaded file {}: {} bytes, content-type: {}",
+                    index + 1,
+                    file_bytes.len(),
+                    content_type
+                );
+
+                // 如果是图片，验证尺寸
+                if content_type.starts_with("image/") {
+                    match common::validate_image_dimensions(&file_bytes) {
+                        Ok(_) => {
+                            log::debug!("Image dimensions valid for: {}", url);
+                        }
+                        Err(e) if keep_oversize && common::is_oversize_image(&file_bytes) => {
+                            log::debug!("Keeping oversized image: {} - {}", url, e);
+                        }
+                        Err(e) => {
+                            log::warn!("Skipping image with invalid dimensions: {} - {}", url, e);
+                            continue; // 跳过这个图片
+                        }
+                    }
+                }
+
+                // 优先使用服务器提供的文件名，否则从URL提取
+                let file_name =
+                    file_name.unwrap_or_else(|| extract_filename_from_url(url, &content_type));
+                downloaded_files.push((file_bytes, content_type, file_name, url.clone()));
+            }
+            Err(e) => {
+                // 存在失败不直接结束，跳过
+                log::warn!("Failed to download media file {}: {}", url, e);
+            }
+        }
+    }
+
+    downloaded_files
+}
+
+/// 部分媒体下载失败时在说明文字后附加下载数量
+pub fn partial_download_caption(caption: String, downloaded: usize, total: usize) -> String {
+    if downloaded == total {
+        return caption;
+    }
+    log::warn!(
+        "Not all media files were downloaded successfully: {}/{}",
+        downloaded,
+        total
+    );
+    caption + format!("\n[{}/{} Media Downloaded]", downloaded, total).as_str()
+}
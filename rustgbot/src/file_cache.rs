@@ -0,0 +1,105 @@
+//! 已发送媒体的 file_id 缓存
+//!
+//! Telegram 在媒体发送成功后会返回 file_id，再次发送同一链接时直接复用，
+//! 避免重复下载和上传。缓存有容量上限，超出时淘汰最早写入的条目。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use teloxide::types::{FileId, Message};
+
+use crate::bot::SendMethod;
+
+/// 全局缓存的容量
+const FILE_ID_CACHE_CAPACITY: usize = 256;
+
+static FILE_ID_CACHE: LazyLock<Mutex<FileIdCache>> =
+    LazyLock::new(|| Mutex::new(FileIdCache::new(FILE_ID_CACHE_CAPACITY)));
+
+/// 媒体链接到 file_id 及发送方式的缓存
+#[derive(Debug)]
+pub struct FileIdCache {
+    capacity: usize,
+    entries: HashMap<String, (FileId, SendMethod)>,
+    /// 写入顺序，用于淘汰最早的条目
+    order: VecDeque<String>,
+}
+
+impl FileIdCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Option<(FileId, SendMethod)> {
+        self.entries.get(url).cloned()
+    }
+
+    /// 写入缓存，超出容量时淘汰最早写入的条目
+    pub fn insert(&mut self, url: &str, file_id: FileId, method: SendMethod) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self
+            .entries
+            .insert(url.to_string(), (file_id, method))
+            .is_none()
+        {
+            self.order.push_back(url.to_string());
+        }
+        while self.entries.len() > self.capacity {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    pub fn remove(&mut self, url: &str) -> Option<(FileId, SendMethod)> {
+        let removed = self.entries.remove(url)?;
+        self.order.retain(|key| key != url);
+        Some(removed)
+    }
+}
+
+/// 获取已发送的消息中媒体的 file_id 及对应的发送方式
+/// 图片取最大尺寸的 file_id
+pub fn sent_file_id(message: &Message) -> Option<(FileId, SendMethod)> {
+    if let Some(animation) = message.animation() {
+        return Some((animation.file.id.clone(), SendMethod::Animation));
+    }
+    if let Some(photo) = message.photo().and_then(|sizes| sizes.last()) {
+        return Some((photo.file.id.clone(), SendMethod::Photo));
+    }
+    if let Some(video) = message.video() {
+        return Some((video.file.id.clone(), SendMethod::Video));
+    }
+    if let Some(audio) = message.audio() {
+        return Some((audio.file.id.clone(), SendMethod::Audio));
+    }
+    message
+        .document()
+        .map(|document| (document.file.id.clone(), SendMethod::Document))
+}
+
+/// 查询链接对应的 file_id
+pub fn cached_file_id(url: &str) -> Option<(FileId, SendMethod)> {
+    FILE_ID_CACHE.lock().unwrap().get(url)
+}
+
+/// 记录发送成功的消息中媒体的 file_id
+pub fn remember_file_id(url: &str, message: &Message) {
+    if let Some((file_id, method)) = sent_file_id(message) {
+        log::debug!("Caching file_id for {} as {:?}", url, method);
+        FILE_ID_CACHE.lock().unwrap().insert(url, file_id, method);
+    }
+}
+
+/// 使用 file_id 发送失败时移除缓存
+pub fn invalidate_file_id(url: &str) {
+    if FILE_ID_CACHE.lock().unwrap().remove(url).is_some() {
+        log::debug!("Invalidated cached file_id for {}", url);
+    }
+}
@@ -0,0 +1,194 @@
+//! 机器人消息的多语言支持
+//!
+//! 只用于机器人自身生成的提示信息，处理器返回的内容不做翻译。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use teloxide::types::ChatId;
+
+/// 支持的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Lang {
+    /// 从语言代码解析，如 `zh`、`en`
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_lowercase().as_str() {
+            "zh" | "zh-cn" | "cn" => Some(Self::Zh),
+            "en" | "en-us" => Some(Self::En),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Zh => "zh",
+            Self::En => "en",
+        }
+    }
+}
+
+/// 机器人消息的键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Text {
+    AdminOnly,
+    NoCache,
+    /// 参数：缓存清除明细
+    CacheCleared,
+    /// 参数：处理器名称、条目数量
+    CacheEntry,
+    CacheUsage,
+    FullUsage,
+    ProcessUsage,
+    NoLinksFound,
+    NoLinksInReply,
+    InvalidUrl,
+    /// 参数：错误信息
+    DownloadFailed,
+    /// 参数：错误信息
+    UploadFailed,
+    /// 参数：失败数量、总数量、失败明细
+    DownloadSummary,
+    /// 参数：当前语言代码
+    LangUsage,
+    /// 参数：语言代码
+    LangChanged,
+    TestUsage,
+    /// 参数：处理器名称、可用的处理器列表
+    UnknownProcessor,
+    /// 参数：处理器名称
+    TestNoMatch,
+    DownloadUsage,
+    /// 参数：类型、用法
+    InvalidMediaType,
+    /// 参数：发送方式、用法
+    InvalidSendMethod,
+    /// 参数：选项、用法
+    UnknownOption,
+    /// 参数：要求的类型、实际的 content-type
+    MediaTypeMismatch,
+    /// 参数：图片格式
+    UnsupportedImageNote,
+    /// 参数：媒体数量
+    MediaStats,
+    /// 参数：媒体数量、总大小
+    MediaStatsWithSize,
+}
+
+/// 中文消息，作为所有语言的回退
+fn zh(text: Text) -> &'static str {
+    match text {
+        Text::AdminOnly => "抱歉，此命令仅限管理员使用。",
+        Text::NoCache => "没有可清除的缓存。",
+        Text::CacheCleared => "已清除缓存：\n{}",
+        Text::CacheEntry => "{}: {} 条",
+        Text::CacheUsage => "用法：/cache clear",
+        Text::FullUsage => "用法：/full <文本>，或回复一条消息发送 /full",
+        Text::ProcessUsage => "用法：回复一条包含链接的消息发送 /process",
+        Text::NoLinksFound => "未在文本中找到支持的链接。",
+        Text::NoLinksInReply => "未在被回复的消息中找到支持的链接。",
+        Text::InvalidUrl => "无效的URL格式。",
+        Text::DownloadFailed => "下载文件失败: {}",
+        Text::UploadFailed => "上传文件到Telegram时出错: {}",
+        Text::DownloadSummary => "{}/{} 个文件处理失败：\n\n{}",
+        Text::LangUsage => "当前语言：{}\n用法：/lang zh|en",
+        Text::LangChanged => "语言已切换为：{}",
+        Text::TestUsage => "用法：/test <处理器名称> <url>",
+        Text::UnknownProcessor => "未知的处理器：{}\n可用的处理器：{}",
+        Text::TestNoMatch => "链接与处理器 {} 不匹配。",
+        Text::DownloadUsage => {
+            "用法：/download <url> [<url> ...] [--type=image|video|audio] [--as=photo|video|document] [--no-reply]"
+        }
+        Text::InvalidMediaType => "无效的类型: {}\n{}",
+        Text::InvalidSendMethod => "无效的发送方式: {}\n{}",
+        Text::UnknownOption => "未知选项: {}\n{}",
+        Text::MediaTypeMismatch => "文件类型不匹配：要求 {}，实际为 {}",
+        Text::UnsupportedImageNote => "{} 格式的图片无法直接显示，已作为文件发送",
+        Text::MediaStats => "(媒体：{})",
+        Text::MediaStatsWithSize => "(媒体：{}，{})",
+    }
+}
+
+/// 英文消息
+fn en(text: Text) -> &'static str {
+    match text {
+        Text::AdminOnly => "Sorry, this command is for admins only.",
+        Text::NoCache => "No caches to clear.",
+        Text::CacheCleared => "Caches cleared:\n{}",
+        Text::CacheEntry => "{}: {} entries",
+        Text::CacheUsage => "Usage: /cache clear",
+        Text::FullUsage => "Usage: /full <text>, or reply to a message with /full",
+        Text::ProcessUsage => "Usage: reply to a message containing links with /process",
+        Text::NoLinksFound => "No supported links found in the text.",
+        Text::NoLinksInReply => "No supported links found in the replied message.",
+        Text::InvalidUrl => "Invalid URL format.",
+        Text::DownloadFailed => "Failed to download file: {}",
+        Text::UploadFailed => "Failed to upload file to Telegram: {}",
+        Text::DownloadSummary => "{}/{} files failed:\n\n{}",
+        Text::LangUsage => "Current language: {}\nUsage: /lang zh|en",
+        Text::LangChanged => "Language switched to: {}",
+        Text::TestUsage => "Usage: /test <processor name> <url>",
+        Text::UnknownProcessor => "Unknown processor: {}\nAvailable processors: {}",
+        Text::TestNoMatch => "The link does not match processor {}.",
+        Text::DownloadUsage => {
+            "Usage: /download <url> [<url> ...] [--type=image|video|audio] [--as=photo|video|document] [--no-reply]"
+        }
+        Text::InvalidMediaType => "Invalid type: {}\n{}",
+        Text::InvalidSendMethod => "Invalid send method: {}\n{}",
+        Text::UnknownOption => "Unknown option: {}\n{}",
+        Text::MediaTypeMismatch => "File type mismatch: expected {}, got {}",
+        Text::UnsupportedImageNote => "{} images cannot be displayed directly, sent as a file",
+        Text::MediaStats => "(media: {})",
+        Text::MediaStatsWithSize => "(media: {}, {})",
+    }
+}
+
+/// 获取指定语言的消息
+pub fn t(lang: Lang, text: Text) -> &'static str {
+    match lang {
+        Lang::Zh => zh(text),
+        Lang::En => en(text),
+    }
+}
+
+/// 获取消息并依次替换其中的 `{}` 占位符
+/// 模板只拆分一次，参数中的 `{}` 不会被后面的参数替换
+pub fn tf(lang: Lang, text: Text, args: &[&dyn std::fmt::Display]) -> String {
+    let mut parts = t(lang, text).split("{}");
+    let mut result = parts.next().unwrap_or_default().to_string();
+    let mut args = args.iter();
+    for part in parts {
+        match args.next() {
+            Some(arg) => result.push_str(&arg.to_string()),
+            None => result.push_str("{}"),
+        }
+        result.push_str(part);
+    }
+    result
+}
+
+/// 按聊天保存的语言设置（仅保存在内存中）
+#[derive(Default)]
+pub struct LangStore {
+    langs: Mutex<HashMap<ChatId, Lang>>,
+}
+
+impl LangStore {
+    /// 获取聊天的语言，未设置时使用默认语言
+    pub fn get(&self, chat_id: ChatId) -> Lang {
+        self.langs
+            .lock()
+            .unwrap()
+            .get(&chat_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set(&self, chat_id: ChatId, lang: Lang) {
+        self.langs.lock().unwrap().insert(chat_id, lang);
+    }
+}
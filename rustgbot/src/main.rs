@@ -1,28 +1,31 @@
-use common::{LinkProcessor, ProcessorResult, ProcessorResultMedia, get_env_var};
+use common::{
+    AttemptBudget, DispatchDistribution, LinkProcessor, LinkResolution, ProcessOptions,
+    ProcessorErrorKind, ProcessorRegistry, ProcessorResult, ProcessorResultMedia, Settings,
+    settings,
+};
 use dotenv::dotenv;
-use regex::RegexSet;
-use std::sync::OnceLock;
+use std::sync::Arc;
+use std::time::Duration;
 use teloxide::dispatching::dialogue::GetChatId;
 use teloxide::prelude::*;
-use teloxide::types::{Message, MessageId, Update};
-use teloxide::{Bot, dptree};
+use teloxide::types::{Me, Message, MessageEntityKind, MessageId, Update, UserId};
+use teloxide::{Bot, RequestError, dptree};
 
 use processor_bili::BiliBiliProcessor;
 use processor_nga::NGALinkProcessor;
-use processor_pixiv::PixivLinkProcessor;
+use processor_pixiv::{PixivLinkProcessor, PixivSeriesLinkProcessor};
 use processor_x::XLinkProcessor;
 
 use crate::bot::MessageSenderBuilder;
+use crate::i18n::{Lang, LangStore};
 
 mod bot;
 mod commands;
+mod download;
+mod file_cache;
+mod i18n;
 mod tests;
 
-static PROCESSORS: OnceLock<Vec<Box<dyn LinkProcessor>>> = OnceLock::new();
-static REGEX_SET: OnceLock<RegexSet> = OnceLock::new();
-
-const TELEGRAM_PROXY_ENV_VAR: &str = "TELEGRAM_PROXY";
-
 #[derive(Debug)]
 pub enum BotResponse {
     Text(String),
@@ -37,13 +40,45 @@ fn init_processors() -> Vec<Box<dyn LinkProcessor>> {
         Box::new(BiliBiliProcessor),
         Box::new(NGALinkProcessor),
         Box::new(PixivLinkProcessor),
+        Box::new(PixivSeriesLinkProcessor),
     ]
 }
 
-fn init_regex_set() -> RegexSet {
-    let processors = PROCESSORS.get_or_init(init_processors);
-    let patterns: Vec<&str> = processors.iter().map(|p| p.pattern()).collect();
-    RegexSet::new(&patterns).expect("Failed to create RegexSet")
+/// 是否对内容不存在或无权访问的链接回复错误信息（SHOW_SKIP_ERRORS，默认不回复）
+fn is_show_skip_errors_enabled() -> bool {
+    settings().show_skip_errors
+}
+
+/// 使用给定的处理器构建注册表，超时时间从配置读取（PROCESSOR_TIMEOUT_SECS）
+fn build_registry(processors: Vec<Box<dyn LinkProcessor>>) -> ProcessorRegistry {
+    ProcessorRegistry::new(processors).with_timeout(settings().processor_timeout)
+}
+
+/// 定期清除各处理器缓存中已过期的条目（CACHE_SWEEP_SECS）
+fn spawn_cache_sweeper(registry: Arc<ProcessorRegistry>, interval: Duration) {
+    log::info!(
+        "Sweeping expired cache entries every {}",
+        common::human_duration(interval)
+    );
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // 第一次 tick 立即完成，跳过
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            for processor in registry.processors() {
+                if let Some(count) = processor.sweep_expired()
+                    && count > 0
+                {
+                    log::debug!(
+                        "Swept {} expired cache entries from {}",
+                        count,
+                        processor.name()
+                    );
+                }
+            }
+        }
+    });
 }
 
 #[tokio::main]
@@ -51,18 +86,45 @@ async fn main() {
     dotenv().ok();
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    let token = get_env_var("TELEGRAM_TOKEN").expect("TELEGRAM_TOKEN must be set");
-    let bot = match get_env_var(TELEGRAM_PROXY_ENV_VAR) {
-        Some(_) => {
-            let client = common::build_reqwest_client_with_proxy(TELEGRAM_PROXY_ENV_VAR);
-            Bot::with_client(token, client)
+    // 环境变量 CONFIG_FILE 指定的配置文件，环境变量优先于其中的值
+    if let Err(e) = common::load_config_from_env() {
+        log::error!("Failed to load CONFIG_FILE: {:#}", e);
+        std::process::exit(1);
+    }
+
+    // 启动时读取并校验所有配置
+    match Settings::from_env() {
+        Ok(settings) => common::init_settings(settings),
+        Err(e) => {
+            log::error!("{:#}", e);
+            log::error!(
+                "Fix the settings above, or set PROXY_OPTIONAL=1 to ignore invalid proxies"
+            );
+            std::process::exit(1);
         }
-        None => Bot::new(token),
-    };
+    }
+
+    let token = settings()
+        .telegram_token
+        .clone()
+        .expect("TELEGRAM_TOKEN must be set");
+    let bot = build_bot(token).await;
+
+    // 启动自检，在后台运行，不阻塞启动
+    if settings().pixiv_validate_on_start {
+        tokio::spawn(processor_pixiv::log_pixiv_auth_status());
+    }
+
+    let registry = Arc::new(build_registry(init_processors()));
+    let lang_store = Arc::new(LangStore::default());
+
+    if let Some(interval) = settings().cache_sweep_interval {
+        spawn_cache_sweeper(registry.clone(), interval);
+    }
 
     log::info!("Bot started. Listening for messages...");
 
-    let handler = Update::filter_message()
+    let message_handler = Update::filter_message()
         .branch(
             // 命令
             dptree::entry()
@@ -72,9 +134,14 @@ async fn main() {
         .branch(
             // 文本
             dptree::filter(|msg: Message| msg.text().is_some()).endpoint(
-                |bot: Bot, msg: Message| async move {
+                |bot: Bot,
+                 msg: Message,
+                 me: Me,
+                 registry: Arc<ProcessorRegistry>,
+                 lang_store: Arc<LangStore>| async move {
                     log::trace!("Received message: {:?}", &msg);
-                    process_text_message(&bot, msg).await;
+                    let lang = lang_store.get(msg.chat.id);
+                    process_text_message(&bot, msg, &me, &registry, lang).await;
                     Ok(())
                 },
             ),
@@ -82,26 +149,100 @@ async fn main() {
         .branch(
             // 处理私聊GIF消息
             dptree::filter(|msg: Message| msg.chat.is_private()).endpoint(
-                |bot: Bot, msg: Message| async move {
+                |bot: Bot, msg: Message, me: Me| async move {
                     log::trace!("Received private message: {:?}", &msg);
-                    process_private_message(&bot, msg).await;
+                    process_private_message(&bot, msg, &me).await;
                     Ok(())
                 },
             ),
         );
 
+    // 频道消息，发送者为频道本身
+    let channel_handler = Update::filter_channel_post()
+        .filter(|msg: Message| msg.text().is_some())
+        .endpoint(
+            |bot: Bot,
+             msg: Message,
+             me: Me,
+             registry: Arc<ProcessorRegistry>,
+             lang_store: Arc<LangStore>| async move {
+                log::trace!("Received channel post: {:?}", &msg);
+                let lang = lang_store.get(msg.chat.id);
+                process_text_message(&bot, msg, &me, &registry, lang).await;
+                Ok(())
+            },
+        );
+
+    let handler = dptree::entry()
+        .branch(message_handler)
+        .branch(channel_handler);
+
     Dispatcher::builder(bot, handler)
+        .dependencies(dptree::deps![registry, lang_store])
         .default_handler(|_| async move {
             // Handle unmatched updates by doing nothing
         })
+        .worker_queue_size(settings().dispatch_queue_size)
+        .distribution_function(dispatch_key)
         .enable_ctrlc_handler()
         .build()
         .dispatch()
         .await;
 }
 
-async fn process_text_message(bot: &Bot, msg: Message) {
-    let text = msg.text().unwrap();
+/// 调度器对更新分组的键，同一键的更新依次处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DispatchKey {
+    Chat(ChatId),
+    User(UserId),
+}
+
+/// 按 DISPATCH_DISTRIBUTION 计算更新的分组键，返回 None 时并发处理
+fn dispatch_key(update: &Update) -> Option<DispatchKey> {
+    match settings().dispatch_distribution {
+        DispatchDistribution::Chat => update.chat().map(|chat| DispatchKey::Chat(chat.id)),
+        DispatchDistribution::User => update.from().map(|user| DispatchKey::User(user.id)),
+        DispatchDistribution::None => None,
+    }
+}
+
+/// 构建 Bot，设置了代理时先检查连接，失败且启用 PROXY_FALLBACK_DIRECT 时改为直连
+async fn build_bot(token: String) -> Bot {
+    if settings().telegram_proxy.is_none() {
+        return Bot::new(token);
+    }
+
+    let bot = Bot::with_client(token.clone(), common::build_reqwest_client());
+    match bot.get_me().await {
+        Ok(_) => bot,
+        Err(e) if should_fallback_direct(settings().proxy_fallback_direct, &e) => {
+            log::warn!(
+                "Failed to connect to Telegram via TELEGRAM_PROXY: {}, falling back to direct connection",
+                e
+            );
+            bot::use_direct_connection();
+            Bot::new(token)
+        }
+        Err(e) => {
+            log::error!("Failed to connect to Telegram via TELEGRAM_PROXY: {}", e);
+            bot
+        }
+    }
+}
+
+/// 通过代理连接失败时是否改为直连，只有网络错误才回退
+fn should_fallback_direct(fallback_enabled: bool, err: &RequestError) -> bool {
+    fallback_enabled && matches!(err, RequestError::Network(_) | RequestError::Io(_))
+}
+
+async fn process_text_message(
+    bot: &Bot,
+    msg: Message,
+    me: &Me,
+    registry: &ProcessorRegistry,
+    lang: Lang,
+) {
+    let text = &message_text_with_links(&msg);
     let chat_id = msg.chat_id().unwrap();
 
     if should_skip_message(&msg) {
@@ -109,75 +250,228 @@ async fn process_text_message(bot: &Bot, msg: Message) {
         return;
     }
 
-    if let Some(responses) = process_links(text).await {
-        send_bot_responses(bot, chat_id, msg.id, responses).await;
+    if should_skip_forward(settings().skip_forwards, &msg) {
+        log::debug!("Skipping forwarded message: {:?}", msg.id);
+        return;
     }
+
+    let is_group = msg.chat.is_group() || msg.chat.is_supergroup();
+    if !should_process_in_chat(
+        is_group,
+        is_group_require_mention_enabled(),
+        is_bot_mentioned(text, me),
+        is_reply_to_bot(&msg, me),
+    ) {
+        log::debug!("Skipping group message without mention: {:?}", msg.id);
+        return;
+    }
+
+    if let Some(responses) = process_links(registry, text).await {
+        send_bot_responses(bot, chat_id, reply_target(&msg), responses, lang).await;
+    }
+}
+
+/// 提取消息中 text_link 实体隐藏在显示文字后的链接
+fn extract_text_link_urls(msg: &Message) -> Vec<String> {
+    msg.entities()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|entity| match &entity.kind {
+            MessageEntityKind::TextLink { url } => Some(url.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 获取需要处理的消息文本，并附加文本中没有出现的 text_link 链接
+fn message_text_with_links(msg: &Message) -> String {
+    let mut text = msg.text().unwrap_or_default().to_string();
+    for url in extract_text_link_urls(msg) {
+        if !text.contains(&url) {
+            text.push('\n');
+            text.push_str(&url);
+        }
+    }
+    text
+}
+
+/// 将媒体响应转换为文本响应（说明文字 + 媒体链接），其他响应保持不变
+fn into_text_only(response: BotResponse) -> BotResponse {
+    match response {
+        BotResponse::Photo(media) => {
+            let mut lines = vec![media.caption];
+            lines.extend(media.urls.iter().map(|url| common::escape_html(url)));
+            let text = lines
+                .into_iter()
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            BotResponse::Text(text)
+        }
+        other => other,
+    }
+}
+
+/// 将媒体结果中放不进说明文字的内容拆分为紧随其后的文本响应
+fn split_overflow_text(responses: Vec<BotResponse>) -> Vec<BotResponse> {
+    let mut split = Vec::with_capacity(responses.len());
+    for response in responses {
+        match response {
+            BotResponse::Photo(mut media) => {
+                let overflow_text = media.overflow_text.take();
+                split.push(BotResponse::Photo(media));
+                if let Some(text) = overflow_text.filter(|text| !text.trim().is_empty()) {
+                    split.push(BotResponse::Text(text));
+                }
+            }
+            other => split.push(other),
+        }
+    }
+    split
 }
 
 /// 发送机器人响应到聊天
 pub async fn send_bot_responses(
     bot: &Bot,
     chat_id: ChatId,
-    message_id: MessageId,
+    reply_to: Option<MessageId>,
     responses: Vec<BotResponse>,
+    lang: Lang,
 ) {
-    for resp in responses {
+    // 只允许发送文本时（TEXT_ONLY_MODE）不上传媒体
+    let text_only = settings().text_only_mode;
+    // 同一条消息的所有响应共享重试预算
+    let budget = AttemptBudget::default();
+    // 没有要回复的消息时（如频道中）不引用原消息
+    let sender = |text: String| {
+        let builder = MessageSenderBuilder::new(chat_id, text)
+            .budget(budget.clone())
+            .lang(lang);
+        match reply_to {
+            Some(message_id) => builder.message_id(message_id),
+            None => builder,
+        }
+    };
+    for resp in split_overflow_text(responses) {
+        let resp = if text_only {
+            into_text_only(resp)
+        } else {
+            resp
+        };
         let send_result = match resp {
-            BotResponse::Text(text) => {
-                MessageSenderBuilder::new(chat_id, text)
-                    .message_id(message_id)
-                    .send_message(bot)
-                    .await
-            }
+            BotResponse::Text(text) => sender(text).send_message(bot).await,
             BotResponse::Photo(media) => {
-                MessageSenderBuilder::new(chat_id, media.caption)
-                    .message_id(message_id)
+                sender(media.caption)
                     .urls(media.urls)
                     .spoiler(media.spoiler)
                     .original_urls(media.original_urls)
+                    .content_type(media.content_type)
+                    .source_url(media.source_url)
                     .send_photo(bot)
                     .await
             }
             BotResponse::RichMessage(html) => {
                 // Rich Message 使用 frankenstein 直接发送
                 if let Err(e) =
-                    bot::send_rich_message(chat_id, Some(message_id), None, Some(&html), false)
-                        .await
+                    bot::send_rich_message(chat_id, reply_to, None, Some(&html), false).await
                 {
                     log::error!("Failed to send rich message to chat {}: {}", chat_id, e);
                     // 回退到普通文本发送
-                    let _ = MessageSenderBuilder::new(
-                        chat_id,
-                        format!("[Rich Message 发送失败: {}]", e),
-                    )
-                    .message_id(message_id)
-                    .send_message(bot)
-                    .await;
+                    let _ = sender(format!("[Rich Message 发送失败: {}]", e))
+                        .send_message(bot)
+                        .await;
                 }
                 continue;
             }
-            BotResponse::Error(err) => {
-                MessageSenderBuilder::new(chat_id, err)
-                    .message_id(message_id)
-                    .send_message(bot)
-                    .await
-            }
+            BotResponse::Error(err) => sender(err).send_message(bot).await,
         };
 
         // 记录发送失败的错误，但不中断处理流程
         if let Err(e) = send_result {
             log::error!("Failed to send message to chat {}: {}", chat_id, e);
-            if let Err(fallback_err) = MessageSenderBuilder::new(chat_id, e.to_string())
-                .message_id(message_id)
-                .send_message(bot)
-                .await
-            {
+            if let Err(fallback_err) = sender(e.to_string()).send_message(bot).await {
                 log::error!("Failed to send fallback error message: {}", fallback_err);
             }
         }
     }
 }
 
+/// 群组中是否仅在提及或回复机器人时处理链接（GROUP_REQUIRE_MENTION）
+fn is_group_require_mention_enabled() -> bool {
+    settings().group_require_mention
+}
+
+/// 开启 SKIP_FORWARDS 时是否跳过该消息，只跳过转发的消息
+fn should_skip_forward(skip_forwards: bool, msg: &Message) -> bool {
+    skip_forwards && msg.forward_origin().is_some()
+}
+
+/// 判断是否处理消息中的链接，私聊总是处理
+fn should_process_in_chat(
+    is_group: bool,
+    require_mention: bool,
+    mentioned: bool,
+    replied_to_bot: bool,
+) -> bool {
+    !is_group || !require_mention || mentioned || replied_to_bot
+}
+
+/// 文本中是否提及了机器人，只匹配完整的用户名（如 `@testbot2` 不算提及 `@testbot`）
+fn is_bot_mentioned(text: &str, me: &Me) -> bool {
+    let mention = format!("@{}", me.username()).to_lowercase();
+    let text = text.to_lowercase();
+    // Telegram 用户名只包含字母、数字和下划线
+    let is_username_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    text.match_indices(&mention).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + mention.len()..].chars().next();
+        !before.is_some_and(is_username_char) && !after.is_some_and(is_username_char)
+    })
+}
+
+/// 消息是否回复了机器人
+fn is_reply_to_bot(msg: &Message, me: &Me) -> bool {
+    msg.reply_to_message()
+        .and_then(|reply| reply.from.as_ref())
+        .is_some_and(|user| user.id == me.id)
+}
+
+/// 消息所在的环境
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChatContext {
+    Private,
+    Group,
+    /// 群组中以群组身份发送的匿名管理员消息
+    AnonymousAdmin,
+    /// 频道中的消息，发送者为频道本身
+    Channel,
+}
+
+/// 判断消息所在的环境
+fn chat_context(msg: &Message) -> ChatContext {
+    if msg.chat.is_channel() {
+        ChatContext::Channel
+    } else if msg
+        .sender_chat
+        .as_ref()
+        .is_some_and(|sender| sender.id == msg.chat.id)
+    {
+        ChatContext::AnonymousAdmin
+    } else if msg.chat.is_private() {
+        ChatContext::Private
+    } else {
+        ChatContext::Group
+    }
+}
+
+/// 根据消息环境决定回复的消息，频道中不引用原消息而是直接发布
+fn reply_target(msg: &Message) -> Option<MessageId> {
+    match chat_context(msg) {
+        ChatContext::Channel => None,
+        ChatContext::Private | ChatContext::Group | ChatContext::AnonymousAdmin => Some(msg.id),
+    }
+}
+
 /// 检查link_preview_options是否存在已经被转换的链接
 fn should_skip_message(msg: &Message) -> bool {
     if msg.link_preview_options().is_none() {
@@ -185,116 +479,108 @@ fn should_skip_message(msg: &Message) -> bool {
     }
     if let Some(preview) = msg.link_preview_options() {
         // 链接存在 fixupx.com 或 fxtwitter.com 跳过
-        if preview
-            .url
-            .as_deref()
-            .is_some_and(|url| url.contains("fixupx.com") || url.contains("fxtwitter.com"))
-        {
+        if preview.url.as_deref().is_some_and(|url| {
+            common::host_matches(url, "fixupx.com") || common::host_matches(url, "fxtwitter.com")
+        }) {
             return true;
         }
     }
     false
 }
 
-async fn process_private_message(bot: &Bot, msg: Message) {
+async fn process_private_message(bot: &Bot, msg: Message, me: &Me) {
     // 处理私聊消息
     // 清理 gif caption
-    if msg.caption().is_none() {
+    let Some(caption) = msg.caption() else {
         return;
-    }
+    };
     if let Some(animation) = msg.animation() {
         if animation.mime_type != Some("video/mp4".parse().unwrap()) {
             return;
         }
         // 处理动画消息（如GIF）
         let gif_id = animation.file.id.clone();
-        if let Err(e) = bot::send_gif_from_fileid(bot, msg.chat.id, gif_id).await {
+        let caption = clean_gif_caption(caption, me.username());
+        if let Err(e) =
+            bot::send_gif_from_fileid(bot, msg.chat.id, gif_id, caption.as_deref()).await
+        {
             log::error!("Failed to send GIF: {}", e);
         }
     }
 }
 
+/// 清理 GIF 的说明文字：去除对 Bot 的提及和末尾的话题标签，去除空行
+/// 清理后为空时返回 None
+fn clean_gif_caption(caption: &str, bot_username: &str) -> Option<String> {
+    // 用户名只包含 ASCII 字符，转换为小写后字节位置不变
+    let mention = format!("@{}", bot_username).to_ascii_lowercase();
+    let mut text = caption.to_string();
+    let mut from = 0;
+    while let Some(pos) = text.to_ascii_lowercase()[from..]
+        .find(&mention)
+        .map(|pos| pos + from)
+    {
+        let end = pos + mention.len();
+        // 只去除完整的用户名，不影响更长的用户名
+        if text[end..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            from = end;
+            continue;
+        }
+        text.replace_range(pos..end, "");
+        from = pos;
+    }
+
+    // 去除末尾的话题标签
+    let mut text = text.trim_end();
+    while let Some(tag) = text.rsplit(char::is_whitespace).next()
+        && tag.starts_with('#')
+    {
+        text = text[..text.len() - tag.len()].trim_end();
+    }
+
+    let cleaned = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
 // 处理链接
-async fn process_links(text: &str) -> Option<Vec<BotResponse>> {
-    process_links_internal(text, true).await
+async fn process_links(registry: &ProcessorRegistry, text: &str) -> Option<Vec<BotResponse>> {
+    process_links_internal(registry, text, true).await
 }
 
 // 处理链接（完整文本，不截断）
-pub async fn process_links_full(text: &str) -> Option<Vec<BotResponse>> {
-    process_links_internal(text, false).await
+pub async fn process_links_full(
+    registry: &ProcessorRegistry,
+    text: &str,
+) -> Option<Vec<BotResponse>> {
+    process_links_internal(registry, text, false).await
 }
 
 // 内部链接处理函数
-async fn process_links_internal(text: &str, is_truncation: bool) -> Option<Vec<BotResponse>> {
-    // 快速检查是否包含任何可能的链接特征
-    if !text.contains("://")
-        && !text.contains(".com")
-        && !text.contains(".tv")
-        && !text.contains(".net")
+async fn process_links_internal(
+    registry: &ProcessorRegistry,
+    text: &str,
+    is_truncation: bool,
+) -> Option<Vec<BotResponse>> {
+    let options = ProcessOptions::from_settings(settings()).truncation(is_truncation);
+    let mut resolutions = registry.process(text, &options).await;
+    if resolutions.is_empty()
+        && let Some(kind) = processor_x::unsupported_x_link_kind(text)
     {
-        return None;
+        log::debug!("Ignoring unsupported X {} link", kind);
     }
-    // 如果文本过长，只处理前面部分
-    const MAX_TEXT_LENGTH: usize = 4000;
-    let text = if text.len() > MAX_TEXT_LENGTH {
-        &text[..MAX_TEXT_LENGTH]
-    } else {
-        text
-    };
-
-    // 设置截断标志
-    common::set_truncation_enabled(is_truncation);
-
-    let processors = PROCESSORS.get_or_init(init_processors);
-    let regex_set = REGEX_SET.get_or_init(init_regex_set);
-    let mut results = Vec::new();
-
-    // 使用 RegexSet 快速检查是否有任何匹配
-    if !regex_set.is_match(text) {
-        return None;
-    }
-
-    // 获取所有匹配的模式索引
-    let matches: Vec<usize> = regex_set.matches(text).into_iter().collect();
-
-    // 只对匹配的处理器进行详细匹配
-    for &match_index in &matches {
-        let processor = &processors[match_index];
-
-        // 使用对应的正则表达式进行详细匹配
-        for captures in processor.regex().captures_iter(text) {
-            let processing_type = if is_truncation { "full link" } else { "link" };
-            log::info!(
-                "Processing {} with {}: {}",
-                processing_type,
-                processor.name(),
-                captures.get(0).unwrap().as_str()
-            );
-
-            match processor.process_captures(&captures).await {
-                Ok(ProcessorResult::Text(processed_text)) => {
-                    results.push(BotResponse::Text(processed_text));
-                }
-                Ok(ProcessorResult::Media(parsed)) => {
-                    results.push(BotResponse::Photo(parsed));
-                }
-                Ok(ProcessorResult::Rich(rich)) => {
-                    results.push(BotResponse::RichMessage(rich.html));
-                }
-                Err(e) => {
-                    let error = format!(
-                        "Failed to process {} with {}\n{}\n{}",
-                        processing_type,
-                        processor.name(),
-                        captures.get(0).unwrap().as_str(),
-                        e
-                    );
-                    log::warn!("{}", error);
-                    results.push(BotResponse::Error(error));
-                }
-            }
-        }
+    if settings().show_source_footer {
+        add_source_footers(registry, &mut resolutions);
     }
+    let results = to_bot_responses(resolutions, is_truncation, is_show_skip_errors_enabled());
 
     if results.is_empty() {
         None
@@ -302,3 +588,51 @@ async fn process_links_internal(text: &str, is_truncation: bool) -> Option<Vec<B
         Some(results)
     }
 }
+
+/// 为媒体结果的说明文字附加对应处理器的来源标识
+fn add_source_footers(registry: &ProcessorRegistry, resolutions: &mut [LinkResolution]) {
+    for resolution in resolutions {
+        if let Ok(ProcessorResult::Media(media)) = &mut resolution.result
+            && let Some(processor) = registry.find_processor(resolution.processor)
+        {
+            media.caption = bot::append_source_footer(&media.caption, &processor.footer());
+        }
+    }
+}
+
+/// 将链接解析结果转换为机器人响应
+///
+/// 内容不存在或无权访问的错误只记录日志，除非 `show_skip_errors` 为 true
+fn to_bot_responses(
+    resolutions: Vec<LinkResolution>,
+    is_truncation: bool,
+    show_skip_errors: bool,
+) -> Vec<BotResponse> {
+    let processing_type = if is_truncation { "full link" } else { "link" };
+
+    resolutions
+        .into_iter()
+        .filter_map(|resolution| match resolution.result {
+            Ok(ProcessorResult::Text(processed_text)) => Some(BotResponse::Text(processed_text)),
+            Ok(ProcessorResult::Media(parsed)) => Some(BotResponse::Photo(parsed)),
+            Ok(ProcessorResult::Rich(rich)) => Some(BotResponse::RichMessage(rich.html)),
+            Err(e) => {
+                let error = format!(
+                    "Failed to process {} with {}\n{}\n{}",
+                    processing_type, resolution.processor, resolution.matched, e
+                );
+                log::warn!("{}", error);
+
+                let skippable = matches!(
+                    e.kind,
+                    ProcessorErrorKind::NotFound | ProcessorErrorKind::Forbidden
+                );
+                if skippable && !show_skip_errors {
+                    log::info!("Skipping {} error reply for {}", e.kind, resolution.matched);
+                    return None;
+                }
+                Some(BotResponse::Error(error))
+            }
+        })
+        .collect()
+}
@@ -1,8 +1,10 @@
 #[cfg(test)]
 mod main_tests {
     use crate::*;
-    use common::LinkProcessor;
+    use common::testing::MockProcessor;
+    use common::{LinkProcessor, ProcessorError};
     use processor_x::XLinkProcessor;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_unified_interface() {
@@ -37,6 +39,7 @@ mod main_tests {
             ("https://bili2233.cn/xyz789", "BiliBili"),
             ("https://pixiv.net/artworks/123456", "Pixiv"),
             ("https://www.pixiv.net/artworks/789012", "Pixiv"),
+            ("https://www.pixiv.net/user/123/series/456", "Pixiv Series"),
             ("https://bbs.nga.cn/read.php?tid=123456", "NGA"),
             ("https://ngabbs.com/read.php?tid=789012", "NGA"),
         ];
@@ -46,6 +49,7 @@ mod main_tests {
             Box::new(BiliBiliProcessor),
             Box::new(NGALinkProcessor),
             Box::new(PixivLinkProcessor),
+            Box::new(PixivSeriesLinkProcessor),
         ];
 
         for (test_url, expected_processor) in test_cases {
@@ -71,4 +75,1385 @@ mod main_tests {
             assert!(found, "URL {} was not matched by any processor", test_url);
         }
     }
+
+    #[tokio::test]
+    async fn test_processor_timeout() {
+        let registry = ProcessorRegistry::new(vec![
+            Box::new(
+                MockProcessor::new("Slow", r"https://slow\.example/\d+")
+                    .delay(Duration::from_secs(10)),
+            ),
+            Box::new(MockProcessor::new("Fast", r"https://fast\.example/\d+")),
+        ])
+        .with_timeout(Duration::from_millis(100));
+
+        let text = "https://slow.example/1 https://fast.example/2";
+        let started = std::time::Instant::now();
+        let results = to_bot_responses(registry.resolve_links(text, true).await, true, false);
+
+        // 超时应该在慢处理器完成之前触发
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(results.len(), 2);
+
+        match &results[0] {
+            BotResponse::Error(err) => {
+                assert!(err.contains("Slow"), "错误信息应包含处理器名称: {}", err);
+                assert!(err.contains("https://slow.example/1"));
+            }
+            other => panic!("慢处理器应该返回超时错误，实际: {:?}", other),
+        }
+
+        match &results[1] {
+            BotResponse::Text(text) => assert_eq!(text, "Fast:https://fast.example/2"),
+            other => panic!("快处理器应该正常返回，实际: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_admin_user_ids() {
+        let test_cases = vec![
+            ("123", vec![123]),
+            ("123,456", vec![123, 456]),
+            (" 123 , 456 ,789 ", vec![123, 456, 789]),
+            // 忽略空项和无效项
+            ("123,,456,", vec![123, 456]),
+            ("abc,123,-5", vec![123]),
+            ("", vec![]),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(
+                common::parse_admin_user_ids(input),
+                expected,
+                "Failed for input: {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_should_fallback_direct() {
+        use std::sync::Arc;
+        use teloxide::{ApiError, RequestError};
+
+        let proxy_failure = RequestError::Io(Arc::new(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "proxy unreachable",
+        )));
+        assert!(should_fallback_direct(true, &proxy_failure));
+        // 未启用时不回退
+        assert!(!should_fallback_direct(false, &proxy_failure));
+        // Telegram 返回的错误与代理无关，不回退
+        assert!(!should_fallback_direct(
+            true,
+            &RequestError::Api(ApiError::InvalidToken)
+        ));
+    }
+
+    #[test]
+    fn test_is_admin() {
+        let settings = common::Settings::from_lookup(|name| {
+            (name == "ADMIN_USER_IDS").then(|| "123, 456,789".to_string())
+        })
+        .unwrap();
+        assert!(settings.is_admin(123));
+        assert!(settings.is_admin(456));
+        assert!(settings.is_admin(789));
+        assert!(!settings.is_admin(100));
+    }
+
+    #[test]
+    fn test_clear_caches() {
+        let processors: Vec<Arc<dyn LinkProcessor>> = vec![
+            Arc::new(MockProcessor::new("Cached", r"cached").with_cache(3)),
+            Arc::new(MockProcessor::new("NoCache", r"nocache")),
+        ];
+
+        // 只返回有缓存的处理器
+        let cleared = commands::clear_caches(&processors);
+        assert_eq!(cleared, vec![("Cached", 3)]);
+
+        // 再次清除时缓存已为空
+        let cleared = commands::clear_caches(&processors);
+        assert_eq!(cleared, vec![("Cached", 0)]);
+    }
+
+    #[test]
+    fn test_split_download_args() {
+        let test_cases = vec![
+            ("https://a.com/1.jpg", vec!["https://a.com/1.jpg"]),
+            (
+                "https://a.com/1.jpg https://b.com/2.mp4",
+                vec!["https://a.com/1.jpg", "https://b.com/2.mp4"],
+            ),
+            (
+                "  https://a.com/1.jpg\n\thttps://b.com/2.mp4  ",
+                vec!["https://a.com/1.jpg", "https://b.com/2.mp4"],
+            ),
+            ("", vec![]),
+            ("   ", vec![]),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(
+                commands::split_download_args(input),
+                expected,
+                "Failed for input: {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_summarize_download_results() {
+        // 全部成功不回复
+        let results = vec![
+            ("https://a.com/1.jpg", Ok(())),
+            ("https://b.com/2.jpg", Ok(())),
+        ];
+        assert_eq!(
+            commands::summarize_download_results(&results, i18n::Lang::Zh),
+            None
+        );
+
+        // 单个URL失败直接返回错误信息
+        let results = vec![("https://a.com/1.jpg", Err("下载文件失败: 404".to_string()))];
+        assert_eq!(
+            commands::summarize_download_results(&results, i18n::Lang::Zh),
+            Some("下载文件失败: 404".to_string())
+        );
+
+        // 多个URL只列出失败的部分
+        let results = vec![
+            ("https://a.com/1.jpg", Ok(())),
+            ("not-a-url", Err("无效的URL格式。".to_string())),
+            ("https://c.com/3.jpg", Err("下载文件失败: 404".to_string())),
+        ];
+        let report = commands::summarize_download_results(&results, i18n::Lang::Zh).unwrap();
+        assert!(report.starts_with("2/3 个文件处理失败"));
+        assert!(report.contains("not-a-url\n无效的URL格式。"));
+        assert!(report.contains("https://c.com/3.jpg\n下载文件失败: 404"));
+        assert!(!report.contains("https://a.com/1.jpg"));
+    }
+
+    #[test]
+    fn test_parse_download_args() {
+        use common::MediaKind;
+
+        let lang = i18n::Lang::Zh;
+        let args = commands::parse_download_args("https://a.com/1.mp3 --type=audio", lang).unwrap();
+        assert_eq!(args.urls, vec!["https://a.com/1.mp3"]);
+        assert_eq!(args.media_type, Some(MediaKind::Audio));
+
+        // 选项位置任意
+        let args = commands::parse_download_args(
+            "--type=Video https://a.com/1.mp4 https://b.com/2.mp4",
+            lang,
+        )
+        .unwrap();
+        assert_eq!(
+            args.urls,
+            vec!["https://a.com/1.mp4", "https://b.com/2.mp4"]
+        );
+        assert_eq!(args.media_type, Some(MediaKind::Video));
+
+        // 不指定类型
+        let args = commands::parse_download_args("https://a.com/1.jpg", lang).unwrap();
+        assert_eq!(args.media_type, None);
+
+        // 错误情况
+        assert!(commands::parse_download_args("https://a.com/1.jpg --type=pdf", lang).is_err());
+        assert!(commands::parse_download_args("https://a.com/1.jpg --unknown", lang).is_err());
+        assert!(commands::parse_download_args("--type=image", lang).is_err());
+        assert!(commands::parse_download_args("", lang).is_err());
+    }
+
+    #[test]
+    fn test_check_media_type() {
+        use common::MediaKind;
+
+        let url = "https://a.com/file";
+        let lang = i18n::Lang::Zh;
+
+        // 未指定类型时全部接受
+        assert!(commands::check_media_type(None, "application/pdf", url, lang).is_ok());
+
+        assert!(commands::check_media_type(Some(MediaKind::Image), "image/png", url, lang).is_ok());
+        assert!(commands::check_media_type(Some(MediaKind::Video), "video/mp4", url, lang).is_ok());
+        assert!(
+            commands::check_media_type(Some(MediaKind::Audio), "audio/mpeg", url, lang).is_ok()
+        );
+
+        let err =
+            commands::check_media_type(Some(MediaKind::Audio), "video/mp4", url, lang).unwrap_err();
+        assert!(err.contains("audio"));
+        assert!(err.contains("video/mp4"));
+
+        // application/octet-stream 根据URL扩展名推断
+        assert!(
+            commands::check_media_type(
+                Some(MediaKind::Audio),
+                "application/octet-stream",
+                "https://a.com/song.mp3",
+                lang
+            )
+            .is_ok()
+        );
+        assert!(
+            commands::check_media_type(
+                Some(MediaKind::Audio),
+                "application/octet-stream",
+                url,
+                lang
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_download_send_as() {
+        use crate::bot::SendMethod;
+
+        let lang = i18n::Lang::Zh;
+        let args =
+            commands::parse_download_args("https://a.com/1.mp4 --as=document", lang).unwrap();
+        assert_eq!(args.urls, vec!["https://a.com/1.mp4"]);
+        assert_eq!(args.send_as, Some(SendMethod::Document));
+
+        let args = commands::parse_download_args("--as=Photo https://a.com/1", lang).unwrap();
+        assert_eq!(args.send_as, Some(SendMethod::Photo));
+
+        let args =
+            commands::parse_download_args("https://a.com/1 --as=video --type=video", lang).unwrap();
+        assert_eq!(args.send_as, Some(SendMethod::Video));
+        assert_eq!(args.media_type, Some(common::MediaKind::Video));
+
+        let args = commands::parse_download_args("https://a.com/1", lang).unwrap();
+        assert_eq!(args.send_as, None);
+
+        assert!(commands::parse_download_args("https://a.com/1 --as=audio", lang).is_err());
+        assert!(commands::parse_download_args("https://a.com/1 --as=", lang).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_test_command_processor_selection() {
+        assert_eq!(
+            commands::parse_test_args("X/Twitter https://x.com/user/status/123"),
+            Some(("X/Twitter", "https://x.com/user/status/123"))
+        );
+        // 名称可包含空格
+        assert_eq!(
+            commands::parse_test_args("  Pixiv Series  https://www.pixiv.net/user/1/series/2 "),
+            Some(("Pixiv Series", "https://www.pixiv.net/user/1/series/2"))
+        );
+        assert_eq!(
+            commands::parse_test_args("https://x.com/user/status/123"),
+            None
+        );
+        assert_eq!(commands::parse_test_args(""), None);
+
+        let registry = build_registry(init_processors());
+        assert_eq!(
+            registry.find_processor("x/twitter").map(|p| p.name()),
+            Some("X/Twitter")
+        );
+        assert_eq!(
+            registry.find_processor("Pixiv Series").map(|p| p.name()),
+            Some("Pixiv Series")
+        );
+        assert!(registry.find_processor("Unknown").is_none());
+
+        // 只运行指定的处理器，不匹配时没有结果
+        let processor = registry.find_processor("X/Twitter").unwrap();
+        let resolutions = registry
+            .resolve_with(processor.as_ref(), "https://x.com/user/status/123", true)
+            .await;
+        assert_eq!(resolutions.len(), 1);
+        let dump = commands::format_test_result(&resolutions);
+        assert!(dump.starts_with("<b>X/Twitter</b>: https://x.com/user/status/123\n<pre>"));
+        assert!(dump.contains("https://fxtwitter.com/user/status/123"));
+
+        assert!(
+            registry
+                .resolve_with(
+                    processor.as_ref(),
+                    "https://www.pixiv.net/artworks/456",
+                    true
+                )
+                .await
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_select_download_source() {
+        use commands::{DownloadSource, select_download_source};
+        use common::{LinkResolution, ProcessorResult, ProcessorResultMedia};
+
+        let resolution = |result| LinkResolution {
+            processor: "Test",
+            matched: "https://example.com/post/1".to_string(),
+            result,
+        };
+        let media = |urls: &[&str], original_urls: Option<&[&str]>| {
+            let mut media = ProcessorResultMedia::new("")
+                .with_urls(urls.iter().map(|s| s.to_string()).collect());
+            if let Some(original_urls) = original_urls {
+                media =
+                    media.with_original_urls(original_urls.iter().map(|s| s.to_string()).collect());
+            }
+            Ok(ProcessorResult::Media(media))
+        };
+
+        // 没有处理器匹配时直接下载
+        assert_eq!(select_download_source(vec![]), Ok(DownloadSource::Raw));
+
+        // 处理器只返回文本时也直接下载
+        assert_eq!(
+            select_download_source(vec![resolution(Ok(ProcessorResult::Text(
+                "text".to_string()
+            )))]),
+            Ok(DownloadSource::Raw)
+        );
+
+        // 处理器返回媒体时使用媒体URL，优先使用原始URL
+        assert_eq!(
+            select_download_source(vec![resolution(media(
+                &["https://proxy/1.jpg", "https://proxy/2.jpg"],
+                Some(&["https://orig/1.jpg", "https://orig/2.jpg"])
+            ))]),
+            Ok(DownloadSource::Media(vec![
+                "https://orig/1.jpg".to_string(),
+                "https://orig/2.jpg".to_string()
+            ]))
+        );
+        assert_eq!(
+            select_download_source(vec![resolution(media(&["https://a/1.jpg"], None))]),
+            Ok(DownloadSource::Media(vec!["https://a/1.jpg".to_string()]))
+        );
+
+        // 处理器失败时返回错误信息
+        let err =
+            select_download_source(vec![resolution(Err(ProcessorError::new("boom")))]).unwrap_err();
+        assert!(err.contains("Test"));
+        assert!(err.contains("boom"));
+    }
+
+    #[test]
+    fn test_parse_download_no_reply() {
+        use teloxide::types::MessageId;
+
+        let lang = i18n::Lang::Zh;
+        let args = commands::parse_download_args("https://a.com/1", lang).unwrap();
+        assert!(!args.no_reply);
+        assert_eq!(args.reply_to(MessageId(42)), Some(MessageId(42)));
+
+        let args =
+            commands::parse_download_args("--no-reply https://a.com/1 --as=photo", lang).unwrap();
+        assert_eq!(args.urls, vec!["https://a.com/1"]);
+        assert!(args.no_reply);
+        assert_eq!(args.reply_to(MessageId(42)), None);
+
+        // 只有选项没有URL
+        assert!(commands::parse_download_args("--no-reply", lang).is_err());
+        assert!(commands::parse_download_args("https://a.com/1 --no-reply=1", lang).is_err());
+    }
+
+    #[test]
+    fn test_send_method_routing() {
+        use crate::bot::SendMethod;
+
+        // 根据content-type自动判断
+        let test_cases = vec![
+            ("image/gif", SendMethod::Animation),
+            ("image/jpeg", SendMethod::Photo),
+            ("image/png", SendMethod::Photo),
+            ("image/webp", SendMethod::Photo),
+            // Telegram 不接受作为图片的类型按文件发送
+            ("image/svg+xml", SendMethod::Document),
+            ("image/svg+xml; charset=utf-8", SendMethod::Document),
+            ("image/x-icon", SendMethod::Document),
+            ("image/tiff", SendMethod::Document),
+            ("image/avif", SendMethod::Document),
+            ("image/heic", SendMethod::Document),
+            ("image/heif", SendMethod::Document),
+            ("video/mp4", SendMethod::Video),
+            ("audio/mpeg", SendMethod::Audio),
+            ("application/pdf", SendMethod::Document),
+            ("application/octet-stream", SendMethod::Document),
+        ];
+        for (content_type, expected) in test_cases {
+            assert_eq!(
+                SendMethod::resolve(content_type, None),
+                expected,
+                "Failed for content-type: {}",
+                content_type
+            );
+        }
+
+        // 强制指定时忽略content-type
+        assert_eq!(
+            SendMethod::resolve("application/octet-stream", Some(SendMethod::Video)),
+            SendMethod::Video
+        );
+        assert_eq!(
+            SendMethod::resolve("image/avif", Some(SendMethod::Photo)),
+            SendMethod::Photo
+        );
+        assert_eq!(
+            SendMethod::resolve("video/mp4", Some(SendMethod::Document)),
+            SendMethod::Document
+        );
+        assert_eq!(
+            SendMethod::resolve("image/gif", Some(SendMethod::Photo)),
+            SendMethod::Photo
+        );
+    }
+
+    #[test]
+    fn test_send_method_sniff() {
+        use crate::bot::SendMethod;
+
+        let animated_webp: &[u8] = b"RIFF\x24\x00\x00\x00WEBPVP8X\x0a\x00\x00\x00\x02\x00\x00\x00\x00\x00\x00\x00\x00\x00ANIM\x06\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+        assert_eq!(
+            SendMethod::sniff(animated_webp),
+            Some(SendMethod::Animation)
+        );
+        assert_eq!(
+            SendMethod::resolve("image/webp", SendMethod::sniff(animated_webp)),
+            SendMethod::Animation
+        );
+
+        // 静态 WEBP 按 content-type 发送
+        let static_webp: &[u8] =
+            b"RIFF\x12\x00\x00\x00WEBPVP8L\x05\x00\x00\x00\x2f\x00\x00\x00\x00\x00";
+        assert_eq!(SendMethod::sniff(static_webp), None);
+        assert_eq!(
+            SendMethod::resolve("image/webp", SendMethod::sniff(static_webp)),
+            SendMethod::Photo
+        );
+    }
+
+    #[test]
+    fn test_send_method_animation_hint() {
+        use crate::bot::SendMethod;
+
+        // 有动画提示时 MP4 作为动画发送
+        assert_eq!(
+            SendMethod::with_animation_hint("video/mp4", true),
+            SendMethod::Animation
+        );
+        assert_eq!(
+            SendMethod::with_animation_hint("video/mp4; codecs=avc1", true),
+            SendMethod::Animation
+        );
+        // 没有提示时仍为视频
+        assert_eq!(
+            SendMethod::with_animation_hint("video/mp4", false),
+            SendMethod::Video
+        );
+        // 其他类型不受影响
+        assert_eq!(
+            SendMethod::with_animation_hint("video/webm", true),
+            SendMethod::Video
+        );
+        assert_eq!(
+            SendMethod::with_animation_hint("image/jpeg", true),
+            SendMethod::Photo
+        );
+        assert_eq!(
+            SendMethod::with_animation_hint("image/gif", false),
+            SendMethod::Animation
+        );
+    }
+
+    #[test]
+    fn test_into_text_only() {
+        use common::ProcessorResultMedia;
+
+        let media = ProcessorResultMedia::new("<b>标题</b>")
+            .with_urls(vec![
+                "https://example.com/1.jpg".to_string(),
+                "https://example.com/2.jpg?a=1&b=2".to_string(),
+            ])
+            .with_spoiler(true);
+        match into_text_only(BotResponse::Photo(media)) {
+            BotResponse::Text(text) => assert_eq!(
+                text,
+                "<b>标题</b>\nhttps://example.com/1.jpg\nhttps://example.com/2.jpg?a=1&amp;b=2"
+            ),
+            other => panic!("应该转换为文本，实际: {:?}", other),
+        }
+
+        // 没有说明文字时只发送链接
+        let media =
+            ProcessorResultMedia::new("").with_urls(vec!["https://example.com/1.jpg".to_string()]);
+        match into_text_only(BotResponse::Photo(media)) {
+            BotResponse::Text(text) => assert_eq!(text, "https://example.com/1.jpg"),
+            other => panic!("应该转换为文本，实际: {:?}", other),
+        }
+
+        // 其他响应不变
+        match into_text_only(BotResponse::Error("error".to_string())) {
+            BotResponse::Error(text) => assert_eq!(text, "error"),
+            other => panic!("错误响应不应改变，实际: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_split_overflow_text() {
+        use common::ProcessorResultMedia;
+
+        let urls = vec!["https://example.com/1.jpg".to_string()];
+        let responses = vec![
+            BotResponse::Photo(
+                ProcessorResultMedia::new("caption")
+                    .with_urls(urls.clone())
+                    .with_overflow_text("overflow"),
+            ),
+            BotResponse::Photo(
+                ProcessorResultMedia::new("no overflow")
+                    .with_urls(urls.clone())
+                    .with_overflow_text("  "),
+            ),
+            BotResponse::Text("text".to_string()),
+        ];
+
+        // 超出部分紧跟在对应的媒体之后发送
+        match split_overflow_text(responses).as_slice() {
+            [
+                BotResponse::Photo(first),
+                BotResponse::Text(overflow),
+                BotResponse::Photo(second),
+                BotResponse::Text(text),
+            ] => {
+                assert_eq!(first.caption, "caption");
+                assert_eq!(first.overflow_text, None);
+                assert_eq!(overflow, "overflow");
+                assert_eq!(second.caption, "no overflow");
+                assert_eq!(text, "text");
+            }
+            other => panic!("拆分结果不符合预期: {:?}", other),
+        }
+
+        // 处理器结果中的后续文本在媒体之后作为文本响应发送
+        let resolution = LinkResolution {
+            processor: "Pixiv",
+            matched: "https://www.pixiv.net/artworks/1".to_string(),
+            result: Ok(ProcessorResult::Media(
+                ProcessorResultMedia::new("<b>标题</b>")
+                    .with_urls(urls)
+                    .with_overflow_text("<blockquote expandable>描述</blockquote>"),
+            )),
+        };
+        match split_overflow_text(to_bot_responses(vec![resolution], true, false)).as_slice() {
+            [BotResponse::Photo(media), BotResponse::Text(overflow)] => {
+                assert_eq!(media.caption, "<b>标题</b>");
+                assert_eq!(overflow, "<blockquote expandable>描述</blockquote>");
+            }
+            other => panic!("拆分结果不符合预期: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_format_media_summary() {
+        use crate::bot::format_media_summary;
+
+        // 下载后发送，大小已知
+        let summary = format_media_summary(&[
+            ("https://i.pximg.net/img-original/1.png", Some(1000)),
+            ("https://pbs.twimg.com/media/2.jpg", Some(500)),
+        ]);
+        assert_eq!(
+            summary,
+            format!(
+                "count=2 hosts=[i.pximg.net, pbs.twimg.com] total={}",
+                common::convert_bytes(1500.0)
+            )
+        );
+
+        // 直接通过 URL 发送，大小未知
+        let summary = format_media_summary(&[("https://pbs.twimg.com/media/2.jpg", None)]);
+        assert_eq!(summary, "count=1 hosts=[pbs.twimg.com] total=unknown");
+
+        // 无法解析的URL
+        let summary = format_media_summary(&[("not a url", Some(10))]);
+        assert!(summary.starts_with("count=1 hosts=[unknown] total="));
+
+        assert_eq!(format_media_summary(&[]), "count=0 hosts=[] total=unknown");
+    }
+
+    #[test]
+    fn test_unsupported_image_note() {
+        use crate::bot::{SendMethod, unsupported_image_note};
+        use i18n::Lang;
+
+        let note = unsupported_image_note("image/avif", SendMethod::Document, Lang::Zh).unwrap();
+        assert_eq!(note, "\n<i>AVIF 格式的图片无法直接显示，已作为文件发送</i>");
+        let note = unsupported_image_note("image/heic", SendMethod::Document, Lang::En).unwrap();
+        assert_eq!(
+            note,
+            "\n<i>HEIC images cannot be displayed directly, sent as a file</i>"
+        );
+
+        // 普通图片或强制按图片发送时不附加提示
+        assert_eq!(
+            unsupported_image_note("image/jpeg", SendMethod::Photo, Lang::Zh),
+            None
+        );
+        assert_eq!(
+            unsupported_image_note("image/avif", SendMethod::Photo, Lang::Zh),
+            None
+        );
+        assert_eq!(
+            unsupported_image_note("application/pdf", SendMethod::Document, Lang::Zh),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registry_with_subset() {
+        // 只注册 X 处理器，其他链接不应被处理
+        let registry = ProcessorRegistry::new(vec![Box::new(XLinkProcessor)]);
+        assert_eq!(registry.processors().len(), 1);
+        assert_eq!(registry.processors()[0].name(), "X/Twitter");
+
+        let text =
+            "https://x.com/user/status/123 https://www.pixiv.net/artworks/456 https://b23.tv/abc";
+        let responses = process_links(&registry, text).await.expect("应该处理X链接");
+        assert_eq!(responses.len(), 1);
+        match &responses[0] {
+            BotResponse::Text(text) => {
+                assert_eq!(text, "https://fxtwitter.com/user/status/123")
+            }
+            other => panic!("应该返回文本结果，实际: {:?}", other),
+        }
+
+        // 没有匹配的处理器时不返回响应
+        assert!(
+            process_links(&registry, "https://www.pixiv.net/artworks/456")
+                .await
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_example_urls() {
+        let processors = init_processors();
+        for processor in &processors {
+            let examples = processor.example_urls();
+            assert!(!examples.is_empty(), "{} has no examples", processor.name());
+            for url in examples {
+                // 示例只匹配自身的正则
+                let matched = processors
+                    .iter()
+                    .filter(|p| p.regex().is_match(url))
+                    .map(|p| p.name())
+                    .collect::<Vec<_>>();
+                assert_eq!(matched, vec![processor.name()], "example: {}", url);
+                // 示例能通过预筛选
+                assert!(
+                    processor.link_hints().iter().any(|hint| url.contains(hint)),
+                    "example not covered by link hints: {}",
+                    url
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_schemeless_nga_link() {
+        let registry = ProcessorRegistry::new(init_processors());
+        let text = "看看 bbs.gnacn.cc/read.php?tid=1";
+
+        assert!(registry.may_contain_links(text));
+        let nga = registry.find_processor("NGA").unwrap();
+        let captures = nga.regex().captures(text).unwrap();
+        assert_eq!(&captures[0], "bbs.gnacn.cc/read.php?tid=1");
+        // 不匹配其他域名中的片段
+        assert!(!nga.regex().is_match("xbbs.gnacn.cc/read.php?tid=1"));
+    }
+
+    #[test]
+    fn test_may_contain_links() {
+        let registry = ProcessorRegistry::new(init_processors());
+
+        // NGA 的 .cc 域名不会被预筛选跳过
+        assert!(registry.may_contain_links("看看 https://bbs.gnacn.cc/read.php?tid=123456"));
+        // 不带协议的短链接
+        assert!(registry.may_contain_links("b23.tv/abc123"));
+        // 不区分大小写
+        assert!(registry.may_contain_links("https://X.com/user/status/1"));
+        assert!(!registry.may_contain_links("没有链接的消息"));
+        assert!(!registry.may_contain_links("https://example.com/page"));
+
+        // 没有提供提示的处理器不跳过任何消息
+        let registry =
+            ProcessorRegistry::new(vec![Box::new(MockProcessor::new("Mock", r"mock:\d+"))]);
+        assert!(registry.may_contain_links("mock:1"));
+    }
+
+    #[test]
+    fn test_registry_regex_matching() {
+        let registry = ProcessorRegistry::new(vec![
+            Box::new(BiliBiliProcessor),
+            Box::new(PixivLinkProcessor),
+        ]);
+        let names: Vec<_> = registry.processors().iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["BiliBili", "Pixiv"]);
+    }
+
+    #[test]
+    fn test_skip_errors() {
+        let resolution = |kind| LinkResolution {
+            processor: "Mock",
+            matched: "https://mock.example/1".to_string(),
+            result: Err(ProcessorError::new("mock error").with_kind(kind)),
+        };
+
+        // 默认不回复无权访问和内容不存在的错误
+        let responses = to_bot_responses(
+            vec![
+                resolution(ProcessorErrorKind::Forbidden),
+                resolution(ProcessorErrorKind::NotFound),
+            ],
+            true,
+            false,
+        );
+        assert!(responses.is_empty(), "不应该返回错误: {:?}", responses);
+
+        // 其他错误仍然回复
+        let responses = to_bot_responses(
+            vec![
+                resolution(ProcessorErrorKind::Forbidden),
+                resolution(ProcessorErrorKind::Network),
+            ],
+            true,
+            false,
+        );
+        assert_eq!(responses.len(), 1);
+        assert!(matches!(&responses[0], BotResponse::Error(err) if err.contains("mock error")));
+
+        // 开启 SHOW_SKIP_ERRORS 后全部回复
+        let responses = to_bot_responses(
+            vec![
+                resolution(ProcessorErrorKind::Forbidden),
+                resolution(ProcessorErrorKind::NotFound),
+            ],
+            true,
+            true,
+        );
+        assert_eq!(responses.len(), 2);
+        assert!(
+            responses
+                .iter()
+                .all(|response| matches!(response, BotResponse::Error(_)))
+        );
+    }
+
+    #[test]
+    fn test_select_command_text() {
+        let reply = Some("https://x.com/user/status/123");
+
+        // 有参数时优先使用参数
+        assert_eq!(
+            commands::select_command_text("https://pixiv.net/artworks/1", reply),
+            Some("https://pixiv.net/artworks/1")
+        );
+        assert_eq!(commands::select_command_text("text", None), Some("text"));
+
+        // 参数为空时使用被回复消息的文本
+        assert_eq!(commands::select_command_text("", reply), reply);
+        assert_eq!(commands::select_command_text("  \n", reply), reply);
+
+        // 都没有时返回 None
+        assert_eq!(commands::select_command_text("", None), None);
+        assert_eq!(commands::select_command_text("", Some("  ")), None);
+    }
+
+    /// 从 JSON 构建测试用的消息
+    fn message_from_json(value: serde_json::Value) -> Message {
+        serde_json::from_value(value).expect("应该是有效的消息")
+    }
+
+    fn chat_json() -> serde_json::Value {
+        serde_json::json!({ "id": 1, "type": "private", "first_name": "test" })
+    }
+
+    #[test]
+    fn test_extract_text_link_urls() {
+        let msg = message_from_json(serde_json::json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": chat_json(),
+            "text": "看这个 https://www.pixiv.net/artworks/456",
+            "entities": [
+                {
+                    "type": "text_link",
+                    "offset": 0,
+                    "length": 3,
+                    "url": "https://x.com/user/status/123"
+                },
+                { "type": "url", "offset": 4, "length": 34 }
+            ]
+        }));
+        assert_eq!(
+            extract_text_link_urls(&msg),
+            vec!["https://x.com/user/status/123".to_string()]
+        );
+        // 隐藏的链接附加到文本后
+        assert_eq!(
+            message_text_with_links(&msg),
+            "看这个 https://www.pixiv.net/artworks/456\nhttps://x.com/user/status/123"
+        );
+
+        // 没有实体时文本不变
+        let msg = message_from_json(serde_json::json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": chat_json(),
+            "text": "https://x.com/user/status/123"
+        }));
+        assert!(extract_text_link_urls(&msg).is_empty());
+        assert_eq!(
+            message_text_with_links(&msg),
+            "https://x.com/user/status/123"
+        );
+    }
+
+    #[test]
+    fn test_is_pixiv_related_url() {
+        use crate::bot::is_pixiv_related_url;
+
+        assert!(is_pixiv_related_url(
+            "https://i.pximg.net/img-original/1.png"
+        ));
+        assert!(is_pixiv_related_url("https://www.pixiv.net/artworks/1"));
+        assert!(is_pixiv_related_url(
+            "https://www.phixiv.net/i/img-original/1.png"
+        ));
+
+        // 域名只出现在路径中时不是 Pixiv 链接
+        assert!(!is_pixiv_related_url("https://evil.com/pixiv.net/x.jpg"));
+        assert!(!is_pixiv_related_url(
+            "https://pbs.twimg.com/media/i.pximg.net.jpg"
+        ));
+        // 国际化域名
+        assert!(!is_pixiv_related_url("https://例子.中国/pximg.net/1.png"));
+    }
+
+    #[test]
+    fn test_chat_context_reply_target() {
+        let message = |chat: serde_json::Value, sender_chat: Option<serde_json::Value>| {
+            let mut value = serde_json::json!({
+                "message_id": 7,
+                "date": 0,
+                "chat": chat,
+                "text": "https://x.com/user/status/123"
+            });
+            if let Some(sender_chat) = sender_chat {
+                value["sender_chat"] = sender_chat;
+            }
+            message_from_json(value)
+        };
+        let group = serde_json::json!({ "id": -1, "type": "supergroup", "title": "group" });
+        let channel = serde_json::json!({ "id": -100, "type": "channel", "title": "channel" });
+
+        // 私聊和群组中回复原消息
+        let msg = message(chat_json(), None);
+        assert_eq!(chat_context(&msg), ChatContext::Private);
+        assert_eq!(reply_target(&msg), Some(MessageId(7)));
+
+        let msg = message(group.clone(), None);
+        assert_eq!(chat_context(&msg), ChatContext::Group);
+        assert_eq!(reply_target(&msg), Some(MessageId(7)));
+
+        // 匿名管理员的消息仍然可以回复
+        let msg = message(group.clone(), Some(group));
+        assert_eq!(chat_context(&msg), ChatContext::AnonymousAdmin);
+        assert_eq!(reply_target(&msg), Some(MessageId(7)));
+
+        // 频道中不引用原消息
+        let msg = message(channel.clone(), Some(channel));
+        assert_eq!(chat_context(&msg), ChatContext::Channel);
+        assert_eq!(reply_target(&msg), None);
+    }
+
+    #[test]
+    fn test_reply_target_text() {
+        // 被回复消息的文本
+        let msg = message_from_json(serde_json::json!({
+            "message_id": 2,
+            "date": 0,
+            "chat": chat_json(),
+            "text": "/process",
+            "reply_to_message": {
+                "message_id": 1,
+                "date": 0,
+                "chat": chat_json(),
+                "text": "https://x.com/user/status/123"
+            }
+        }));
+        assert_eq!(
+            commands::reply_target_text(&msg),
+            Some("https://x.com/user/status/123")
+        );
+
+        // 被回复消息只有说明文字
+        let msg = message_from_json(serde_json::json!({
+            "message_id": 2,
+            "date": 0,
+            "chat": chat_json(),
+            "text": "/process",
+            "reply_to_message": {
+                "message_id": 1,
+                "date": 0,
+                "chat": chat_json(),
+                "caption": "https://www.pixiv.net/artworks/456",
+                "photo": [{
+                    "file_id": "id",
+                    "file_unique_id": "uid",
+                    "width": 1,
+                    "height": 1
+                }]
+            }
+        }));
+        assert_eq!(
+            commands::reply_target_text(&msg),
+            Some("https://www.pixiv.net/artworks/456")
+        );
+
+        // 没有回复消息
+        let msg = message_from_json(serde_json::json!({
+            "message_id": 2,
+            "date": 0,
+            "chat": chat_json(),
+            "text": "/process"
+        }));
+        assert_eq!(commands::reply_target_text(&msg), None);
+    }
+
+    #[test]
+    fn test_should_process_in_chat() {
+        // 私聊总是处理
+        assert!(should_process_in_chat(false, true, false, false));
+        assert!(should_process_in_chat(false, false, false, false));
+
+        // 未开启时群组总是处理
+        assert!(should_process_in_chat(true, false, false, false));
+
+        // 开启后群组中需要提及或回复机器人
+        assert!(!should_process_in_chat(true, true, false, false));
+        assert!(should_process_in_chat(true, true, true, false));
+        assert!(should_process_in_chat(true, true, false, true));
+    }
+
+    #[test]
+    fn test_should_skip_forward() {
+        let original = message_from_json(serde_json::json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": chat_json(),
+            "text": "https://x.com/a/status/1"
+        }));
+        let forwarded = message_from_json(serde_json::json!({
+            "message_id": 2,
+            "date": 0,
+            "chat": chat_json(),
+            "text": "https://x.com/a/status/1",
+            "forward_origin": {
+                "type": "hidden_user",
+                "sender_user_name": "someone",
+                "date": 0
+            }
+        }));
+
+        // 开启后只跳过转发的消息
+        assert!(should_skip_forward(true, &forwarded));
+        assert!(!should_skip_forward(true, &original));
+        // 未开启时都处理
+        assert!(!should_skip_forward(false, &forwarded));
+        assert!(!should_skip_forward(false, &original));
+    }
+
+    #[test]
+    fn test_bot_mention_and_reply() {
+        let me: Me = serde_json::from_value(serde_json::json!({
+            "id": 42,
+            "is_bot": true,
+            "first_name": "bot",
+            "username": "TestBot",
+            "can_join_groups": true,
+            "can_read_all_group_messages": false,
+            "supports_inline_queries": false,
+            "can_connect_to_business": false,
+            "has_main_web_app": false
+        }))
+        .expect("应该是有效的机器人信息");
+
+        assert!(is_bot_mentioned("@testbot https://x.com/a/status/1", &me));
+        assert!(is_bot_mentioned("看看 @TestBot", &me));
+        assert!(!is_bot_mentioned("https://x.com/a/status/1", &me));
+        assert!(is_bot_mentioned("@testbot，看看这个", &me));
+        assert!(is_bot_mentioned("@testbot2 @testbot", &me));
+
+        // 用户名前缀相同的其他账号不算提及
+        assert!(!is_bot_mentioned("@testbot2 https://x.com/a/status/1", &me));
+        assert!(!is_bot_mentioned("@testbot_fan 看看", &me));
+        assert!(!is_bot_mentioned("mail@testbot.dev", &me));
+
+        let group_chat = serde_json::json!({ "id": -1, "type": "supergroup", "title": "group" });
+        let user = |id: u64| serde_json::json!({ "id": id, "is_bot": false, "first_name": "u" });
+        let msg = message_from_json(serde_json::json!({
+            "message_id": 2,
+            "date": 0,
+            "chat": group_chat,
+            "from": user(1),
+            "text": "https://x.com/a/status/1",
+            "reply_to_message": {
+                "message_id": 1,
+                "date": 0,
+                "chat": group_chat,
+                "from": { "id": 42, "is_bot": true, "first_name": "bot" },
+                "text": "hello"
+            }
+        }));
+        assert!(is_reply_to_bot(&msg, &me));
+
+        let msg = message_from_json(serde_json::json!({
+            "message_id": 2,
+            "date": 0,
+            "chat": group_chat,
+            "from": user(1),
+            "text": "https://x.com/a/status/1",
+            "reply_to_message": {
+                "message_id": 1,
+                "date": 0,
+                "chat": group_chat,
+                "from": user(2),
+                "text": "hello"
+            }
+        }));
+        assert!(!is_reply_to_bot(&msg, &me));
+    }
+
+    #[test]
+    fn test_clean_gif_caption() {
+        let cases = [
+            // 去除 Bot 提及，不区分大小写
+            ("@testbot 好耶", Some("好耶")),
+            ("好耶 @TestBot", Some("好耶")),
+            // 更长的用户名不受影响
+            ("@TestBot_2 好耶", Some("@TestBot_2 好耶")),
+            ("@TestBot_2 @testbot 好耶", Some("@TestBot_2  好耶")),
+            // 去除末尾的话题标签
+            ("好耶 #gif #猫猫", Some("好耶")),
+            ("好耶\n#gif\n#猫猫", Some("好耶")),
+            // 中间的话题标签保留
+            ("#猫猫 好耶", Some("#猫猫 好耶")),
+            // 去除空行和首尾空白
+            ("  第一行 \n\n 第二行  ", Some("第一行\n第二行")),
+            // 清理后为空
+            ("@TestBot #gif", None),
+            ("   ", None),
+        ];
+        for (caption, expected) in cases {
+            assert_eq!(
+                clean_gif_caption(caption, "TestBot").as_deref(),
+                expected,
+                "caption: {:?}",
+                caption
+            );
+        }
+    }
+
+    /// 用于测试的下载器，只返回预设的链接内容，其他链接下载失败
+    struct MockDownloader {
+        files: std::collections::HashMap<&'static str, (&'static [u8], &'static str)>,
+    }
+
+    impl MockDownloader {
+        fn new(files: &[(&'static str, &'static [u8], &'static str)]) -> Self {
+            Self {
+                files: files
+                    .iter()
+                    .map(|(url, bytes, content_type)| (*url, (*bytes, *content_type)))
+                    .collect(),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl crate::download::Downloader for MockDownloader {
+        async fn download(
+            &self,
+            url: &str,
+            _budget: &common::AttemptBudget,
+        ) -> crate::download::DownloadResult {
+            match self.files.get(url) {
+                Some((bytes, content_type)) => Ok((bytes.to_vec(), content_type.to_string(), None)),
+                None => Err(anyhow::anyhow!("not found: {}", url)),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_single_media() {
+        use crate::download::download_single_media;
+
+        let budget = common::AttemptBudget::default();
+        let downloader = MockDownloader::new(&[
+            (
+                "https://example.com/a.mp4",
+                b"video",
+                "application/octet-stream",
+            ),
+            (
+                "https://i.pximg.net/img-original/1.png",
+                b"original",
+                "video/mp4",
+            ),
+            ("https://example.com/bad.png", b"not an image", "image/png"),
+        ]);
+
+        // octet-stream 时从链接推断类型
+        let (bytes, content_type, _, download_url) =
+            download_single_media(&downloader, "https://example.com/a.mp4", None, &budget)
+                .await
+                .unwrap();
+        assert_eq!(bytes, b"video");
+        assert_eq!(content_type, "video/mp4");
+        assert_eq!(download_url, "https://example.com/a.mp4");
+
+        // Pixiv 链接使用原始链接下载
+        let original_urls = vec!["https://i.pximg.net/img-original/1.png".to_string()];
+        let (bytes, _, _, download_url) = download_single_media(
+            &downloader,
+            "https://i.pximg.net/c/600x1200/1.png",
+            Some(&original_urls),
+            &budget,
+        )
+        .await
+        .unwrap();
+        assert_eq!(bytes, b"original");
+        assert_eq!(download_url, "https://i.pximg.net/img-original/1.png");
+
+        // 下载失败
+        let err = download_single_media(
+            &downloader,
+            "https://example.com/missing.mp4",
+            None,
+            &budget,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Failed to download"));
+        // 图片尺寸无效
+        let err = download_single_media(&downloader, "https://example.com/bad.png", None, &budget)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid dimensions"));
+    }
+
+    #[tokio::test]
+    async fn test_download_media_files() {
+        use crate::download::{download_media_files, partial_download_caption};
+
+        let budget = common::AttemptBudget::default();
+        // 100x12000 的长图 PNG 头部
+        const LONG_PNG: &[u8] = &[
+            0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x2E, 0xE0, 0x08, 0x02, 0x00, 0x00,
+            0x00,
+        ];
+        let downloader = MockDownloader::new(&[
+            ("https://example.com/1.mp4", b"one", "video/mp4"),
+            ("https://example.com/2.mp4", b"two", "video/mp4"),
+            ("https://example.com/bad.png", b"not an image", "image/png"),
+            ("https://example.com/long.png", LONG_PNG, "image/png"),
+        ]);
+
+        // 全部下载成功
+        let urls = vec![
+            "https://example.com/1.mp4".to_string(),
+            "https://example.com/2.mp4".to_string(),
+        ];
+        let files = download_media_files(&downloader, &urls, None, false, &budget).await;
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, b"one");
+        assert_eq!(files[1].3, "https://example.com/2.mp4");
+        assert_eq!(
+            partial_download_caption("caption".to_string(), files.len(), urls.len()),
+            "caption"
+        );
+
+        // 部分失败：下载失败和尺寸无效的文件被跳过
+        let urls = vec![
+            "https://example.com/1.mp4".to_string(),
+            "https://example.com/missing.mp4".to_string(),
+            "https://example.com/bad.png".to_string(),
+        ];
+        let files = download_media_files(&downloader, &urls, None, false, &budget).await;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].3, "https://example.com/1.mp4");
+        assert_eq!(
+            partial_download_caption("caption".to_string(), files.len(), urls.len()),
+            "caption\n[1/3 Media Downloaded]"
+        );
+
+        // 保留超出尺寸的图片，无法解析的图片仍被跳过
+        let urls = vec![
+            "https://example.com/long.png".to_string(),
+            "https://example.com/bad.png".to_string(),
+        ];
+        let files = download_media_files(&downloader, &urls, None, true, &budget).await;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].3, "https://example.com/long.png");
+        assert!(
+            download_media_files(&downloader, &urls, None, false, &budget)
+                .await
+                .is_empty()
+        );
+
+        // 全部失败
+        let urls = vec!["https://example.com/missing.mp4".to_string()];
+        assert!(
+            download_media_files(&downloader, &urls, None, false, &budget)
+                .await
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_file_id_cache() {
+        use crate::bot::SendMethod;
+        use crate::file_cache::FileIdCache;
+        use teloxide::types::FileId;
+
+        let file_id = |id: &str| FileId(id.to_string());
+        let mut cache = FileIdCache::new(2);
+        assert_eq!(cache.get("a"), None);
+
+        cache.insert("a", file_id("1"), SendMethod::Photo);
+        cache.insert("b", file_id("2"), SendMethod::Animation);
+        assert_eq!(cache.get("a"), Some((file_id("1"), SendMethod::Photo)));
+
+        // 覆盖已有条目不改变淘汰顺序
+        cache.insert("a", file_id("3"), SendMethod::Photo);
+        assert_eq!(cache.get("a"), Some((file_id("3"), SendMethod::Photo)));
+
+        // 超出容量时淘汰最早写入的条目
+        cache.insert("c", file_id("4"), SendMethod::Video);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some((file_id("2"), SendMethod::Animation)));
+        assert_eq!(cache.get("c"), Some((file_id("4"), SendMethod::Video)));
+
+        assert_eq!(
+            cache.remove("b"),
+            Some((file_id("2"), SendMethod::Animation))
+        );
+        assert_eq!(cache.remove("b"), None);
+
+        let mut cache = FileIdCache::new(0);
+        cache.insert("a", file_id("1"), SendMethod::Photo);
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[test]
+    fn test_file_id_send_path() {
+        use crate::bot::SendMethod;
+        use crate::file_cache::{
+            cached_file_id, invalidate_file_id, remember_file_id, sent_file_id,
+        };
+        use teloxide::types::FileId;
+
+        let photo = |file_id: &str, size: u32| {
+            serde_json::json!({
+                "file_id": file_id,
+                "file_unique_id": file_id,
+                "width": size,
+                "height": size
+            })
+        };
+        let msg = message_from_json(serde_json::json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": chat_json(),
+            "photo": [photo("small", 90), photo("large", 1280)]
+        }));
+        // 图片使用最大尺寸的 file_id
+        let expected = (FileId("large".to_string()), SendMethod::Photo);
+        assert_eq!(sent_file_id(&msg), Some(expected.clone()));
+
+        // 发送成功后再次发送同一链接时使用 file_id，失败后移除
+        let url = "https://example.com/test_file_id_send_path.jpg";
+        assert_eq!(cached_file_id(url), None);
+        remember_file_id(url, &msg);
+        assert_eq!(cached_file_id(url), Some(expected));
+        invalidate_file_id(url);
+        assert_eq!(cached_file_id(url), None);
+
+        // 没有媒体的消息不缓存
+        let text_msg = message_from_json(serde_json::json!({
+            "message_id": 2,
+            "date": 0,
+            "chat": chat_json(),
+            "text": "hello"
+        }));
+        assert_eq!(sent_file_id(&text_msg), None);
+        remember_file_id(url, &text_msg);
+        assert_eq!(cached_file_id(url), None);
+    }
+
+    #[test]
+    fn test_i18n_lookup() {
+        use i18n::{Lang, LangStore, Text, t, tf};
+
+        assert_eq!(Lang::from_code("en"), Some(Lang::En));
+        assert_eq!(Lang::from_code(" ZH "), Some(Lang::Zh));
+        assert_eq!(Lang::from_code("fr"), None);
+
+        assert_eq!(t(Lang::Zh, Text::InvalidUrl), "无效的URL格式。");
+        assert_eq!(t(Lang::En, Text::InvalidUrl), "Invalid URL format.");
+        assert_eq!(
+            tf(Lang::En, Text::DownloadSummary, &[&1, &2, &"details"]),
+            "1/2 files failed:\n\ndetails"
+        );
+        assert_eq!(
+            tf(Lang::Zh, Text::CacheEntry, &[&"BiliBili", &3]),
+            "BiliBili: 3 条"
+        );
+
+        // 未设置的聊天使用默认语言
+        let store = LangStore::default();
+        let chat_id = teloxide::types::ChatId(1);
+        assert_eq!(store.get(chat_id), Lang::Zh);
+        store.set(chat_id, Lang::En);
+        assert_eq!(store.get(chat_id), Lang::En);
+        assert_eq!(store.get(teloxide::types::ChatId(2)), Lang::Zh);
+
+        // 英文报告
+        let results = vec![
+            ("https://a.com/1.jpg", Err("e1".to_string())),
+            ("https://b.com/2.jpg", Ok(())),
+        ];
+        let report = commands::summarize_download_results(&results, Lang::En).unwrap();
+        assert!(report.starts_with("1/2 files failed"));
+
+        // 参数中的 `{}` 不会被替换
+        assert_eq!(
+            tf(Lang::Zh, Text::UnknownProcessor, &[&"{}", &"A, B"]),
+            "未知的处理器：{}\n可用的处理器：A, B"
+        );
+        let err = commands::parse_download_args("https://a.com/1 --as=gif", Lang::En).unwrap_err();
+        assert!(err.starts_with("Invalid send method: gif\nUsage: /download"));
+        let err = commands::check_media_type(
+            Some(common::MediaKind::Audio),
+            "video/mp4",
+            "https://a.com/file",
+            Lang::En,
+        )
+        .unwrap_err();
+        assert_eq!(err, "File type mismatch: expected audio, got video/mp4");
+    }
 }